@@ -1,21 +1,115 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::account::{base::AccountBase, offline_account::OfflineAccount};
+use crate::account::{
+    base::AccountBase,
+    credential::AccountSecrets,
+    microsoft_account::{MicrosoftAccount, MicrosoftEndpoints},
+    offline_account::OfflineAccount,
+    yggdrasil_account::YggdrasilAccount,
+};
 
 pub mod base;
+pub mod credential;
+pub mod local_yggdrasil;
+pub mod microsoft_account;
 pub mod offline_account;
+pub mod skin;
+pub mod validator;
+pub mod yggdrasil_account;
+
+/// 账号的登录状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Valid,
+    /// access token 即将过期或已过期，可以用 refresh token 刷新
+    Expiring,
+    /// 无法刷新，需要重新登录
+    NeedsLogin,
+}
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum Account {
     Offline(OfflineAccount),
+    Microsoft(MicrosoftAccount),
+    Yggdrasil(YggdrasilAccount),
 }
 
 impl Account {
     pub fn get_type(&self) -> &str {
         match self {
             Account::Offline(_) => OfflineAccount::KIND,
+            Account::Microsoft(_) => MicrosoftAccount::KIND,
+            Account::Yggdrasil(_) => YggdrasilAccount::KIND,
+        }
+    }
+
+    /// 账号的唯一标识，外置登录切换角色时 uuid 会改变，因此使用服务器和登录名
+    pub fn get_id(&self) -> String {
+        match self {
+            Account::Offline(account) => account.uuid.clone(),
+            Account::Microsoft(account) => account.uuid.clone(),
+            Account::Yggdrasil(account) => format!("{}@{}", account.login, account.api_root),
+        }
+    }
+
+    pub fn get_status(&self) -> AccountStatus {
+        match self {
+            Account::Offline(_) => AccountStatus::Valid,
+            Account::Microsoft(account) => account.get_status(),
+            Account::Yggdrasil(account) => account.get_status(),
+        }
+    }
+
+    /// 启动游戏前调用，必要时刷新 access token，返回账号是否有变化
+    ///
+    /// 返回 [`microsoft_account::LoginExpired`] 时需要重新登录
+    pub fn refresh_before_launch(&mut self, endpoints: &MicrosoftEndpoints) -> Result<bool> {
+        match self {
+            Account::Offline(_) => Ok(false),
+            Account::Microsoft(account) => account.refresh_if_needed(endpoints),
+            Account::Yggdrasil(account) => account.refresh_if_needed(),
         }
     }
+
+    /// 需要单独保存的凭据，离线账号没有凭据
+    pub fn get_secrets(&self) -> Option<AccountSecrets> {
+        match self {
+            Account::Offline(_) => None,
+            Account::Microsoft(account) => Some(AccountSecrets {
+                access_token: account.access_token.clone(),
+                refresh_token: account.refresh_token.clone(),
+            }),
+            Account::Yggdrasil(account) => Some(AccountSecrets {
+                access_token: account.access_token.clone(),
+                refresh_token: String::new(),
+            }),
+        }
+    }
+
+    pub fn set_secrets(&mut self, secrets: AccountSecrets) {
+        match self {
+            Account::Offline(_) => {}
+            Account::Microsoft(account) => {
+                account.access_token = secrets.access_token;
+                account.refresh_token = secrets.refresh_token;
+            }
+            Account::Yggdrasil(account) => account.access_token = secrets.access_token,
+        }
+    }
+
+    /// 迁移旧版本保存的账号，返回是否有修改
+    pub fn migrate(&mut self) -> bool {
+        match self {
+            Account::Offline(account) => account.migrate(),
+            _ => false,
+        }
+    }
+
+    /// 是否为同一个账号
+    pub fn same_as(&self, other: &Account) -> bool {
+        self.get_type() == other.get_type() && self.get_id() == other.get_id()
+    }
 }
 
 impl AccountBase for Account {
@@ -23,21 +117,29 @@ impl AccountBase for Account {
     fn get_username(&self) -> &str {
         match self {
             Account::Offline(account) => account.get_username(),
+            Account::Microsoft(account) => account.get_username(),
+            Account::Yggdrasil(account) => account.get_username(),
         }
     }
     fn get_access_token(&self) -> &str {
         match self {
             Account::Offline(account) => account.get_access_token(),
+            Account::Microsoft(account) => account.get_access_token(),
+            Account::Yggdrasil(account) => account.get_access_token(),
         }
     }
     fn get_user_type(&self) -> &str {
         match self {
             Account::Offline(account) => account.get_user_type(),
+            Account::Microsoft(account) => account.get_user_type(),
+            Account::Yggdrasil(account) => account.get_user_type(),
         }
     }
     fn get_uuid(&self) -> &str {
         match self {
             Account::Offline(account) => account.get_uuid(),
+            Account::Microsoft(account) => account.get_uuid(),
+            Account::Yggdrasil(account) => account.get_uuid(),
         }
     }
 }
@@ -0,0 +1,436 @@
+//! 账号凭据（access token / refresh token）的存储
+//!
+//! 优先使用系统密钥环（Secret Service / Keychain / Credential Manager），
+//! 不可用时使用口令加密的文件，设置文件中只保存不敏感的账号信息
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use chacha20poly1305::{
+    XChaCha20Poly1305, XNonce,
+    aead::{Aead, KeyInit},
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::account::Account;
+
+/// 密钥环中的服务名
+pub const KEYRING_SERVICE: &str = "mctui";
+
+const FILE_MAGIC: &[u8; 6] = b"MCTC\x00\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// 单个账号的敏感信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountSecrets {
+    #[serde(default)]
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+}
+
+impl AccountSecrets {
+    pub fn is_empty(&self) -> bool {
+        self.access_token.is_empty() && self.refresh_token.is_empty()
+    }
+}
+
+/// 凭据存储的位置
+pub enum CredentialStore {
+    /// 系统密钥环
+    Keyring,
+    /// 口令加密的文件
+    File(EncryptedFile),
+    /// 只保存在内存中，退出后需要重新登录
+    Memory,
+    /// 需要口令才能使用的加密文件：还没有输入口令，或口令错误、文件损坏
+    ///
+    /// 解锁之前不会写入凭据，避免用错误的口令覆盖原来的文件
+    Locked(PathBuf),
+}
+
+impl CredentialStore {
+    /// 密钥环可用时使用密钥环，否则有口令时使用加密文件，没有口令时需要解锁
+    pub fn open(file: &Path, passphrase: Option<&str>) -> Self {
+        if keyring_available() {
+            info!(target: "account", "credentials are stored in the system keyring");
+            return CredentialStore::Keyring;
+        }
+        match passphrase {
+            Some(passphrase) if !passphrase.is_empty() => {
+                info!(target: "account", "credentials are stored in {:?}", file);
+                CredentialStore::File(EncryptedFile::new(file, passphrase))
+            }
+            _ => {
+                warn!(target: "account", "no keyring or passphrase, credentials file is locked");
+                CredentialStore::Locked(file.to_path_buf())
+            }
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        matches!(self, CredentialStore::Locked(_))
+    }
+
+    /// 用口令解锁加密文件并读取凭据，口令错误时保持锁定
+    pub fn unlock<'a>(
+        &mut self,
+        passphrase: &str,
+        accounts: impl IntoIterator<Item = &'a mut Account>,
+    ) -> Result<()> {
+        let CredentialStore::Locked(path) = self else {
+            bail!("credential store is not locked");
+        };
+        if passphrase.is_empty() {
+            bail!("passphrase is empty");
+        }
+        let store = CredentialStore::File(EncryptedFile::new(path, passphrase));
+        store.load_accounts(accounts)?;
+        info!(target: "account", "credentials are stored in {:?}", path);
+        *self = store;
+        Ok(())
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            CredentialStore::Keyring => "keyring",
+            CredentialStore::File(_) => "file",
+            CredentialStore::Memory => "memory",
+            CredentialStore::Locked(_) => "locked",
+        }
+    }
+
+    /// 读取账号的凭据并填入账号
+    pub fn load_accounts<'a>(&self, accounts: impl IntoIterator<Item = &'a mut Account>) -> Result<()> {
+        let entries = match self {
+            CredentialStore::File(file) => file.read()?,
+            _ => BTreeMap::new(),
+        };
+        for account in accounts {
+            if account.get_secrets().is_none() {
+                continue;
+            }
+            let key = credential_key(account);
+            let secrets = match self {
+                CredentialStore::Keyring => keyring_get(&key)?,
+                CredentialStore::File(_) => entries.get(&key).cloned(),
+                CredentialStore::Memory | CredentialStore::Locked(_) => None,
+            };
+            // 没有保存过凭据时保留设置文件中旧版本留下的 token
+            if let Some(secrets) = secrets {
+                account.set_secrets(secrets);
+            }
+        }
+        Ok(())
+    }
+
+    /// 保存账号的凭据，并删除已不存在的账号的凭据
+    pub fn save_accounts<'a>(&self, accounts: impl IntoIterator<Item = &'a Account>) -> Result<()> {
+        let entries: BTreeMap<String, AccountSecrets> = accounts
+            .into_iter()
+            .filter_map(|account| Some((credential_key(account), account.get_secrets()?)))
+            .filter(|(_, secrets)| !secrets.is_empty())
+            .collect();
+        match self {
+            CredentialStore::Keyring => {
+                for (key, secrets) in &entries {
+                    keyring_set(key, secrets)?;
+                }
+                // 密钥环无法列出条目，用索引记录保存过哪些账号
+                let old_keys = keyring_get_index()?;
+                for key in old_keys.iter().filter(|key| !entries.contains_key(*key)) {
+                    keyring_delete(key)?;
+                }
+                keyring_set_index(&entries.keys().cloned().collect::<Vec<_>>())
+            }
+            CredentialStore::File(file) => file.write(&entries),
+            CredentialStore::Memory => Ok(()),
+            CredentialStore::Locked(path) => {
+                warn!(target: "account", "credentials file is locked, not saving {:?}", path);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 凭据的键，类型加账号标识
+pub fn credential_key(account: &Account) -> String {
+    format!("{}:{}", account.get_type(), account.get_id())
+}
+
+fn keyring_entry(key: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, key).context("failed to open keyring entry")
+}
+
+/// 尝试读取一个条目，判断密钥环是否可用
+fn keyring_available() -> bool {
+    let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, "index") else {
+        return false;
+    };
+    match entry.get_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => true,
+        Err(e) => {
+            warn!(target: "account", "keyring is not available: {}", e);
+            false
+        }
+    }
+}
+
+fn keyring_get(key: &str) -> Result<Option<AccountSecrets>> {
+    match keyring_entry(key)?.get_password() {
+        Ok(text) => Ok(Some(
+            serde_json::from_str(&text).context("failed to parse credentials in keyring")?,
+        )),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!(e).context("failed to read keyring")),
+    }
+}
+
+fn keyring_set(key: &str, secrets: &AccountSecrets) -> Result<()> {
+    let text = serde_json::to_string(secrets)?;
+    keyring_entry(key)?
+        .set_password(&text)
+        .context("failed to write keyring")
+}
+
+fn keyring_delete(key: &str) -> Result<()> {
+    match keyring_entry(key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!(e).context("failed to delete keyring entry")),
+    }
+}
+
+fn keyring_get_index() -> Result<Vec<String>> {
+    match keyring_entry("index")?.get_password() {
+        Ok(text) => Ok(serde_json::from_str(&text).unwrap_or_default()),
+        Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+        Err(e) => Err(anyhow!(e).context("failed to read keyring")),
+    }
+}
+
+fn keyring_set_index(keys: &[String]) -> Result<()> {
+    keyring_entry("index")?
+        .set_password(&serde_json::to_string(keys)?)
+        .context("failed to write keyring")
+}
+
+/// 口令加密的凭据文件
+///
+/// 格式：magic + salt + nonce + 密文，密钥由 Argon2id 从口令派生，使用 XChaCha20-Poly1305 加密
+pub struct EncryptedFile {
+    path: PathBuf,
+    passphrase: String,
+}
+
+impl EncryptedFile {
+    pub fn new(path: &Path, passphrase: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            passphrase: passphrase.to_string(),
+        }
+    }
+
+    pub fn read(&self) -> Result<BTreeMap<String, AccountSecrets>> {
+        if !self.path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        let data = fs::read(&self.path).context(format!("failed to read {:?}", self.path))?;
+        let plain = decrypt(&data, &self.passphrase)?;
+        serde_json::from_slice(&plain).context("failed to parse credentials file")
+    }
+
+    pub fn write(&self, entries: &BTreeMap<String, AccountSecrets>) -> Result<()> {
+        let plain = serde_json::to_vec(entries)?;
+        let data = encrypt(&plain, &self.passphrase)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+        }
+        // 先写临时文件再替换，避免写到一半时丢失凭据
+        let temp = self.path.with_extension("tmp");
+        let mut file = private_file(&temp)?;
+        file.write_all(&data)
+            .context(format!("failed to write {:?}", temp))?;
+        file.sync_all()?;
+        fs::rename(&temp, &self.path).context(format!("failed to write {:?}", self.path))
+    }
+}
+
+/// 创建只有当前用户可以读写的文件
+fn private_file(path: &Path) -> Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path).context(format!("failed to create {:?}", path))?;
+        // 文件已存在时 mode 不生效
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path).context(format!("failed to create {:?}", path))
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+pub fn encrypt(plain: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut salt).map_err(|e| anyhow!("failed to generate salt: {}", e))?;
+    getrandom::fill(&mut nonce).map_err(|e| anyhow!("failed to generate nonce: {}", e))?;
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?.into());
+    let encrypted = cipher
+        .encrypt(XNonce::from_slice(&nonce), plain)
+        .map_err(|_| anyhow!("failed to encrypt credentials"))?;
+
+    let mut data = Vec::with_capacity(FILE_MAGIC.len() + SALT_LEN + NONCE_LEN + encrypted.len());
+    data.extend_from_slice(FILE_MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&encrypted);
+    Ok(data)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let Some(rest) = data.strip_prefix(FILE_MAGIC.as_slice()) else {
+        bail!("not a credentials file");
+    };
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        bail!("credentials file is truncated");
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, salt)?.into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce), encrypted)
+        .map_err(|_| anyhow!("wrong passphrase or corrupted credentials file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::microsoft_account::MicrosoftAccount;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mctui-credential-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("credentials.bin")
+    }
+
+    fn secrets(token: &str) -> AccountSecrets {
+        AccountSecrets { access_token: token.to_string(), refresh_token: format!("{}-refresh", token) }
+    }
+
+    fn account(uuid: &str) -> Account {
+        Account::Microsoft(MicrosoftAccount {
+            username: "Steve".to_string(),
+            uuid: uuid.to_string(),
+            access_token: String::new(),
+            refresh_token: String::new(),
+            expires_at: 0,
+        })
+    }
+
+    #[test]
+    fn encrypt_round_trip() {
+        let data = encrypt(b"secret", "passphrase").unwrap();
+        assert!(data.starts_with(FILE_MAGIC));
+        assert_eq!(decrypt(&data, "passphrase").unwrap(), b"secret");
+        // 每次的 salt 和 nonce 都不同
+        assert_ne!(encrypt(b"secret", "passphrase").unwrap(), data);
+
+        let e = decrypt(&data, "wrong").unwrap_err();
+        assert_eq!(e.to_string(), "wrong passphrase or corrupted credentials file");
+    }
+
+    #[test]
+    fn reject_corrupted_data() {
+        let data = encrypt(b"secret", "passphrase").unwrap();
+        let mut corrupted = data.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&corrupted, "passphrase").is_err());
+        // 截断到密文中间
+        assert!(decrypt(&data[..data.len() - 1], "passphrase").is_err());
+
+        let header = FILE_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        let e = decrypt(&data[..header - 1], "passphrase").unwrap_err();
+        assert_eq!(e.to_string(), "credentials file is truncated");
+        let e = decrypt(&data[..3], "passphrase").unwrap_err();
+        assert_eq!(e.to_string(), "not a credentials file");
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        let e = decrypt(&bad_magic, "passphrase").unwrap_err();
+        assert_eq!(e.to_string(), "not a credentials file");
+    }
+
+    #[test]
+    fn encrypted_file_round_trip() {
+        let path = temp_file("file");
+        let file = EncryptedFile::new(&path, "passphrase");
+        assert!(file.read().unwrap().is_empty());
+
+        let entries = BTreeMap::from([("microsoft:a".to_string(), secrets("a"))]);
+        file.write(&entries).unwrap();
+        assert_eq!(file.read().unwrap(), entries);
+        assert!(!path.with_extension("tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let e = EncryptedFile::new(&path, "wrong").read().unwrap_err();
+        assert!(format!("{:#}", e).contains("wrong passphrase"));
+        fs::write(&path, b"garbage").unwrap();
+        assert!(file.read().is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn locked_store() {
+        let path = temp_file("locked");
+        let mut saved = account("a");
+        saved.set_secrets(secrets("saved"));
+        CredentialStore::File(EncryptedFile::new(&path, "passphrase"))
+            .save_accounts([&saved])
+            .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        let mut store = CredentialStore::Locked(path.clone());
+        assert!(store.is_locked());
+        // 锁定时不读取也不覆盖文件
+        let mut accounts = [account("a")];
+        store.load_accounts(accounts.iter_mut()).unwrap();
+        assert_eq!(accounts[0].get_secrets(), Some(AccountSecrets::default()));
+        store.save_accounts([&account("b")]).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        assert!(store.unlock("", accounts.iter_mut()).is_err());
+        assert!(store.unlock("wrong", accounts.iter_mut()).is_err());
+        assert!(store.is_locked());
+
+        store.unlock("passphrase", accounts.iter_mut()).unwrap();
+        assert_eq!(store.get_name(), "file");
+        assert_eq!(accounts[0].get_secrets(), Some(secrets("saved")));
+        assert!(store.unlock("passphrase", accounts.iter_mut()).is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
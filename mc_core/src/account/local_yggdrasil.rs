@@ -0,0 +1,722 @@
+//! 内置的本地外置登录服务器
+//!
+//! 只监听回环地址，为离线账号提供角色和本地皮肤，启动游戏时通过 authlib-injector 注入，
+//! 不需要任何外部服务就能看到各自的皮肤
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::{info, warn};
+use rsa::{
+    RsaPrivateKey,
+    pkcs1v15::SigningKey,
+    pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey, LineEnding},
+    rand_core::OsRng,
+    signature::{SignatureEncoding, Signer},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::account::{
+    microsoft_account::now_secs,
+    offline_account::OfflineAccount,
+    skin::{SkinImage, SkinVariant},
+};
+
+const KEY_BITS: usize = 2048;
+/// 请求头和请求体的最大长度
+const MAX_HEADER: usize = 64 * 1024;
+const MAX_BODY: usize = 1024 * 1024;
+
+/// 本地外置登录服务器，克隆的副本共享同一个服务器，全部 drop 后停止
+#[derive(Clone)]
+pub struct LocalYggdrasil {
+    state: Arc<ServerState>,
+    _listener: Arc<ListenerGuard>,
+}
+
+/// 监听线程的停止标志，drop 时停止监听
+struct ListenerGuard {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+struct ServerState {
+    api_root: String,
+    key: SigningKey<Sha1>,
+    public_key: String,
+    accounts: Mutex<Vec<OfflineAccount>>,
+    /// accessToken -> 角色 uuid
+    tokens: Mutex<HashMap<String, String>>,
+    /// serverId -> 加入服务器的角色 uuid
+    sessions: Mutex<HashMap<String, String>>,
+}
+
+impl LocalYggdrasil {
+    /// 在随机端口启动，签名密钥保存在 key_path，不存在时生成
+    pub fn start(key_path: &Path, accounts: Vec<OfflineAccount>) -> Result<Self> {
+        let private_key = load_or_create_key(key_path)?;
+        let public_key = private_key
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .context("failed to encode public key")?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .context("failed to start local yggdrasil server")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(ServerState {
+            api_root: format!("http://{}", addr),
+            key: SigningKey::new(private_key),
+            public_key,
+            accounts: Mutex::new(accounts),
+            tokens: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let server_state = state.clone();
+        let server_stop = stop.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let state = server_state.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &state) {
+                        warn!(target: "account", "local yggdrasil request failed: {:#}", e);
+                    }
+                });
+            }
+        });
+        info!(target: "account", "local yggdrasil server started at {}", state.api_root);
+        Ok(Self { state, _listener: Arc::new(ListenerGuard { addr, stop }) })
+    }
+
+    /// 传给 authlib-injector 的 API 地址
+    pub fn api_root(&self) -> &str {
+        &self.state.api_root
+    }
+
+    /// API 元数据，启动时预先传给 authlib-injector
+    pub fn metadata(&self) -> String {
+        self.state.metadata().to_string()
+    }
+
+    /// 更新提供的离线账号
+    pub fn set_accounts(&self, accounts: Vec<OfflineAccount>) {
+        *self.state.accounts.lock().unwrap() = accounts;
+    }
+}
+
+impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // 连接一次让监听线程从 accept 返回
+        let _ = TcpStream::connect(self.addr);
+        info!(target: "account", "local yggdrasil server stopped");
+    }
+}
+
+fn load_or_create_key(path: &Path) -> Result<RsaPrivateKey> {
+    if let Ok(pem) = fs::read_to_string(path) {
+        match RsaPrivateKey::from_pkcs8_pem(&pem) {
+            Ok(key) => return Ok(key),
+            Err(e) => warn!(target: "account", "invalid signature key {:?}, creating a new one: {}", path, e),
+        }
+    }
+    info!(target: "account", "creating signature key {:?}", path);
+    let key = RsaPrivateKey::new(&mut OsRng, KEY_BITS).context("failed to create signature key")?;
+    let pem = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .context("failed to encode signature key")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    fs::write(path, pem.as_bytes()).context(format!("failed to write {:?}", path))?;
+    Ok(key)
+}
+
+/// 去掉 uuid 中的连字符，协议中使用无符号 uuid
+fn unsigned_uuid(uuid: &str) -> String {
+    uuid.replace('-', "")
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    let _ = getrandom::fill(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(value: Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json; charset=utf-8",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: 204,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }
+    }
+
+    fn error(status: u16, error: &str, message: &str) -> Self {
+        Self {
+            status,
+            ..Self::json(json!({ "error": error, "errorMessage": message }))
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::error(404, "Not Found", "The requested resource is not found")
+    }
+
+    fn forbidden(message: &str) -> Self {
+        Self::error(403, "ForbiddenOperationException", message)
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &ServerState) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    // 限制总长度，避免一行过长的请求占满内存
+    let mut reader = BufReader::new(stream.try_clone()?.take((MAX_HEADER + MAX_BODY) as u64));
+    let request = read_request(&mut reader)?;
+    let response = state.route(&request);
+    write_response(stream, &response)
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("invalid request line: {:?}", line);
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.trim_end_matches('/').to_string();
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (url_decode(key), url_decode(value)))
+        .collect();
+
+    let mut content_length = 0;
+    let mut header_len = line.len();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        header_len += header.len();
+        if header_len > MAX_HEADER {
+            bail!("request header is too large");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().context("invalid content-length")?;
+        }
+    }
+    if content_length > MAX_BODY {
+        bail!("request body is too large");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request { method, path, query, body })
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        _ => "Not Found",
+    };
+    let header = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Deserialize)]
+struct AuthenticateRequest {
+    username: String,
+    #[serde(default, rename = "clientToken")]
+    client_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRequest {
+    access_token: String,
+    #[serde(default)]
+    client_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JoinRequest {
+    selected_profile: String,
+    server_id: String,
+}
+
+impl ServerState {
+    fn route(&self, request: &Request) -> Response {
+        let path = request.path.as_str();
+        match (request.method.as_str(), path) {
+            ("GET", "") => Response::json(self.metadata()),
+            ("POST", "/authserver/authenticate") => self.authenticate(&request.body),
+            ("POST", "/authserver/refresh") => self.refresh(&request.body),
+            ("POST", "/authserver/validate") => self.validate(&request.body),
+            ("POST", "/authserver/invalidate") | ("POST", "/authserver/signout") => {
+                Response::no_content()
+            }
+            ("POST", "/sessionserver/session/minecraft/join") => self.join(&request.body),
+            ("GET", "/sessionserver/session/minecraft/hasJoined") => self.has_joined(&request.query),
+            ("POST", "/api/profiles/minecraft") => self.profiles_by_name(&request.body),
+            // 1.19 以后游戏会请求的接口，返回默认值
+            ("GET", "/minecraftservices/player/attributes") => Response::json(json!({
+                "privileges": {
+                    "onlineChat": { "enabled": true },
+                    "multiplayerServer": { "enabled": true },
+                    "multiplayerRealms": { "enabled": false },
+                    "telemetry": { "enabled": false },
+                },
+                "profanityFilterPreferences": { "profanityFilterOn": false },
+            })),
+            ("GET", "/minecraftservices/privacy/blocklist") => {
+                Response::json(json!({ "blockedProfiles": [] }))
+            }
+            ("GET", _) => {
+                if let Some(uuid) = path.strip_prefix("/sessionserver/session/minecraft/profile/") {
+                    let unsigned = request.query.get("unsigned").is_none_or(|v| v != "false");
+                    match self.find_account(|account| unsigned_uuid(&account.uuid) == unsigned_uuid(uuid)) {
+                        Some(account) => Response::json(self.profile(&account, !unsigned)),
+                        None => Response::no_content(),
+                    }
+                } else if let Some(hash) = path.strip_prefix("/textures/") {
+                    self.texture(hash)
+                } else {
+                    Response::not_found()
+                }
+            }
+            _ => Response::not_found(),
+        }
+    }
+
+    fn metadata(&self) -> Value {
+        json!({
+            "meta": {
+                "serverName": "mctui",
+                "implementationName": "mctui",
+                "implementationVersion": env!("CARGO_PKG_VERSION"),
+                "feature.non_email_login": true,
+            },
+            "skinDomains": ["127.0.0.1", "localhost"],
+            "signaturePublickey": self.public_key,
+        })
+    }
+
+    fn find_account(&self, f: impl Fn(&OfflineAccount) -> bool) -> Option<OfflineAccount> {
+        self.accounts.lock().unwrap().iter().find(|account| f(account)).cloned()
+    }
+
+    /// 角色信息，signed 时 textures 属性带有签名
+    fn profile(&self, account: &OfflineAccount, signed: bool) -> Value {
+        let uuid = unsigned_uuid(&account.uuid);
+        let mut textures = json!({
+            "timestamp": now_secs() * 1000,
+            "profileId": uuid,
+            "profileName": account.username,
+            "textures": {},
+        });
+        if let Some((hash, variant)) = account.skin_path.as_deref().and_then(skin_texture) {
+            let mut skin = json!({ "url": format!("{}/textures/{}", self.api_root, hash) });
+            if variant == SkinVariant::Slim {
+                skin["metadata"] = json!({ "model": "slim" });
+            }
+            textures["textures"]["SKIN"] = skin;
+        }
+        let value = STANDARD.encode(textures.to_string());
+        let mut property = json!({ "name": "textures", "value": value });
+        if signed {
+            property["signature"] = json!(STANDARD.encode(self.key.sign(value.as_bytes()).to_bytes()));
+        }
+        json!({ "id": uuid, "name": account.username, "properties": [property] })
+    }
+
+    fn short_profile(account: &OfflineAccount) -> Value {
+        json!({ "id": unsigned_uuid(&account.uuid), "name": account.username })
+    }
+
+    /// 离线账号不需要密码，按用户名登录
+    fn authenticate(&self, body: &[u8]) -> Response {
+        let Ok(request) = serde_json::from_slice::<AuthenticateRequest>(body) else {
+            return Response::error(400, "IllegalArgumentException", "Invalid request");
+        };
+        let Some(account) =
+            self.find_account(|account| account.username.eq_ignore_ascii_case(&request.username))
+        else {
+            return Response::forbidden("Invalid credentials. Invalid username or password.");
+        };
+        let access_token = random_token();
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(access_token.clone(), account.uuid.clone());
+        let profile = Self::short_profile(&account);
+        Response::json(json!({
+            "accessToken": access_token,
+            "clientToken": request.client_token.unwrap_or_else(random_token),
+            "availableProfiles": [profile],
+            "selectedProfile": profile,
+        }))
+    }
+
+    fn refresh(&self, body: &[u8]) -> Response {
+        let Ok(request) = serde_json::from_slice::<TokenRequest>(body) else {
+            return Response::error(400, "IllegalArgumentException", "Invalid request");
+        };
+        let mut tokens = self.tokens.lock().unwrap();
+        let Some(uuid) = tokens.remove(&request.access_token) else {
+            return Response::forbidden("Invalid token.");
+        };
+        let Some(account) = self.find_account(|account| account.uuid == uuid) else {
+            return Response::forbidden("Invalid token.");
+        };
+        let access_token = random_token();
+        tokens.insert(access_token.clone(), uuid);
+        Response::json(json!({
+            "accessToken": access_token,
+            "clientToken": request.client_token.unwrap_or_else(random_token),
+            "selectedProfile": Self::short_profile(&account),
+        }))
+    }
+
+    fn validate(&self, body: &[u8]) -> Response {
+        match serde_json::from_slice::<TokenRequest>(body) {
+            Ok(request) if self.tokens.lock().unwrap().contains_key(&request.access_token) => {
+                Response::no_content()
+            }
+            _ => Response::forbidden("Invalid token."),
+        }
+    }
+
+    /// 启动器直接使用离线账号的令牌，因此只检查角色是否存在
+    fn join(&self, body: &[u8]) -> Response {
+        let Ok(request) = serde_json::from_slice::<JoinRequest>(body) else {
+            return Response::error(400, "IllegalArgumentException", "Invalid request");
+        };
+        let uuid = unsigned_uuid(&request.selected_profile);
+        if self
+            .find_account(|account| unsigned_uuid(&account.uuid) == uuid)
+            .is_none()
+        {
+            return Response::forbidden("Invalid token.");
+        }
+        self.sessions.lock().unwrap().insert(request.server_id, uuid);
+        Response::no_content()
+    }
+
+    fn has_joined(&self, query: &HashMap<String, String>) -> Response {
+        let (Some(username), Some(server_id)) = (query.get("username"), query.get("serverId")) else {
+            return Response::no_content();
+        };
+        let Some(uuid) = self.sessions.lock().unwrap().get(server_id).cloned() else {
+            return Response::no_content();
+        };
+        match self.find_account(|account| unsigned_uuid(&account.uuid) == uuid && account.username == *username) {
+            Some(account) => Response::json(self.profile(&account, true)),
+            None => Response::no_content(),
+        }
+    }
+
+    fn profiles_by_name(&self, body: &[u8]) -> Response {
+        let names: Vec<String> = serde_json::from_slice(body).unwrap_or_default();
+        let accounts = self.accounts.lock().unwrap();
+        let profiles: Vec<Value> = accounts
+            .iter()
+            .filter(|account| names.iter().any(|name| name.eq_ignore_ascii_case(&account.username)))
+            .map(Self::short_profile)
+            .collect();
+        Response::json(json!(profiles))
+    }
+
+    /// 按内容的 sha256 提供皮肤图片
+    fn texture(&self, hash: &str) -> Response {
+        let accounts = self.accounts.lock().unwrap();
+        for path in accounts.iter().filter_map(|account| account.skin_path.as_deref()) {
+            if let Ok(data) = fs::read(path)
+                && format!("{:x}", Sha256::digest(&data)) == hash
+            {
+                return Response {
+                    status: 200,
+                    content_type: "image/png",
+                    body: data,
+                };
+            }
+        }
+        Response::not_found()
+    }
+}
+
+/// 皮肤文件的 sha256 和模型，文件无效时为 None
+fn skin_texture(path: &Path) -> Option<(String, SkinVariant)> {
+    let data = fs::read(path).ok()?;
+    let variant = SkinImage::decode(&data).ok()?.guess_variant();
+    Some((format!("{:x}", Sha256::digest(&data)), variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use rsa::{
+        RsaPublicKey,
+        pkcs1v15::{Signature, VerifyingKey},
+        pkcs8::DecodePublicKey,
+        signature::Verifier,
+    };
+
+    use super::*;
+
+    #[test]
+    fn decode_url() {
+        assert_eq!(url_decode("a%20b+c"), "a b c");
+        assert_eq!(url_decode("%E4%B8%AD%e6%96%87"), "中文");
+        assert_eq!(url_decode("100%"), "100%");
+        assert_eq!(url_decode("%zz%4"), "%zz%4");
+        assert_eq!(url_decode(""), "");
+    }
+
+    #[test]
+    fn parse_request() {
+        let raw = "POST /authserver/authenticate/?a=1&b=x%20y HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody";
+        let request = read_request(&mut Cursor::new(raw)).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/authserver/authenticate");
+        assert_eq!(request.query["b"], "x y");
+        assert_eq!(request.body, b"body");
+
+        assert!(read_request(&mut Cursor::new("\r\n")).is_err());
+        assert!(read_request(&mut Cursor::new("GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n")).is_err());
+        // 请求体不完整
+        assert!(read_request(&mut Cursor::new("POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nab")).is_err());
+    }
+
+    #[test]
+    fn request_limits() {
+        let header = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(MAX_HEADER));
+        let e = read_request(&mut Cursor::new(header)).map(|_| ()).unwrap_err();
+        assert_eq!(e.to_string(), "request header is too large");
+
+        let mut many = "GET / HTTP/1.1\r\n".to_string();
+        for _ in 0..MAX_HEADER / 8 {
+            many.push_str("X: 1234\r\n");
+        }
+        many.push_str("\r\n");
+        assert!(read_request(&mut Cursor::new(many)).is_err());
+
+        let body = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        let e = read_request(&mut Cursor::new(body)).map(|_| ()).unwrap_err();
+        assert_eq!(e.to_string(), "request body is too large");
+        let body = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", MAX_BODY, "a".repeat(MAX_BODY));
+        assert_eq!(read_request(&mut Cursor::new(body)).unwrap().body.len(), MAX_BODY);
+    }
+
+    fn skin_png() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 64, 64);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255; 64 * 64 * 4]).unwrap();
+        writer.finish().unwrap();
+        data
+    }
+
+    fn post(url: &str, body: Value) -> attohttpc::Response {
+        attohttpc::post(url)
+            .header("Content-Type", "application/json")
+            .text(body.to_string())
+            .send()
+            .unwrap()
+    }
+
+    fn json_body(response: attohttpc::Response) -> Value {
+        serde_json::from_str(&response.text().unwrap()).unwrap()
+    }
+
+    /// 解码 textures 属性，签名存在时用元数据中的公钥验证
+    fn textures(profile: &Value, public_key: &str) -> Value {
+        let property = &profile["properties"][0];
+        let value = property["value"].as_str().unwrap();
+        if let Some(signature) = property["signature"].as_str() {
+            let key = VerifyingKey::<Sha1>::new(RsaPublicKey::from_public_key_pem(public_key).unwrap());
+            let signature = Signature::try_from(STANDARD.decode(signature).unwrap().as_slice()).unwrap();
+            key.verify(value.as_bytes(), &signature).unwrap();
+        }
+        serde_json::from_slice(&STANDARD.decode(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn serve_over_loopback() {
+        let dir = std::env::temp_dir().join(format!("mctui-local-yggdrasil-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let skin = skin_png();
+        let skin_path = dir.join("skin.png");
+        fs::write(&skin_path, &skin).unwrap();
+        let mut steve = OfflineAccount::new("Steve".to_string()).unwrap();
+        steve.skin_path = Some(PathBuf::from(&skin_path));
+        let alex = OfflineAccount::new("Alex".to_string()).unwrap();
+
+        let key_path = dir.join("key.pem");
+        let server = LocalYggdrasil::start(&key_path, vec![steve.clone()]).unwrap();
+        assert!(key_path.is_file());
+        let root = server.api_root().to_string();
+        assert!(root.starts_with("http://127.0.0.1:"));
+        let metadata: Value = serde_json::from_str(&server.metadata()).unwrap();
+        let public_key = metadata["signaturePublickey"].as_str().unwrap().to_string();
+        let fetched: Value = json_body(attohttpc::get(&root).send().unwrap());
+        assert_eq!(fetched, metadata);
+
+        // 登录、刷新、验证
+        let authenticate = format!("{}/authserver/authenticate", root);
+        let response = post(&authenticate, json!({ "username": "steve", "clientToken": "c" }));
+        assert!(response.is_success());
+        let auth = json_body(response);
+        assert_eq!(auth["clientToken"], "c");
+        assert_eq!(auth["selectedProfile"]["id"], unsigned_uuid(&steve.uuid));
+        let token = auth["accessToken"].as_str().unwrap().to_string();
+        let validate = format!("{}/authserver/validate", root);
+        assert_eq!(post(&validate, json!({ "accessToken": token })).status().as_u16(), 204);
+        let refresh = format!("{}/authserver/refresh", root);
+        let refreshed = json_body(post(&refresh, json!({ "accessToken": token })));
+        assert_ne!(refreshed["accessToken"], auth["accessToken"]);
+        assert_eq!(post(&validate, json!({ "accessToken": token })).status().as_u16(), 403);
+        let body = json!({ "accessToken": refreshed["accessToken"] });
+        assert_eq!(post(&validate, body).status().as_u16(), 204);
+        let response = post(&authenticate, json!({ "username": "Alex" }));
+        assert_eq!(response.status().as_u16(), 403);
+
+        // 带签名的角色信息和皮肤
+        let profile_url = format!("{}/sessionserver/session/minecraft/profile/{}", root, steve.uuid);
+        let unsigned = json_body(attohttpc::get(&profile_url).send().unwrap());
+        assert!(unsigned["properties"][0].get("signature").is_none());
+        let profile = json_body(attohttpc::get(format!("{}?unsigned=false", profile_url)).send().unwrap());
+        assert!(profile["properties"][0]["signature"].is_string());
+        let textures = textures(&profile, &public_key);
+        assert_eq!(textures["profileName"], "Steve");
+        let skin_url = textures["textures"]["SKIN"]["url"].as_str().unwrap();
+        let variant = SkinImage::decode(&skin).unwrap().guess_variant();
+        assert_eq!(textures["textures"]["SKIN"].get("metadata").is_some(), variant == SkinVariant::Slim);
+        assert_eq!(attohttpc::get(skin_url).send().unwrap().bytes().unwrap(), skin);
+        let missing = format!("{}/sessionserver/session/minecraft/profile/{}", root, alex.uuid);
+        assert_eq!(attohttpc::get(&missing).send().unwrap().status().as_u16(), 204);
+
+        // 加入服务器
+        let join = format!("{}/sessionserver/session/minecraft/join", root);
+        let has_joined = |username: &str| {
+            attohttpc::get(format!("{}/sessionserver/session/minecraft/hasJoined", root))
+                .param("username", username)
+                .param("serverId", "server hash")
+                .send()
+                .unwrap()
+        };
+        assert_eq!(has_joined("Steve").status().as_u16(), 204);
+        let body = json!({
+            "accessToken": token,
+            "selectedProfile": unsigned_uuid(&steve.uuid),
+            "serverId": "server hash",
+        });
+        assert_eq!(post(&join, body).status().as_u16(), 204);
+        let joined = json_body(has_joined("Steve"));
+        assert_eq!(joined["name"], "Steve");
+        assert_eq!(self::textures(&joined, &public_key)["profileId"], unsigned_uuid(&steve.uuid));
+        assert_eq!(has_joined("Alex").status().as_u16(), 204);
+        let body = json!({ "accessToken": token, "selectedProfile": alex.uuid, "serverId": "other" });
+        assert_eq!(post(&join, body).status().as_u16(), 403);
+
+        // 更新账号列表，重启后使用同一个密钥
+        server.set_accounts(vec![steve, alex]);
+        let by_name = format!("{}/api/profiles/minecraft", root);
+        let profiles = json_body(post(&by_name, json!(["alex", "nobody"])));
+        assert_eq!(profiles.as_array().unwrap().len(), 1);
+        drop(server);
+        let restarted = LocalYggdrasil::start(&key_path, Vec::new()).unwrap();
+        let metadata: Value = serde_json::from_str(&restarted.metadata()).unwrap();
+        assert_eq!(metadata["signaturePublickey"], public_key.as_str());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,735 @@
+//! 微软正版账号，使用 OAuth 设备代码流程登录
+//!
+//! 设备代码 -> OAuth token -> Xbox Live -> XSTS -> Minecraft 登录 -> 检查是否拥有游戏 -> 获取角色
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::info;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use crate::{
+    account::{AccountStatus, base::AccountBase},
+    statue::Status,
+};
+
+/// access token 剩余时间少于此值时视为即将过期，秒
+pub const EXPIRING_SECS: i64 = 10 * 60;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct MicrosoftAccount {
+    pub username: String,
+    pub uuid: String,
+    /// 凭据保存在 [`super::credential::CredentialStore`] 中，只读取旧版本设置文件中的值
+    #[serde(default, skip_serializing)]
+    pub access_token: String,
+    /// 微软 OAuth refresh token
+    #[serde(default, skip_serializing)]
+    pub refresh_token: String,
+    /// access token 过期时间，unix 秒
+    pub expires_at: i64,
+}
+
+impl AccountBase for MicrosoftAccount {
+    const KIND: &'static str = "microsoft";
+    fn get_username(&self) -> &str {
+        &self.username
+    }
+    fn get_uuid(&self) -> &str {
+        &self.uuid
+    }
+    fn get_access_token(&self) -> &str {
+        &self.access_token
+    }
+    fn get_user_type(&self) -> &str {
+        "msa"
+    }
+}
+
+impl MicrosoftAccount {
+    pub fn get_status(&self) -> AccountStatus {
+        // 凭据没有保存下来时 access token 为空
+        if !self.access_token.is_empty() && self.expires_at - now_secs() > EXPIRING_SECS {
+            AccountStatus::Valid
+        } else if self.refresh_token.is_empty() {
+            AccountStatus::NeedsLogin
+        } else {
+            AccountStatus::Expiring
+        }
+    }
+
+    /// 使用 refresh token 重新走一遍登录，得到新的 access token
+    ///
+    /// refresh token 失效时返回 [`LoginExpired`]，并清空本账号的 refresh token，
+    /// 之后只能重新使用设备代码登录
+    pub fn refresh(&mut self, endpoints: &MicrosoftEndpoints) -> Result<()> {
+        if self.refresh_token.is_empty() {
+            return Err(LoginExpired.into());
+        }
+        let mut token = match refresh_oauth_token(endpoints, &self.refresh_token) {
+            Ok(token) => token,
+            Err(e) => {
+                if e.is::<LoginExpired>() {
+                    self.refresh_token.clear();
+                    self.expires_at = 0;
+                }
+                return Err(e);
+            }
+        };
+        // 响应中没有新的 refresh token 时继续使用原来的
+        if token.refresh_token.is_empty() {
+            token.refresh_token = self.refresh_token.clone();
+        }
+        let account = login_with_oauth(endpoints, &token, |_| {})?;
+        if account.uuid != self.uuid {
+            bail!("refreshed token belongs to another profile: {}", account.username);
+        }
+        info!(target: "account", "microsoft account refreshed: {}", account.username);
+        *self = account;
+        Ok(())
+    }
+
+    /// 即将过期时刷新，返回是否刷新过
+    pub fn refresh_if_needed(&mut self, endpoints: &MicrosoftEndpoints) -> Result<bool> {
+        match self.get_status() {
+            AccountStatus::Valid => Ok(false),
+            AccountStatus::Expiring => self.refresh(endpoints).map(|_| true),
+            AccountStatus::NeedsLogin => Err(LoginExpired.into()),
+        }
+    }
+
+    /// 用 access token 请求角色信息，确认 token 没有被吊销
+    pub fn validate(&self, endpoints: &MicrosoftEndpoints) -> Result<bool> {
+        let url = format!("{}/minecraft/profile", endpoints.minecraft_base);
+        let response = attohttpc::get(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .send()
+            .context(format!("failed to request {}", url))?;
+        match response.status().as_u16() {
+            401 | 403 => Ok(false),
+            _ => {
+                let profile: MinecraftProfile = parse_response(&url, response)?;
+                Ok(profile.id == self.uuid)
+            }
+        }
+    }
+}
+
+/// refresh token 已失效，需要重新登录
+#[derive(Debug)]
+pub struct LoginExpired;
+
+impl std::fmt::Display for LoginExpired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "login expired, please log in again")
+    }
+}
+
+impl std::error::Error for LoginExpired {}
+
+/// 登录使用的接口地址，测试时可以指向本地服务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MicrosoftEndpoints {
+    pub client_id: String,
+    pub scope: String,
+    /// 包含 devicecode 和 token 接口
+    pub oauth_base: String,
+    pub xbox_user_base: String,
+    pub xsts_base: String,
+    pub minecraft_base: String,
+}
+
+impl Default for MicrosoftEndpoints {
+    fn default() -> Self {
+        Self {
+            // 需要在 Azure 注册应用后填写，界面中可以设置
+            client_id: String::new(),
+            scope: "XboxLive.signin offline_access".to_string(),
+            oauth_base: "https://login.microsoftonline.com/consumers/oauth2/v2.0".to_string(),
+            xbox_user_base: "https://user.auth.xboxlive.com".to_string(),
+            xsts_base: "https://xsts.auth.xboxlive.com".to_string(),
+            minecraft_base: "https://api.minecraftservices.com".to_string(),
+        }
+    }
+}
+
+/// 需要用户在浏览器中输入的设备代码
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DeviceCode {
+    pub user_code: String,
+    pub device_code: String,
+    pub verification_uri: String,
+    /// 秒
+    pub expires_in: u64,
+    /// 轮询间隔，秒
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    #[serde(default)]
+    pub message: String,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    #[serde(default)]
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    #[serde(default)]
+    error_description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxToken {
+    token: String,
+    display_claims: XboxClaims,
+}
+
+#[derive(Deserialize)]
+struct XboxClaims {
+    xui: Vec<XboxUser>,
+}
+
+#[derive(Deserialize)]
+struct XboxUser {
+    uhs: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct Entitlements {
+    #[serde(default)]
+    items: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct MinecraftProfile {
+    id: String,
+    name: String,
+}
+
+/// 当前 unix 秒
+pub(crate) fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn form_body(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", url_encode(key), url_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// POST 表单，返回状态码和内容
+fn post_form(url: &str, params: &[(&str, &str)]) -> Result<(u16, String)> {
+    let response = attohttpc::post(url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .text(form_body(params))
+        .send()
+        .context(format!("failed to request {}", url))?;
+    let status = response.status().as_u16();
+    Ok((status, response.text().context(format!("failed to read {}", url))?))
+}
+
+fn post_json<T: DeserializeOwned>(url: &str, body: &Value) -> Result<T> {
+    let response = attohttpc::post(url)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .text(body.to_string())
+        .send()
+        .context(format!("failed to request {}", url))?;
+    parse_response(url, response)
+}
+
+fn get_bearer<T: DeserializeOwned>(url: &str, token: &str) -> Result<T> {
+    let response = attohttpc::get(url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/json")
+        .send()
+        .context(format!("failed to request {}", url))?;
+    parse_response(url, response)
+}
+
+pub(crate) fn parse_response<T: DeserializeOwned>(url: &str, response: attohttpc::Response) -> Result<T> {
+    let status = response.status();
+    let text = response.text().context(format!("failed to read {}", url))?;
+    if !status.is_success() {
+        bail!("{} returned {}: {}", url, status, text);
+    }
+    serde_json::from_str(&text).context(format!("failed to parse response of {}", url))
+}
+
+/// 第一步：获取设备代码
+pub fn request_device_code(endpoints: &MicrosoftEndpoints) -> Result<DeviceCode> {
+    if endpoints.client_id.is_empty() {
+        bail!("microsoft client id is not configured");
+    }
+    let url = format!("{}/devicecode", endpoints.oauth_base);
+    let (status, text) = post_form(
+        &url,
+        &[("client_id", &endpoints.client_id), ("scope", &endpoints.scope)],
+    )?;
+    if !(200..300).contains(&status) {
+        bail!("{} returned {}: {}", url, status, text);
+    }
+    serde_json::from_str(&text).context("failed to parse device code")
+}
+
+/// 第二步：等待用户在浏览器中完成登录
+pub fn poll_device_token(endpoints: &MicrosoftEndpoints, code: &DeviceCode) -> Result<OAuthToken> {
+    let url = format!("{}/token", endpoints.oauth_base);
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = code.interval.max(1);
+    loop {
+        if Instant::now() > deadline {
+            bail!("device code expired");
+        }
+        thread::sleep(Duration::from_secs(interval));
+        let (status, text) = post_form(
+            &url,
+            &[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("client_id", &endpoints.client_id),
+                ("device_code", &code.device_code),
+            ],
+        )?;
+        if (200..300).contains(&status) {
+            return serde_json::from_str(&text).context("failed to parse oauth token");
+        }
+        let error: OAuthError = serde_json::from_str(&text)
+            .map_err(|_| anyhow!("{} returned {}: {}", url, status, text))?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += 5,
+            "authorization_declined" => bail!("login declined by user"),
+            "expired_token" => bail!("device code expired"),
+            _ => bail!("login failed: {} {}", error.error, error.error_description),
+        }
+    }
+}
+
+/// 用 refresh token 换取新的 OAuth token
+pub fn refresh_oauth_token(endpoints: &MicrosoftEndpoints, refresh_token: &str) -> Result<OAuthToken> {
+    let url = format!("{}/token", endpoints.oauth_base);
+    let (status, text) = post_form(
+        &url,
+        &[
+            ("grant_type", "refresh_token"),
+            ("client_id", &endpoints.client_id),
+            ("scope", &endpoints.scope),
+            ("refresh_token", refresh_token),
+        ],
+    )?;
+    if (200..300).contains(&status) {
+        return serde_json::from_str(&text).context("failed to parse oauth token");
+    }
+    // invalid_grant 表示 refresh token 过期或被吊销
+    match serde_json::from_str::<OAuthError>(&text) {
+        Ok(error) if error.error == "invalid_grant" => Err(LoginExpired.into()),
+        _ => bail!("failed to refresh token: {} {}", status, text),
+    }
+}
+
+/// 登录过程中的步骤
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginStep {
+    RequestingCode,
+    /// 等待用户在浏览器中输入代码
+    WaitingForUser(DeviceCode),
+    XboxLive,
+    Xsts,
+    Minecraft,
+    Entitlement,
+    Profile,
+}
+
+/// 从 OAuth token 完成剩下的登录步骤
+pub fn login_with_oauth(
+    endpoints: &MicrosoftEndpoints,
+    token: &OAuthToken,
+    mut step: impl FnMut(LoginStep),
+) -> Result<MicrosoftAccount> {
+    step(LoginStep::XboxLive);
+    let xbox: XboxToken = post_json(
+        &format!("{}/user/authenticate", endpoints.xbox_user_base),
+        &json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": format!("d={}", token.access_token),
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT",
+        }),
+    )
+    .context("xbox live authentication failed")?;
+
+    step(LoginStep::Xsts);
+    let xsts: XboxToken = post_json(
+        &format!("{}/xsts/authorize", endpoints.xsts_base),
+        &json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbox.token],
+            },
+            "RelyingParty": "rp://api.minecraftservices.com/",
+            "TokenType": "JWT",
+        }),
+    )
+    .map_err(xsts_error)?;
+    let uhs = xsts
+        .display_claims
+        .xui
+        .first()
+        .map(|user| user.uhs.clone())
+        .context("xsts response has no user hash")?;
+
+    step(LoginStep::Minecraft);
+    let minecraft: MinecraftToken = post_json(
+        &format!("{}/authentication/login_with_xbox", endpoints.minecraft_base),
+        &json!({ "identityToken": format!("XBL3.0 x={};{}", uhs, xsts.token) }),
+    )
+    .context("minecraft login failed")?;
+
+    step(LoginStep::Entitlement);
+    let entitlements: Entitlements = get_bearer(
+        &format!("{}/entitlements/mcstore", endpoints.minecraft_base),
+        &minecraft.access_token,
+    )?;
+    if entitlements.items.is_empty() {
+        bail!("this account does not own minecraft");
+    }
+
+    step(LoginStep::Profile);
+    let profile: MinecraftProfile = get_bearer(
+        &format!("{}/minecraft/profile", endpoints.minecraft_base),
+        &minecraft.access_token,
+    )
+    .context("this account has no minecraft profile")?;
+
+    info!(target: "account", "microsoft account logged in: {}", profile.name);
+    Ok(MicrosoftAccount {
+        username: profile.name,
+        uuid: profile.id,
+        access_token: minecraft.access_token,
+        refresh_token: token.refresh_token.clone(),
+        expires_at: now_secs() + minecraft.expires_in as i64,
+    })
+}
+
+/// XSTS 返回 401 时的常见原因
+fn xsts_error(e: anyhow::Error) -> anyhow::Error {
+    let text = e.to_string();
+    if text.contains("2148916233") {
+        anyhow!("this microsoft account has no xbox account")
+    } else if text.contains("2148916235") {
+        anyhow!("xbox live is not available in your country")
+    } else if text.contains("2148916238") {
+        anyhow!("child accounts must be added to a family by an adult")
+    } else {
+        e.context("xsts authorization failed")
+    }
+}
+
+/// 完整的设备代码登录
+pub fn login(
+    endpoints: &MicrosoftEndpoints,
+    mut step: impl FnMut(LoginStep),
+) -> Result<MicrosoftAccount> {
+    step(LoginStep::RequestingCode);
+    let code = request_device_code(endpoints)?;
+    step(LoginStep::WaitingForUser(code.clone()));
+    let token = poll_device_token(endpoints, &code)?;
+    login_with_oauth(endpoints, &token, step)
+}
+
+enum LoginMessage {
+    Step(LoginStep),
+    Done(Result<MicrosoftAccount, String>),
+}
+
+/// 在后台线程中登录
+#[derive(Default)]
+pub struct MicrosoftLogin {
+    rx: Option<Receiver<LoginMessage>>,
+    state: Option<Status<MicrosoftAccount, LoginStep, String>>,
+}
+
+impl MicrosoftLogin {
+    pub fn start(&mut self, endpoints: MicrosoftEndpoints) {
+        let (tx, rx): (Sender<LoginMessage>, _) = mpsc::channel();
+        self.rx = Some(rx);
+        self.state = Some(Status::Progress(LoginStep::RequestingCode));
+        thread::spawn(move || {
+            let result = login(&endpoints, |step| {
+                let _ = tx.send(LoginMessage::Step(step));
+            })
+            .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(LoginMessage::Done(result));
+        });
+    }
+
+    /// None 表示没有进行中的登录
+    pub fn get_state(&mut self) -> Option<Status<MicrosoftAccount, LoginStep, String>> {
+        if let Some(rx) = &self.rx {
+            while let Ok(message) = rx.try_recv() {
+                self.state = Some(match message {
+                    LoginMessage::Step(step) => Status::Progress(step),
+                    LoginMessage::Done(Ok(account)) => Status::Success(account),
+                    LoginMessage::Done(Err(e)) => Status::Failed(e),
+                });
+            }
+        }
+        match &self.state {
+            Some(Status::Success(account)) => Some(Status::Success(account.clone())),
+            Some(Status::Progress(step)) => Some(Status::Progress(step.clone())),
+            Some(Status::Failed(e)) => Some(Status::Failed(e.clone())),
+            None => None,
+        }
+    }
+
+    /// 取走登录结果并回到初始状态
+    pub fn set_none(&mut self) {
+        self.rx = None;
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use super::*;
+    use crate::test_server::{Request, Response, TestServer};
+
+    const UUID: &str = "069a79f444e94726a5befca90e38aaf5";
+
+    fn endpoints(server: &TestServer) -> MicrosoftEndpoints {
+        MicrosoftEndpoints {
+            client_id: "client".to_string(),
+            scope: "XboxLive.signin offline_access".to_string(),
+            oauth_base: format!("{}/oauth", server.url),
+            xbox_user_base: format!("{}/xbl", server.url),
+            xsts_base: format!("{}/xsts", server.url),
+            minecraft_base: format!("{}/mc", server.url),
+        }
+    }
+
+    fn form(request: &Request) -> HashMap<String, String> {
+        request
+            .text()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.replace("%3A", ":").replace('+', " ")))
+            .collect()
+    }
+
+    fn xbox_token(token: &str) -> Response {
+        Response::json(200, json!({ "Token": token, "DisplayClaims": { "xui": [{ "uhs": "hash" }] } }))
+    }
+
+    /// 模拟 Xbox Live、XSTS 和 Minecraft 服务，token 接口由 oauth 处理
+    fn start_server(oauth: impl Fn(&Request) -> Response + Send + Sync + 'static) -> TestServer {
+        TestServer::start(move |request| {
+            let bearer = request.header("Authorization") == Some("Bearer mc-token");
+            match (request.method.as_str(), request.path.as_str()) {
+                (_, path) if path.starts_with("/oauth/") => oauth(request),
+                ("POST", "/xbl/user/authenticate") => {
+                    assert_eq!(request.json()["Properties"]["RpsTicket"], "d=ms-token");
+                    xbox_token("xbl-token")
+                }
+                ("POST", "/xsts/xsts/authorize") => {
+                    assert_eq!(request.json()["Properties"]["UserTokens"], json!(["xbl-token"]));
+                    xbox_token("xsts-token")
+                }
+                ("POST", "/mc/authentication/login_with_xbox") => {
+                    assert_eq!(request.json()["identityToken"], "XBL3.0 x=hash;xsts-token");
+                    Response::json(200, json!({ "access_token": "mc-token", "expires_in": 86400 }))
+                }
+                ("GET", "/mc/entitlements/mcstore") if bearer => {
+                    Response::json(200, json!({ "items": [{ "name": "game_minecraft" }] }))
+                }
+                ("GET", "/mc/minecraft/profile") if bearer => {
+                    Response::json(200, json!({ "id": UUID, "name": "Steve" }))
+                }
+                ("GET", "/mc/minecraft/profile") => Response::status(401),
+                _ => Response::status(404),
+            }
+        })
+    }
+
+    fn token(request: &Request) -> Response {
+        let form = form(request);
+        assert_eq!(form["client_id"], "client");
+        match form["grant_type"].as_str() {
+            "refresh_token" if form["refresh_token"] == "refresh-token" => {
+                Response::json(200, json!({ "access_token": "ms-token", "expires_in": 3600 }))
+            }
+            "refresh_token" => Response::json(400, json!({ "error": "invalid_grant" })),
+            _ => Response::json(200, json!({ "access_token": "ms-token", "refresh_token": "refresh-token" })),
+        }
+    }
+
+    fn account(refresh_token: &str, expires_at: i64) -> MicrosoftAccount {
+        MicrosoftAccount {
+            username: "Steve".to_string(),
+            uuid: UUID.to_string(),
+            access_token: "old-token".to_string(),
+            refresh_token: refresh_token.to_string(),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn device_code_login() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        let server = start_server(move |request| match request.path.as_str() {
+            "/oauth/devicecode" => {
+                assert_eq!(form(request)["scope"], "XboxLive.signin offline_access");
+                Response::json(200, json!({
+                    "user_code": "ABCD1234",
+                    "device_code": "device",
+                    "verification_uri": "https://microsoft.com/link",
+                    "expires_in": 60,
+                    "interval": 1,
+                }))
+            }
+            // 第一次轮询时用户还没有完成登录
+            _ if counter.fetch_add(1, Ordering::Relaxed) == 0 => {
+                Response::json(400, json!({ "error": "authorization_pending" }))
+            }
+            _ => {
+                let form = form(request);
+                assert_eq!(form["grant_type"], "urn:ietf:params:oauth:grant-type:device_code");
+                assert_eq!(form["device_code"], "device");
+                token(request)
+            }
+        });
+
+        let mut steps = Vec::new();
+        let account = login(&endpoints(&server), |step| steps.push(step)).unwrap();
+        assert_eq!(account.username, "Steve");
+        assert_eq!(account.uuid, UUID);
+        assert_eq!(account.access_token, "mc-token");
+        assert_eq!(account.refresh_token, "refresh-token");
+        assert_eq!(account.get_status(), AccountStatus::Valid);
+        assert_eq!(polls.load(Ordering::Relaxed), 2);
+        assert!(matches!(&steps[1], LoginStep::WaitingForUser(code) if code.user_code == "ABCD1234"));
+        assert_eq!(
+            steps[2..],
+            [
+                LoginStep::XboxLive,
+                LoginStep::Xsts,
+                LoginStep::Minecraft,
+                LoginStep::Entitlement,
+                LoginStep::Profile,
+            ]
+        );
+
+        let missing_id = MicrosoftEndpoints { client_id: String::new(), ..endpoints(&server) };
+        assert!(request_device_code(&missing_id).is_err());
+    }
+
+    #[test]
+    fn refresh_account() {
+        let server = start_server(token);
+        let endpoints = endpoints(&server);
+        let mut expiring = account("refresh-token", now_secs() + 60);
+        assert_eq!(expiring.get_status(), AccountStatus::Expiring);
+        assert!(expiring.refresh_if_needed(&endpoints).unwrap());
+        assert_eq!(expiring.access_token, "mc-token");
+        // 响应中没有新的 refresh token 时保留原来的
+        assert_eq!(expiring.refresh_token, "refresh-token");
+        assert!(!expiring.refresh_if_needed(&endpoints).unwrap());
+
+        assert!(expiring.validate(&endpoints).unwrap());
+        assert!(!account("refresh-token", 0).validate(&endpoints).unwrap());
+
+        let mut other = account("refresh-token", 0);
+        other.uuid = "another".to_string();
+        let e = other.refresh(&endpoints).unwrap_err();
+        assert!(e.to_string().contains("another profile"));
+    }
+
+    #[test]
+    fn expired_refresh_token() {
+        let server = start_server(token);
+        let endpoints = endpoints(&server);
+        let mut revoked = account("revoked", 0);
+        let e = revoked.refresh(&endpoints).unwrap_err();
+        assert!(e.is::<LoginExpired>());
+        assert!(revoked.refresh_token.is_empty());
+        assert_eq!(revoked.get_status(), AccountStatus::NeedsLogin);
+        assert!(revoked.refresh_if_needed(&endpoints).unwrap_err().is::<LoginExpired>());
+
+        // 其他错误不清空 refresh token
+        let broken = TestServer::start(|_| Response::json(500, json!({ "error": "server_error" })));
+        let mut account = account("refresh-token", 0);
+        let e = account.refresh(&self::endpoints(&broken)).unwrap_err();
+        assert!(!e.is::<LoginExpired>());
+        assert_eq!(account.refresh_token, "refresh-token");
+    }
+
+    #[test]
+    fn xsts_errors() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/xbl/user/authenticate" => xbox_token("xbl-token"),
+            _ => Response::json(401, json!({ "XErr": 2148916233u64 })),
+        });
+        let token = OAuthToken {
+            access_token: "ms-token".to_string(),
+            refresh_token: String::new(),
+            expires_in: 0,
+        };
+        let e = login_with_oauth(&endpoints(&server), &token, |_| {}).map(|_| ()).unwrap_err();
+        assert_eq!(e.to_string(), "this microsoft account has no xbox account");
+    }
+}
@@ -1,21 +1,72 @@
+use std::path::PathBuf;
+
 use crate::account::base::AccountBase;
 
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct OfflineAccount {
     pub username: String,
     pub uuid: String,
+    /// 离线账号不需要验证，每次启动 mctui 时随机生成
+    #[serde(skip, default = "random_access_token")]
+    pub access_token: String,
+    /// 本地皮肤图片
+    #[serde(default)]
+    pub skin_path: Option<PathBuf>,
 }
 
 impl OfflineAccount {
-    pub fn new(username: String) -> Self {
-        let uuid = format!("{:x}", md5::compute(&username));
-        OfflineAccount { username, uuid }
+    pub fn new(username: String) -> Result<Self> {
+        validate_username(&username)?;
+        let uuid = offline_uuid(&username);
+        Ok(OfflineAccount {
+            username,
+            uuid,
+            access_token: random_access_token(),
+            skin_path: None,
+        })
+    }
+
+    /// 使用指定的 uuid，可以带或不带连字符
+    pub fn new_with_uuid(username: String, uuid: String) -> Result<Self> {
+        validate_username(&username)?;
+        let Some(uuid) = dashed_uuid(&uuid) else {
+            bail!("invalid uuid: {}", uuid);
+        };
+        Ok(OfflineAccount {
+            username,
+            uuid,
+            access_token: random_access_token(),
+            skin_path: None,
+        })
+    }
+
+    /// 修改昵称，uuid 由昵称生成时一起更新，自定义的 uuid 保持不变
+    pub fn rename(&mut self, username: String) -> Result<()> {
+        validate_username(&username)?;
+        if self.uuid == offline_uuid(&self.username) {
+            self.uuid = offline_uuid(&username);
+        }
+        self.username = username;
+        Ok(())
     }
 
-    pub fn new_with_uuid(username: String, uuid: String) -> Self {
-        OfflineAccount { username, uuid }
+    /// 旧版本使用用户名的 MD5 作为 uuid，迁移为与服务器一致的 uuid，返回是否有修改
+    pub fn migrate(&mut self) -> bool {
+        if self.uuid == format!("{:x}", md5::compute(&self.username)) {
+            self.uuid = offline_uuid(&self.username);
+            return true;
+        }
+        // 自定义的 uuid 只统一格式
+        match dashed_uuid(&self.uuid) {
+            Some(uuid) if uuid != self.uuid => {
+                self.uuid = uuid;
+                true
+            }
+            _ => false,
+        }
     }
 }
 
@@ -25,13 +76,112 @@ impl AccountBase for OfflineAccount {
         &self.username
     }
     fn get_access_token(&self) -> &str {
-        &self.uuid
+        &self.access_token
     }
     fn get_uuid(&self) -> &str {
-        // MD5: OfflinePlayer: + username
         &self.uuid
     }
     fn get_user_type(&self) -> &str {
-        "msa"
+        "legacy"
+    }
+}
+
+/// 用户名只能包含字母、数字和下划线，长度 3 到 16
+pub fn validate_username(username: &str) -> Result<()> {
+    if !(3..=16).contains(&username.len()) {
+        bail!("username must be 3 to 16 characters long");
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        bail!("username can only contain letters, digits and underscores");
+    }
+    Ok(())
+}
+
+/// 与服务器离线模式相同的 uuid：`OfflinePlayer:<name>` 的 MD5，按 UUID v3 设置版本和变体位
+pub fn offline_uuid(username: &str) -> String {
+    let mut bytes = md5::compute(format!("OfflinePlayer:{}", username)).0;
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    format_uuid(&bytes)
+}
+
+/// 转为带连字符的小写格式，不是 uuid 时返回 None
+fn dashed_uuid(uuid: &str) -> Option<String> {
+    let hex: String = uuid.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(format_uuid(&bytes))
+}
+
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn random_access_token() -> String {
+    let mut bytes = [0u8; 16];
+    let _ = getrandom::fill(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuid_matches_server() {
+        // 与 Java 的 UUID.nameUUIDFromBytes("OfflinePlayer:<name>") 相同
+        assert_eq!(offline_uuid("Notch"), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(offline_uuid("Steve"), "5627dd98-e6be-3c21-b8a8-e92344183641");
+    }
+
+    #[test]
+    fn dashed_uuid_formats() {
+        assert_eq!(
+            dashed_uuid("B50AD385829D3141A2167E7D7539BA7F").as_deref(),
+            Some("b50ad385-829d-3141-a216-7e7d7539ba7f")
+        );
+        assert_eq!(dashed_uuid("not-a-uuid"), None);
+        assert_eq!(dashed_uuid("b50ad385829d3141a2167e7d7539ba7"), None);
+    }
+
+    #[test]
+    fn rename_and_migrate() {
+        let mut account = OfflineAccount::new("Steve".to_string()).unwrap();
+        account.rename("Notch".to_string()).unwrap();
+        assert_eq!(account.uuid, offline_uuid("Notch"));
+
+        let custom = "00000000000000000000000000000001";
+        let mut account = OfflineAccount::new_with_uuid("Steve".to_string(), custom.to_string()).unwrap();
+        account.rename("Alex_1".to_string()).unwrap();
+        assert_eq!(account.uuid, "00000000-0000-0000-0000-000000000001");
+
+        account.uuid = format!("{:x}", md5::compute("Alex_1"));
+        assert!(account.migrate());
+        assert_eq!(account.uuid, offline_uuid("Alex_1"));
+        assert!(!account.migrate());
+    }
+
+    #[test]
+    fn username_rules() {
+        assert!(validate_username("Steve_01").is_ok());
+        assert!(validate_username("ab").is_err());
+        assert!(validate_username("a_very_long_username").is_err());
+        assert!(validate_username("bad name").is_err());
     }
 }
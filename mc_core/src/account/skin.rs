@@ -0,0 +1,623 @@
+//! 皮肤和披风
+//!
+//! 正版账号通过 Minecraft 服务的角色接口管理皮肤和披风，所有账号都可以加载头像用于显示
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    account::{
+        Account,
+        credential::credential_key,
+        microsoft_account::{MicrosoftAccount, MicrosoftEndpoints, parse_response},
+        yggdrasil_account::YggdrasilAccount,
+    },
+    statue::Status,
+};
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// 头像的边长，像素
+pub const FACE_SIZE: usize = 8;
+
+/// 皮肤模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SkinVariant {
+    /// 4 像素宽手臂（Steve）
+    #[default]
+    #[serde(alias = "classic")]
+    Classic,
+    /// 3 像素宽手臂（Alex）
+    #[serde(alias = "slim")]
+    Slim,
+}
+
+impl SkinVariant {
+    /// 上传时使用的名称
+    pub fn as_str(&self) -> &str {
+        match self {
+            SkinVariant::Classic => "classic",
+            SkinVariant::Slim => "slim",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Skin {
+    pub id: String,
+    /// ACTIVE 或 INACTIVE
+    pub state: String,
+    /// 皮肤图片地址
+    pub url: String,
+    #[serde(default)]
+    pub variant: SkinVariant,
+    /// 默认皮肤的名称，例如 STEVE
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+impl Skin {
+    pub fn is_active(&self) -> bool {
+        self.state == "ACTIVE"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cape {
+    pub id: String,
+    /// ACTIVE 或 INACTIVE
+    pub state: String,
+    pub url: String,
+    #[serde(default)]
+    pub alias: String,
+}
+
+impl Cape {
+    pub fn is_active(&self) -> bool {
+        self.state == "ACTIVE"
+    }
+}
+
+/// 角色拥有的皮肤和披风
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkinProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub skins: Vec<Skin>,
+    #[serde(default)]
+    pub capes: Vec<Cape>,
+}
+
+impl SkinProfile {
+    pub fn active_skin(&self) -> Option<&Skin> {
+        self.skins.iter().find(|skin| skin.is_active())
+    }
+
+    /// 没有装备披风时为 None
+    pub fn active_cape(&self) -> Option<&Cape> {
+        self.capes.iter().find(|cape| cape.is_active())
+    }
+}
+
+/// 检查是否为 64x64 或 64x32 的 PNG 图片
+pub fn validate_skin(data: &[u8]) -> Result<()> {
+    // 签名之后第一个块必须是 IHDR：长度、类型、宽、高
+    if data.len() < 24 || !data.starts_with(PNG_SIGNATURE) || &data[12..16] != b"IHDR" {
+        bail!("skin is not a png image");
+    }
+    let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+    let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+    if width != 64 || (height != 64 && height != 32) {
+        bail!("skin must be 64x64 or 64x32, got {}x{}", width, height);
+    }
+    Ok(())
+}
+
+impl MicrosoftAccount {
+    fn profile_url(&self, endpoints: &MicrosoftEndpoints) -> String {
+        format!("{}/minecraft/profile", endpoints.minecraft_base)
+    }
+
+    /// 获取角色的皮肤和披风列表
+    pub fn get_skin_profile(&self, endpoints: &MicrosoftEndpoints) -> Result<SkinProfile> {
+        let url = self.profile_url(endpoints);
+        let response = attohttpc::get(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .send()
+            .context(format!("failed to request {}", url))?;
+        parse_response(&url, response)
+    }
+
+    /// 上传本地的皮肤图片，返回修改后的角色
+    pub fn upload_skin(
+        &self,
+        endpoints: &MicrosoftEndpoints,
+        path: &Path,
+        variant: SkinVariant,
+    ) -> Result<SkinProfile> {
+        let data = fs::read(path).context(format!("failed to read {:?}", path))?;
+        validate_skin(&data)?;
+        let form = attohttpc::MultipartBuilder::new()
+            .with_text("variant", variant.as_str())
+            .with_file(
+                attohttpc::MultipartFile::new("file", &data)
+                    .with_type("image/png")?
+                    .with_filename("skin.png"),
+            )
+            .build()?;
+        let url = format!("{}/skins", self.profile_url(endpoints));
+        let response = attohttpc::post(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .body(form)
+            .send()
+            .context(format!("failed to request {}", url))?;
+        info!(target: "account", "skin uploaded: {:?} ({})", path, variant.as_str());
+        self.profile_response(endpoints, &url, response)
+    }
+
+    /// 恢复为默认皮肤
+    pub fn reset_skin(&self, endpoints: &MicrosoftEndpoints) -> Result<SkinProfile> {
+        let url = format!("{}/skins/active", self.profile_url(endpoints));
+        let response = attohttpc::delete(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .send()
+            .context(format!("failed to request {}", url))?;
+        info!(target: "account", "skin reset: {}", self.username);
+        self.profile_response(endpoints, &url, response)
+    }
+
+    /// 装备一件拥有的披风
+    pub fn equip_cape(&self, endpoints: &MicrosoftEndpoints, cape_id: &str) -> Result<SkinProfile> {
+        let url = format!("{}/capes/active", self.profile_url(endpoints));
+        let response = attohttpc::put(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .text(json!({ "capeId": cape_id }).to_string())
+            .send()
+            .context(format!("failed to request {}", url))?;
+        info!(target: "account", "cape equipped: {}", cape_id);
+        self.profile_response(endpoints, &url, response)
+    }
+
+    /// 隐藏披风
+    pub fn hide_cape(&self, endpoints: &MicrosoftEndpoints) -> Result<SkinProfile> {
+        let url = format!("{}/capes/active", self.profile_url(endpoints));
+        let response = attohttpc::delete(&url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .send()
+            .context(format!("failed to request {}", url))?;
+        info!(target: "account", "cape hidden: {}", self.username);
+        self.profile_response(endpoints, &url, response)
+    }
+
+    /// 修改接口通常直接返回角色，返回内容为空时重新获取
+    fn profile_response(
+        &self,
+        endpoints: &MicrosoftEndpoints,
+        url: &str,
+        response: attohttpc::Response,
+    ) -> Result<SkinProfile> {
+        let status = response.status();
+        let text = response.text().context(format!("failed to read {}", url))?;
+        if !status.is_success() {
+            bail!("{} returned {}: {}", url, status, text);
+        }
+        if text.trim().is_empty() {
+            return self.get_skin_profile(endpoints);
+        }
+        serde_json::from_str(&text).context(format!("failed to parse response of {}", url))
+    }
+}
+
+impl YggdrasilAccount {
+    /// 从会话服务器的角色属性中取出皮肤地址，没有皮肤时为 None
+    pub fn get_skin_url(&self) -> Result<Option<String>> {
+        let url = format!(
+            "{}/sessionserver/session/minecraft/profile/{}?unsigned=true",
+            self.api_root,
+            self.uuid.replace('-', "")
+        );
+        let response = attohttpc::get(&url)
+            .header("Accept", "application/json")
+            .send()
+            .context(format!("failed to request {}", url))?;
+        // 角色不存在时返回 204
+        if response.status().as_u16() == 204 {
+            return Ok(None);
+        }
+        let profile: Value = parse_response(&url, response)?;
+        let Some(textures) = profile["properties"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|property| property["name"] == "textures")
+            .and_then(|property| property["value"].as_str())
+        else {
+            return Ok(None);
+        };
+        let textures: Value = serde_json::from_slice(
+            &STANDARD
+                .decode(textures)
+                .context("invalid textures property")?,
+        )
+        .context("invalid textures property")?;
+        Ok(textures["textures"]["SKIN"]["url"]
+            .as_str()
+            .map(|url| url.to_string()))
+    }
+}
+
+/// 获取账号当前使用的皮肤图片，没有设置皮肤时为 None
+///
+/// 正版账号来自角色信息，外置登录账号来自认证服务器，离线账号来自本地文件
+pub fn fetch_skin(account: &Account, endpoints: &MicrosoftEndpoints) -> Result<Option<Vec<u8>>> {
+    let url = match account {
+        Account::Offline(account) => {
+            return match &account.skin_path {
+                Some(path) => Ok(Some(
+                    fs::read(path).context(format!("failed to read {:?}", path))?,
+                )),
+                None => Ok(None),
+            };
+        }
+        Account::Microsoft(account) => account
+            .get_skin_profile(endpoints)?
+            .active_skin()
+            .map(|skin| skin.url.clone()),
+        Account::Yggdrasil(account) => account.get_skin_url()?,
+    };
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    let data = attohttpc::get(&url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .context(format!("failed to download skin from {}", url))?;
+    Ok(Some(data))
+}
+
+/// 解码后的皮肤图片，每个像素为 RGBA
+pub struct SkinImage {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl SkinImage {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        validate_skin(data)?;
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().context("failed to decode skin")?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).context("failed to decode skin")?;
+        let buf = &buf[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buf
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            png::ColorType::Rgb => buf.chunks_exact(3).map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => {
+                buf.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect()
+            }
+            png::ColorType::Grayscale => buf.iter().map(|&p| [p, p, p, 255]).collect(),
+            png::ColorType::Indexed => bail!("unsupported skin color type"),
+        };
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels
+            .get(y * self.width + x)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 根据右臂最外侧一列是否透明判断模型，64x32 的旧格式只有 classic
+    pub fn guess_variant(&self) -> SkinVariant {
+        if self.height == 64 && self.pixel(55, 20)[3] == 0 {
+            SkinVariant::Slim
+        } else {
+            SkinVariant::Classic
+        }
+    }
+
+    /// 8x8 的脸部，叠加帽子层
+    pub fn face(&self) -> PlayerFace {
+        let mut pixels = [[0; 4]; FACE_SIZE * FACE_SIZE];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let (x, y) = (i % FACE_SIZE, i / FACE_SIZE);
+            let face = self.pixel(8 + x, 8 + y);
+            let hat = self.pixel(40 + x, 8 + y);
+            *pixel = blend(face, hat);
+        }
+        PlayerFace { pixels }
+    }
+}
+
+/// 把 over 按透明度叠加到 base 上
+fn blend(base: [u8; 4], over: [u8; 4]) -> [u8; 4] {
+    let alpha = over[3] as u32;
+    if alpha == 0 {
+        return base;
+    }
+    let mix = |b: u8, o: u8| ((o as u32 * alpha + b as u32 * (255 - alpha)) / 255) as u8;
+    [
+        mix(base[0], over[0]),
+        mix(base[1], over[1]),
+        mix(base[2], over[2]),
+        base[3].max(over[3]),
+    ]
+}
+
+/// 玩家头像，8x8 RGBA
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerFace {
+    pub pixels: [[u8; 4]; FACE_SIZE * FACE_SIZE],
+}
+
+impl PlayerFace {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * FACE_SIZE + x]
+    }
+}
+
+/// 下载并解码头像，没有皮肤时为 None
+pub fn load_face(account: &Account, endpoints: &MicrosoftEndpoints) -> Result<Option<PlayerFace>> {
+    let Some(data) = fetch_skin(account, endpoints)? else {
+        return Ok(None);
+    };
+    Ok(Some(SkinImage::decode(&data)?.face()))
+}
+
+/// 头像的加载状态，成功但没有皮肤时为 None
+pub type FaceState = Status<Option<PlayerFace>, (), String>;
+
+type FaceResult = Result<Option<PlayerFace>, String>;
+
+/// 在后台线程中加载头像，按账号缓存
+#[derive(Default)]
+pub struct PlayerHeads {
+    heads: HashMap<String, FaceState>,
+    pending: Vec<(String, Receiver<FaceResult>)>,
+}
+
+impl PlayerHeads {
+    /// 账号的头像，第一次调用时开始加载
+    pub fn get_state(
+        &mut self,
+        account: &Account,
+        endpoints: &MicrosoftEndpoints,
+    ) -> &FaceState {
+        self.pending.retain(|(key, rx)| match rx.try_recv() {
+            Ok(result) => {
+                let state = match result {
+                    Ok(face) => Status::Success(face),
+                    Err(e) => Status::Failed(e),
+                };
+                self.heads.insert(key.clone(), state);
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        let key = credential_key(account);
+        if !self.heads.contains_key(&key) {
+            let (tx, rx) = mpsc::channel();
+            let account = account.clone();
+            let endpoints = endpoints.clone();
+            thread::spawn(move || {
+                let _ = tx.send(load_face(&account, &endpoints).map_err(|e| format!("{:#}", e)));
+            });
+            self.pending.push((key.clone(), rx));
+            self.heads.insert(key.clone(), Status::Progress(()));
+        }
+        &self.heads[&key]
+    }
+
+    /// 已加载完成的头像
+    pub fn get(&mut self, account: &Account, endpoints: &MicrosoftEndpoints) -> Option<&PlayerFace> {
+        match self.get_state(account, endpoints) {
+            Status::Success(face) => face.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// 皮肤修改后重新加载
+    pub fn reload(&mut self, account: &Account) {
+        let key = credential_key(account);
+        self.heads.remove(&key);
+        self.pending.retain(|(k, _)| *k != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_server::{Request, Response, TestServer};
+
+    /// 只有签名和 IHDR 的 PNG 头
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn validate_skin_size() {
+        assert!(validate_skin(&png_header(64, 64)).is_ok());
+        assert!(validate_skin(&png_header(64, 32)).is_ok());
+        assert!(validate_skin(&png_header(128, 128)).is_err());
+        assert!(validate_skin(&png_header(64, 48)).is_err());
+    }
+
+    #[test]
+    fn validate_skin_format() {
+        assert!(validate_skin(b"").is_err());
+        assert!(validate_skin(&png_header(64, 64)[..20]).is_err());
+        let mut jpeg = png_header(64, 64);
+        jpeg[..4].copy_from_slice(&[0xff, 0xd8, 0xff, 0xe0]);
+        assert!(validate_skin(&jpeg).is_err());
+        let mut no_ihdr = png_header(64, 64);
+        no_ihdr[12..16].copy_from_slice(b"IDAT");
+        assert!(validate_skin(&no_ihdr).is_err());
+    }
+
+    fn endpoints(server: &TestServer) -> MicrosoftEndpoints {
+        MicrosoftEndpoints { minecraft_base: format!("{}/mc", server.url), ..Default::default() }
+    }
+
+    fn account() -> MicrosoftAccount {
+        MicrosoftAccount {
+            username: "Steve".to_string(),
+            uuid: "a1".to_string(),
+            access_token: "token".to_string(),
+            refresh_token: String::new(),
+            expires_at: 0,
+        }
+    }
+
+    /// 角色信息，skin 为上传的皮肤模型，没有时使用默认皮肤，cape 为装备的披风
+    fn profile(skin: Option<&str>, cape: Option<&str>) -> Value {
+        let state = |active: bool| if active { "ACTIVE" } else { "INACTIVE" };
+        let capes = [("c1", "Migrator"), ("c2", "Vanilla")].map(|(id, alias)| {
+            json!({
+                "id": id,
+                "state": state(cape == Some(id)),
+                "url": format!("http://textures/{}", id),
+                "alias": alias,
+            })
+        });
+        json!({
+            "id": "a1",
+            "name": "Steve",
+            "skins": [{
+                "id": "s1",
+                "state": "ACTIVE",
+                "url": "http://textures/s1",
+                "variant": skin.unwrap_or("CLASSIC"),
+                "alias": if skin.is_none() { Some("STEVE") } else { None },
+            }],
+            "capes": capes,
+        })
+    }
+
+    /// 模拟角色接口，上传皮肤后返回空内容，需要重新获取角色
+    fn start_server() -> TestServer {
+        // 上传的皮肤模型和装备的披风
+        let current: Arc<Mutex<(Option<&str>, Option<String>)>> = Arc::default();
+        TestServer::start(move |request: &Request| {
+            if request.header("Authorization") != Some("Bearer token") {
+                return Response::status(401);
+            }
+            let mut current = current.lock().unwrap();
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/mc/minecraft/profile") => {}
+                ("POST", "/mc/minecraft/profile/skins") => {
+                    current.0 = Some(if request.text().contains("slim") { "SLIM" } else { "CLASSIC" });
+                    return Response::status(200);
+                }
+                ("DELETE", "/mc/minecraft/profile/skins/active") => current.0 = None,
+                ("PUT", "/mc/minecraft/profile/capes/active") => {
+                    let id = request.json()["capeId"].as_str().unwrap().to_string();
+                    if id == "missing" {
+                        return Response::json(400, json!({ "errorMessage": "profile does not own cape" }));
+                    }
+                    current.1 = Some(id);
+                }
+                ("DELETE", "/mc/minecraft/profile/capes/active") => current.1 = None,
+                _ => return Response::status(404),
+            }
+            Response::json(200, profile(current.0, current.1.as_deref()))
+        })
+    }
+
+    #[test]
+    fn upload_and_reset_skin() {
+        let server = start_server();
+        let endpoints = endpoints(&server);
+        let account = account();
+        let dir = std::env::temp_dir().join(format!("mctui-skin-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("skin.png");
+        fs::write(&path, png_header(64, 64)).unwrap();
+
+        let profile = account.upload_skin(&endpoints, &path, SkinVariant::Slim).unwrap();
+        let skin = profile.active_skin().unwrap();
+        assert_eq!((skin.variant, skin.alias.as_deref()), (SkinVariant::Slim, None));
+        let requests = server.requests();
+        let upload = requests.iter().find(|r| r.method == "POST").unwrap();
+        let content_type = upload.header("Content-Type").unwrap();
+        let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        let body = upload.text();
+        assert!(body.contains("Content-Disposition: form-data; name=\"variant\"\r\n\r\nslim\r\n"));
+        assert!(body.contains("Content-Disposition: form-data; name=\"file\"; filename=\"skin.png\""));
+        assert!(body.contains("Content-Type: image/png"));
+        let png = png_header(64, 64);
+        assert!(upload.body.windows(png.len()).any(|part| part == png));
+        assert!(body.trim_end().ends_with(&format!("--{}--", boundary)));
+
+        let profile = account.reset_skin(&endpoints).unwrap();
+        let skin = profile.active_skin().unwrap();
+        assert_eq!((skin.variant, skin.alias.as_deref()), (SkinVariant::Classic, Some("STEVE")));
+
+        // 上传前检查图片
+        fs::write(&path, png_header(128, 128)).unwrap();
+        assert!(account.upload_skin(&endpoints, &path, SkinVariant::Classic).is_err());
+        assert_eq!(server.requests().iter().filter(|r| r.method == "POST").count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn equip_and_hide_cape() {
+        let server = start_server();
+        let endpoints = endpoints(&server);
+        let account = account();
+        assert!(account.get_skin_profile(&endpoints).unwrap().active_cape().is_none());
+
+        let profile = account.equip_cape(&endpoints, "c2").unwrap();
+        assert_eq!(profile.active_cape().unwrap().alias, "Vanilla");
+        assert_eq!(profile.capes.iter().filter(|cape| cape.is_active()).count(), 1);
+        let e = account.equip_cape(&endpoints, "missing").unwrap_err();
+        assert!(e.to_string().contains("400"), "{}", e);
+
+        let profile = account.hide_cape(&endpoints).unwrap();
+        assert!(profile.active_cape().is_none());
+        assert_eq!(server.requests().last().unwrap().method, "DELETE");
+
+        let e = MicrosoftAccount { access_token: "expired".to_string(), ..account }
+            .hide_cape(&endpoints)
+            .unwrap_err();
+        assert!(e.to_string().contains("401"), "{}", e);
+    }
+}
@@ -0,0 +1,87 @@
+//! 在后台检查当前账号，需要时刷新 access token
+
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::account::{
+    Account, AccountStatus,
+    base::AccountBase,
+    microsoft_account::{LoginExpired, MicrosoftEndpoints},
+};
+
+/// 两次检查之间的间隔
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// 一次检查的结果
+pub struct Validation {
+    /// 检查后的账号，刷新过 token 时与原账号不同
+    pub account: Account,
+    pub status: AccountStatus,
+    pub changed: bool,
+}
+
+/// 后台账号检查
+#[derive(Default)]
+pub struct AccountValidator {
+    rx: Option<Receiver<Validation>>,
+    last_check: Option<Instant>,
+}
+
+impl AccountValidator {
+    /// 距离上次检查超过 [`CHECK_INTERVAL`] 且没有正在进行的检查
+    pub fn needs_check(&self) -> bool {
+        self.rx.is_none() && self.last_check.is_none_or(|last| last.elapsed() >= CHECK_INTERVAL)
+    }
+
+    pub fn start(&mut self, account: Account, endpoints: MicrosoftEndpoints) {
+        let (tx, rx) = mpsc::channel();
+        self.rx = Some(rx);
+        self.last_check = Some(Instant::now());
+        thread::spawn(move || {
+            let _ = tx.send(validate(account, &endpoints));
+        });
+    }
+
+    /// 检查完成时返回结果
+    pub fn take_result(&mut self) -> Option<Validation> {
+        let validation = self.rx.as_ref()?.try_recv().ok()?;
+        self.rx = None;
+        Some(validation)
+    }
+}
+
+/// 刷新并验证账号，网络错误时保持原状态
+pub fn validate(mut account: Account, endpoints: &MicrosoftEndpoints) -> Validation {
+    let changed = match account.refresh_before_launch(endpoints) {
+        Ok(changed) => changed,
+        Err(e) => {
+            warn!(target: "account", "failed to refresh account {}: {:#}", account.get_username(), e);
+            let status = if e.is::<LoginExpired>() {
+                AccountStatus::NeedsLogin
+            } else {
+                account.get_status()
+            };
+            // refresh token 失效时账号本身也被修改了
+            let changed = status == AccountStatus::NeedsLogin;
+            return Validation { account, status, changed };
+        }
+    };
+    let status = match &account {
+        Account::Microsoft(microsoft) => match microsoft.validate(endpoints) {
+            Ok(true) => account.get_status(),
+            Ok(false) => AccountStatus::NeedsLogin,
+            Err(e) => {
+                warn!(target: "account", "failed to validate account {}: {:#}", microsoft.username, e);
+                account.get_status()
+            }
+        },
+        // 外置登录账号在刷新时已经验证过
+        _ => account.get_status(),
+    };
+    Validation { account, status, changed }
+}
@@ -0,0 +1,524 @@
+//! 外置登录（Yggdrasil / authlib-injector）账号
+//!
+//! 协议见 <https://github.com/yushijinhun/authlib-injector/wiki/Yggdrasil-服务端技术规范>
+
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::info;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use crate::{
+    account::{AccountStatus, base::AccountBase, microsoft_account::LoginExpired},
+    statue::Status,
+};
+
+/// 服务器通过这个响应头指明真正的 API 地址
+const API_LOCATION_HEADER: &str = "X-Authlib-Injector-API-Location";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YggdrasilProfile {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct YggdrasilAccount {
+    /// API 地址，例如 https://littleskin.cn/api/yggdrasil
+    pub api_root: String,
+    /// 服务器名称，来自元数据中的 serverName
+    pub server_name: String,
+    /// 登录时使用的邮箱或用户名
+    pub login: String,
+    /// 当前使用的角色
+    pub username: String,
+    pub uuid: String,
+    /// 账号下所有可用的角色
+    #[serde(default)]
+    pub profiles: Vec<YggdrasilProfile>,
+    pub client_token: String,
+    /// 凭据保存在 [`super::credential::CredentialStore`] 中
+    #[serde(default, skip_serializing)]
+    pub access_token: String,
+}
+
+impl AccountBase for YggdrasilAccount {
+    const KIND: &'static str = "yggdrasil";
+    fn get_username(&self) -> &str {
+        &self.username
+    }
+    fn get_uuid(&self) -> &str {
+        &self.uuid
+    }
+    fn get_access_token(&self) -> &str {
+        &self.access_token
+    }
+    fn get_user_type(&self) -> &str {
+        "mojang"
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthResponse {
+    access_token: String,
+    client_token: String,
+    #[serde(default)]
+    available_profiles: Vec<YggdrasilProfile>,
+    selected_profile: Option<YggdrasilProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilError {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+/// 补全协议并跟随 API 地址指示，得到真正的 API 地址
+pub fn resolve_api_root(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    let response = attohttpc::get(&url)
+        .send()
+        .context(format!("failed to request {}", url))?;
+    let location = response
+        .headers()
+        .get(API_LOCATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|location| join_url(&url, location));
+    Ok(match location {
+        Some(location) => location.trim_end_matches('/').to_string(),
+        None => url,
+    })
+}
+
+/// 把相对地址解析为绝对地址
+fn join_url(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let scheme_end = base.find("://").map(|i| i + 3).unwrap_or_default();
+    if location.starts_with('/') {
+        let host_end = base[scheme_end..]
+            .find('/')
+            .map(|i| i + scheme_end)
+            .unwrap_or(base.len());
+        format!("{}{}", &base[..host_end], location)
+    } else {
+        let dir_end = base[scheme_end..]
+            .rfind('/')
+            .map(|i| i + scheme_end)
+            .unwrap_or(base.len());
+        format!("{}/{}", &base[..dir_end], location)
+    }
+}
+
+/// API 元数据的原始 JSON，启动时会预先传给 authlib-injector
+pub fn fetch_metadata(api_root: &str) -> Result<String> {
+    let text = attohttpc::get(api_root)
+        .header("Accept", "application/json")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .context(format!("failed to get metadata from {}", api_root))?;
+    serde_json::from_str::<Value>(&text).context("invalid yggdrasil metadata")?;
+    Ok(text)
+}
+
+fn server_name(metadata: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(metadata).ok()?;
+    value["meta"]["serverName"].as_str().map(|name| name.to_string())
+}
+
+/// 随机的 clientToken
+fn new_client_token() -> String {
+    let mut bytes = [0u8; 16];
+    let _ = getrandom::fill(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// POST JSON，返回状态码和内容
+fn post(url: &str, body: &Value) -> Result<(u16, String)> {
+    let response = attohttpc::post(url)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .text(body.to_string())
+        .send()
+        .context(format!("failed to request {}", url))?;
+    let status = response.status().as_u16();
+    Ok((status, response.text().context(format!("failed to read {}", url))?))
+}
+
+fn post_json<T: DeserializeOwned>(url: &str, body: &Value) -> Result<T> {
+    let (status, text) = post(url, body)?;
+    parse_response(url, status, &text)
+}
+
+fn parse_response<T: DeserializeOwned>(url: &str, status: u16, text: &str) -> Result<T> {
+    if (200..300).contains(&status) {
+        return serde_json::from_str(text).context(format!("failed to parse response of {}", url));
+    }
+    let error: YggdrasilError = serde_json::from_str(text)
+        .map_err(|_| anyhow!("{} returned {}: {}", url, status, text))?;
+    // ForbiddenOperationException 表示凭据无效或令牌已失效
+    if status == 403 && error.error == "ForbiddenOperationException" {
+        bail!("{}", error.error_message);
+    }
+    bail!("{} returned {}: {} {}", url, status, error.error, error.error_message)
+}
+
+impl YggdrasilAccount {
+    fn from_response(
+        api_root: &str,
+        server_name: String,
+        login: &str,
+        response: AuthResponse,
+        profile: YggdrasilProfile,
+    ) -> Self {
+        Self {
+            api_root: api_root.to_string(),
+            server_name,
+            login: login.to_string(),
+            username: profile.name,
+            uuid: profile.id,
+            profiles: response.available_profiles,
+            client_token: response.client_token,
+            access_token: response.access_token,
+        }
+    }
+
+    pub fn get_status(&self) -> AccountStatus {
+        if self.access_token.is_empty() {
+            AccountStatus::NeedsLogin
+        } else {
+            AccountStatus::Valid
+        }
+    }
+
+    /// 检查 access token 是否仍然有效
+    pub fn validate(&self) -> Result<bool> {
+        let (status, text) = post(
+            &format!("{}/authserver/validate", self.api_root),
+            &json!({ "accessToken": self.access_token, "clientToken": self.client_token }),
+        )?;
+        match status {
+            204 | 200 => Ok(true),
+            403 => Ok(false),
+            _ => bail!("failed to validate token: {} {}", status, text),
+        }
+    }
+
+    /// 刷新 access token，传入角色时同时切换到该角色
+    ///
+    /// 令牌已失效时返回 [`LoginExpired`]，需要重新输入密码登录
+    pub fn refresh(&mut self, profile: Option<&YggdrasilProfile>) -> Result<()> {
+        if self.access_token.is_empty() {
+            return Err(LoginExpired.into());
+        }
+        let mut body = json!({
+            "accessToken": self.access_token,
+            "clientToken": self.client_token,
+            "requestUser": false,
+        });
+        if let Some(profile) = profile {
+            body["selectedProfile"] = json!(profile);
+        }
+        let url = format!("{}/authserver/refresh", self.api_root);
+        let (status, text) = post(&url, &body)?;
+        if status == 403 {
+            self.access_token.clear();
+            return Err(LoginExpired.into());
+        }
+        let response: AuthResponse = parse_response(&url, status, &text)?;
+        let selected = response
+            .selected_profile
+            .context("server did not select a profile")?;
+        self.access_token = response.access_token;
+        self.client_token = response.client_token;
+        self.username = selected.name;
+        self.uuid = selected.id;
+        if !response.available_profiles.is_empty() {
+            self.profiles = response.available_profiles;
+        }
+        info!(target: "account", "yggdrasil account refreshed: {}", self.username);
+        Ok(())
+    }
+
+    /// 令牌失效时刷新，返回是否刷新过
+    pub fn refresh_if_needed(&mut self) -> Result<bool> {
+        if self.access_token.is_empty() {
+            return Err(LoginExpired.into());
+        }
+        if self.validate()? {
+            return Ok(false);
+        }
+        self.refresh(None).map(|_| true)
+    }
+
+    /// 切换到账号下的另一个角色
+    pub fn select_profile(&mut self, profile_id: &str) -> Result<()> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.id == profile_id)
+            .cloned()
+            .context(format!("profile {} not found", profile_id))?;
+        self.refresh(Some(&profile))
+    }
+
+    /// 吊销 access token，删除账号时调用
+    pub fn invalidate(&self) -> Result<()> {
+        let (status, text) = post(
+            &format!("{}/authserver/invalidate", self.api_root),
+            &json!({ "accessToken": self.access_token, "clientToken": self.client_token }),
+        )?;
+        if !(200..300).contains(&status) {
+            bail!("failed to invalidate token: {} {}", status, text);
+        }
+        Ok(())
+    }
+}
+
+/// 使用用户名和密码登录，api_root 应当已经由 [`resolve_api_root`] 解析过
+///
+/// 服务器没有选中角色时使用第一个可用角色
+pub fn login(api_root: &str, login: &str, password: &str) -> Result<YggdrasilAccount> {
+    let metadata = fetch_metadata(api_root)?;
+    let server_name = server_name(&metadata).unwrap_or_else(|| api_root.to_string());
+    let response: AuthResponse = post_json(
+        &format!("{}/authserver/authenticate", api_root),
+        &json!({
+            "username": login,
+            "password": password,
+            "clientToken": new_client_token(),
+            "requestUser": false,
+            "agent": { "name": "Minecraft", "version": 1 },
+        }),
+    )
+    .context("yggdrasil authentication failed")?;
+
+    let account = match response.selected_profile.clone() {
+        Some(profile) => {
+            YggdrasilAccount::from_response(api_root, server_name, login, response, profile)
+        }
+        None => {
+            let profile = response
+                .available_profiles
+                .first()
+                .cloned()
+                .context("this account has no profile")?;
+            let mut account = YggdrasilAccount::from_response(
+                api_root,
+                server_name,
+                login,
+                response,
+                profile.clone(),
+            );
+            account.refresh(Some(&profile))?;
+            account
+        }
+    };
+    info!(target: "account", "yggdrasil account logged in: {} ({})", account.username, account.server_name);
+    Ok(account)
+}
+
+/// 在后台线程中登录
+#[derive(Default)]
+pub struct YggdrasilLogin {
+    rx: Option<Receiver<Result<YggdrasilAccount, String>>>,
+    state: Option<Status<YggdrasilAccount, (), String>>,
+}
+
+impl YggdrasilLogin {
+    pub fn start(&mut self, server: String, login_name: String, password: String) {
+        let (tx, rx) = mpsc::channel();
+        self.rx = Some(rx);
+        self.state = Some(Status::Progress(()));
+        thread::spawn(move || {
+            let result = resolve_api_root(&server)
+                .and_then(|api_root| login(&api_root, &login_name, &password))
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    /// None 表示没有进行中的登录
+    pub fn get_state(&mut self) -> Option<Status<YggdrasilAccount, (), String>> {
+        if let Some(rx) = &self.rx
+            && let Ok(result) = rx.try_recv()
+        {
+            self.state = Some(match result {
+                Ok(account) => Status::Success(account),
+                Err(e) => Status::Failed(e),
+            });
+        }
+        match &self.state {
+            Some(Status::Success(account)) => Some(Status::Success(account.clone())),
+            Some(Status::Progress(())) => Some(Status::Progress(())),
+            Some(Status::Failed(e)) => Some(Status::Failed(e.clone())),
+            None => None,
+        }
+    }
+
+    /// 取走登录结果并回到初始状态
+    pub fn set_none(&mut self) {
+        self.rx = None;
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Request, Response, TestServer};
+
+    fn profile(id: &str, name: &str) -> Value {
+        json!({ "id": id, "name": name })
+    }
+
+    fn forbidden(message: &str) -> Response {
+        Response::json(403, json!({ "error": "ForbiddenOperationException", "errorMessage": message }))
+    }
+
+    /// 模拟外置登录服务器，API 地址为 /api/yggdrasil，select 为 false 时登录后不选择角色
+    fn start_server(select: bool) -> TestServer {
+        TestServer::start(move |request: &Request| match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => Response::status(200).with_header(API_LOCATION_HEADER, "/api/yggdrasil/"),
+            ("GET", "/api/yggdrasil") => Response::json(200, json!({ "meta": { "serverName": "Test Skin" } })),
+            ("POST", "/api/yggdrasil/authserver/authenticate") => {
+                let body = request.json();
+                assert_eq!(body["agent"]["name"], "Minecraft");
+                if body["password"] != "secret" {
+                    return forbidden("Invalid credentials. Invalid username or password.");
+                }
+                let mut response = json!({
+                    "accessToken": "token-1",
+                    "clientToken": body["clientToken"],
+                    "availableProfiles": [profile("a1", "Alice"), profile("b2", "Bob")],
+                });
+                if select {
+                    response["selectedProfile"] = profile("a1", "Alice");
+                }
+                Response::json(200, response)
+            }
+            ("POST", "/api/yggdrasil/authserver/refresh") => {
+                let body = request.json();
+                if body["accessToken"] == "revoked" {
+                    return forbidden("Invalid token.");
+                }
+                let selected = match body.get("selectedProfile") {
+                    Some(selected) => selected.clone(),
+                    None => profile("a1", "Alice"),
+                };
+                Response::json(200, json!({
+                    "accessToken": "token-2",
+                    "clientToken": body["clientToken"],
+                    "selectedProfile": selected,
+                }))
+            }
+            ("POST", "/api/yggdrasil/authserver/validate") => match request.json()["accessToken"].as_str() {
+                Some("token-2") => Response::status(204),
+                Some("broken") => Response::status(500),
+                _ => forbidden("Invalid token."),
+            },
+            ("POST", "/api/yggdrasil/authserver/invalidate") => Response::status(204),
+            _ => Response::status(404),
+        })
+    }
+
+    #[test]
+    fn join_relative_urls() {
+        assert_eq!(join_url("https://a.com/x/y", "/api"), "https://a.com/api");
+        assert_eq!(join_url("https://a.com/x/y", "api"), "https://a.com/x/api");
+        assert_eq!(join_url("https://a.com", "/api"), "https://a.com/api");
+        assert_eq!(join_url("https://a.com", "api"), "https://a.com/api");
+        assert_eq!(join_url("https://a.com/x", "https://b.com/api"), "https://b.com/api");
+    }
+
+    #[test]
+    fn authenticate_and_resolve() {
+        let server = start_server(true);
+        let api_root = resolve_api_root(&format!("{}/", server.url)).unwrap();
+        assert_eq!(api_root, format!("{}/api/yggdrasil", server.url));
+        // 没有 API 地址指示时使用原地址
+        assert_eq!(resolve_api_root(&api_root).unwrap(), api_root);
+
+        let account = login(&api_root, "alice@example.com", "secret").unwrap();
+        assert_eq!(account.server_name, "Test Skin");
+        assert_eq!(account.login, "alice@example.com");
+        assert_eq!((account.username.as_str(), account.uuid.as_str()), ("Alice", "a1"));
+        assert_eq!(account.profiles.len(), 2);
+        assert_eq!(account.access_token, "token-1");
+        assert_eq!(account.client_token.len(), 32);
+        let requests = server.requests();
+        let authenticate = requests.iter().find(|r| r.path.ends_with("/authenticate")).unwrap();
+        assert_eq!(authenticate.json()["username"], "alice@example.com");
+
+        let e = login(&api_root, "alice@example.com", "wrong").map(|_| ()).unwrap_err();
+        assert_eq!(format!("{:#}", e), "yggdrasil authentication failed: Invalid credentials. Invalid username or password.");
+    }
+
+    #[test]
+    fn login_without_selected_profile() {
+        let server = start_server(false);
+        let api_root = format!("{}/api/yggdrasil", server.url);
+        let account = login(&api_root, "alice", "secret").unwrap();
+        // 使用第一个角色刷新
+        assert_eq!(account.username, "Alice");
+        assert_eq!(account.access_token, "token-2");
+        let refresh = server.requests().into_iter().find(|r| r.path.ends_with("/refresh")).unwrap();
+        assert_eq!(refresh.json()["selectedProfile"], profile("a1", "Alice"));
+    }
+
+    fn account(server: &TestServer, access_token: &str) -> YggdrasilAccount {
+        YggdrasilAccount {
+            api_root: format!("{}/api/yggdrasil", server.url),
+            server_name: "Test Skin".to_string(),
+            login: "alice".to_string(),
+            username: "Alice".to_string(),
+            uuid: "a1".to_string(),
+            profiles: vec![
+                YggdrasilProfile { id: "a1".to_string(), name: "Alice".to_string() },
+                YggdrasilProfile { id: "b2".to_string(), name: "Bob".to_string() },
+            ],
+            client_token: "client".to_string(),
+            access_token: access_token.to_string(),
+        }
+    }
+
+    #[test]
+    fn validate_and_refresh() {
+        let server = start_server(true);
+        assert!(account(&server, "token-2").validate().unwrap());
+        assert!(!account(&server, "token-1").validate().unwrap());
+        assert!(account(&server, "broken").validate().is_err());
+
+        let mut expired = account(&server, "token-1");
+        assert!(expired.refresh_if_needed().unwrap());
+        assert_eq!(expired.access_token, "token-2");
+        assert_eq!(expired.client_token, "client");
+        assert!(!expired.refresh_if_needed().unwrap());
+
+        expired.select_profile("b2").unwrap();
+        assert_eq!((expired.username.as_str(), expired.uuid.as_str()), ("Bob", "b2"));
+        assert!(expired.select_profile("missing").is_err());
+        expired.invalidate().unwrap();
+
+        let mut revoked = account(&server, "revoked");
+        assert!(revoked.refresh(None).unwrap_err().is::<LoginExpired>());
+        assert!(revoked.access_token.is_empty());
+        assert_eq!(revoked.get_status(), AccountStatus::NeedsLogin);
+        assert!(revoked.refresh_if_needed().unwrap_err().is::<LoginExpired>());
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{self, Sender},
     },
@@ -15,10 +16,13 @@ use crate::download::{
     download_url::spawn_download_worker,
 };
 
+#[derive(Clone)]
 pub struct DownloadPool {
     sender: Sender<DownloadEvent>,
     max_workers: Arc<AtomicUsize>,
     pub have_failed: Arc<AtomicBool>,
+    /// 下载失败的保存路径，重新添加任务时移除
+    failed_paths: Arc<Mutex<HashSet<PathBuf>>>,
 }
 
 impl DownloadPool {
@@ -36,6 +40,9 @@ impl DownloadPool {
         let have_failed = Arc::new(AtomicBool::new(false));
         let have_failed_actor = have_failed.clone();
 
+        let failed_paths = Arc::new(Mutex::new(HashSet::new()));
+        let failed_paths_actor = failed_paths.clone();
+
         thread::spawn(move || {
             let mut tasks: Vec<DownloadTask> = Vec::new();
             let mut queue: VecDeque<usize> = VecDeque::new();
@@ -67,6 +74,7 @@ impl DownloadPool {
                             running = running.saturating_sub(1);
                             error!(target: "download_core", "{} download failed: {}", task.url, error);
                             have_failed_actor.store(true, Ordering::Relaxed);
+                            failed_paths_actor.lock().unwrap().insert(task.save_path.clone());
                         }
                     }
 
@@ -181,15 +189,22 @@ impl DownloadPool {
             sender: tx,
             max_workers,
             have_failed,
+            failed_paths,
         }
     }
 
     pub fn add_task(&self, url: String, save_path: String) {
+        self.failed_paths.lock().unwrap().remove(Path::new(&save_path));
         let _ = self
             .sender
             .send(DownloadEvent::AddTask((url, save_path.into())));
     }
 
+    /// 保存到 save_path 的任务是否下载失败
+    pub fn is_failed(&self, save_path: &Path) -> bool {
+        self.failed_paths.lock().unwrap().contains(save_path)
+    }
+
     pub fn stop_all(&self) {
         let _ = self.sender.send(DownloadEvent::StopAll);
     }
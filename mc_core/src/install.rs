@@ -1 +1,2 @@
-pub mod minecraft;
\ No newline at end of file
+pub mod minecraft;
+pub mod verify;
@@ -1,5 +1,10 @@
 use crate::{download::single_downloader::{SingleDownloader, SingleDownloaderEvent}, install::minecraft::version_manifest::MinecraftVersionManifest, statue::Status};
 
+pub mod asset_index;
+pub mod game_root;
+pub mod rule;
+pub mod scan;
+pub mod version_json;
 pub mod version_manifest;
 
 /// 从Mojang获取所有Minecraft版本
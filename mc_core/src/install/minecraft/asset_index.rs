@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::game_root::GameRoot;
+
+/// assets/indexes/<id>.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetIndex {
+    pub objects: HashMap<String, AssetObject>,
+    /// 1.6 之前的版本需要把资源复制到 resources 目录
+    #[serde(default)]
+    pub map_to_resources: bool,
+    #[serde(default, rename = "virtual")]
+    pub virtual_: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetObject {
+    pub hash: String,
+    pub size: u64,
+}
+
+impl AssetIndex {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("failed to read asset index: {:?}", path))?;
+        let index: Self = serde_json::from_str(&content)
+            .context(format!("failed to parse asset index: {:?}", path))?;
+        if let Some((name, _)) = index.objects.iter().find(|(_, object)| object.path().is_none()) {
+            bail!("invalid hash for asset {:?} in asset index: {:?}", name, path);
+        }
+        Ok(index)
+    }
+
+    /// 旧版本按原始文件名读取资源的目录，新版本为 None
+    ///
+    /// map_to_resources 时为 `<game_dir>/resources`，virtual 时为 `assets/virtual/<id>`
+    pub fn legacy_dir(&self, root: &GameRoot, id: &str, game_dir: &Path) -> Option<PathBuf> {
+        if self.map_to_resources {
+            Some(game_dir.join("resources"))
+        } else if self.virtual_ {
+            Some(root.assets_dir().join("virtual").join(id))
+        } else {
+            None
+        }
+    }
+}
+
+/// 把 objects 中的资源按原始文件名复制到旧版本使用的目录，返回复制的文件数
+///
+/// 没有索引文件时什么都不做；已存在且大小一致的文件跳过，带 `..` 或绝对路径的文件名忽略
+pub fn copy_legacy_assets(root: &GameRoot, id: &str, game_dir: &Path) -> Result<usize> {
+    let index_path = root.asset_index(id);
+    if !index_path.is_file() {
+        return Ok(0);
+    }
+    let index = AssetIndex::load(&index_path)?;
+    let Some(dir) = index.legacy_dir(root, id, game_dir) else {
+        return Ok(0);
+    };
+    let mut copied = 0;
+    for (name, object) in &index.objects {
+        let relative = Path::new(name);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            warn!(target: "assets", "skipping unsafe asset name: {:?}", name);
+            continue;
+        }
+        let target = dir.join(relative);
+        if fs::metadata(&target).is_ok_and(|meta| meta.len() == object.size) {
+            continue;
+        }
+        let Some(path) = object.path() else { continue };
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("cannot create directory: {:?}", parent))?;
+        }
+        fs::copy(root.asset_object(&path), &target)
+            .context(format!("failed to copy asset {:?} to {:?}", name, target))?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+impl AssetObject {
+    /// objects 目录下的相对路径，hash 不是 sha1 时为 None
+    pub fn path(&self) -> Option<String> {
+        if self.hash.len() != 40 || !self.hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(format!("{}/{}", self.hash.get(..2)?, self.hash))
+    }
+
+    pub fn url(&self) -> Option<String> {
+        Some(format!(
+            "https://resources.download.minecraft.net/{}",
+            self.path()?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
+
+    fn setup(name: &str, index: &str) -> (GameRoot, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("mctui-assets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let root = GameRoot::new(dir.join("root"));
+        fs::create_dir_all(root.assets_dir().join("indexes")).unwrap();
+        fs::write(root.asset_index("legacy"), index).unwrap();
+        let object = root.asset_object(&format!("bd/{}", HASH));
+        fs::create_dir_all(object.parent().unwrap()).unwrap();
+        fs::write(object, b"sound").unwrap();
+        (root, dir)
+    }
+
+    fn index_json(flags: &str) -> String {
+        format!(
+            r#"{{ {} "objects": {{
+                "sounds/step/grass1.ogg": {{ "hash": "{HASH}", "size": 5 }},
+                "../escape.ogg": {{ "hash": "{HASH}", "size": 5 }}
+            }} }}"#,
+            flags
+        )
+    }
+
+    #[test]
+    fn copies_virtual_assets() {
+        let (root, dir) = setup("virtual", &index_json(r#""virtual": true,"#));
+        let game_dir = dir.join("game");
+        assert_eq!(copy_legacy_assets(&root, "legacy", &game_dir).unwrap(), 1);
+        let target = root.assets_dir().join("virtual/legacy/sounds/step/grass1.ogg");
+        assert_eq!(fs::read(&target).unwrap(), b"sound");
+        assert!(!root.assets_dir().join("virtual/escape.ogg").exists());
+        // 第二次不再复制
+        assert_eq!(copy_legacy_assets(&root, "legacy", &game_dir).unwrap(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn copies_resources_assets() {
+        let (root, dir) = setup("resources", &index_json(r#""map_to_resources": true,"#));
+        let game_dir = dir.join("game");
+        assert_eq!(copy_legacy_assets(&root, "legacy", &game_dir).unwrap(), 1);
+        assert!(game_dir.join("resources/sounds/step/grass1.ogg").is_file());
+        assert!(!root.assets_dir().join("virtual").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skips_modern_and_missing_index() {
+        let (root, dir) = setup("modern", &index_json(""));
+        let game_dir = dir.join("game");
+        assert_eq!(copy_legacy_assets(&root, "legacy", &game_dir).unwrap(), 0);
+        assert_eq!(copy_legacy_assets(&root, "17", &game_dir).unwrap(), 0);
+        assert!(!root.assets_dir().join("virtual").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
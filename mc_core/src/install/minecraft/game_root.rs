@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 一个 .minecraft 目录，存放 versions、libraries 和 assets
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRoot {
+    pub path: PathBuf,
+}
+
+impl GameRoot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn versions_dir(&self) -> PathBuf {
+        self.path.join("versions")
+    }
+
+    pub fn version_dir(&self, id: &str) -> PathBuf {
+        self.versions_dir().join(id)
+    }
+
+    pub fn version_json(&self, id: &str) -> PathBuf {
+        self.version_dir(id).join(format!("{}.json", id))
+    }
+
+    pub fn version_jar(&self, id: &str) -> PathBuf {
+        self.version_dir(id).join(format!("{}.jar", id))
+    }
+
+    /// 解压后的 natives 目录
+    pub fn natives_dir(&self, id: &str) -> PathBuf {
+        self.version_dir(id).join("natives")
+    }
+
+    pub fn libraries_dir(&self) -> PathBuf {
+        self.path.join("libraries")
+    }
+
+    pub fn library(&self, path: &str) -> PathBuf {
+        self.libraries_dir().join(path)
+    }
+
+    pub fn assets_dir(&self) -> PathBuf {
+        self.path.join("assets")
+    }
+
+    pub fn asset_index(&self, id: &str) -> PathBuf {
+        self.assets_dir().join("indexes").join(format!("{}.json", id))
+    }
+
+    pub fn asset_object(&self, path: &str) -> PathBuf {
+        self.assets_dir().join("objects").join(path)
+    }
+
+    pub fn log_config(&self, id: &str) -> PathBuf {
+        self.assets_dir().join("log_configs").join(id)
+    }
+
+    /// 启动器下载的 Java 运行时所在的目录
+    pub fn runtimes_dir(&self) -> PathBuf {
+        self.path.join("runtime")
+    }
+
+    /// 游戏实例所在的目录
+    pub fn instances_dir(&self) -> PathBuf {
+        self.path.join("instances")
+    }
+
+    /// versions 目录下所有存在 <id>/<id>.json 的版本
+    pub fn installed_versions(&self) -> Vec<String> {
+        let mut versions: Vec<String> = fs::read_dir(self.versions_dir())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .filter(|id| self.version_json(id).is_file())
+                    .collect()
+            })
+            .unwrap_or_default();
+        versions.sort();
+        versions
+    }
+}
+
+impl AsRef<Path> for GameRoot {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// 版本 json 中 `rules` 的一条规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<HashMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+}
+
+/// 规则匹配时使用的运行环境
+#[derive(Debug, Clone)]
+pub struct RuleContext {
+    /// windows / osx / linux
+    pub os_name: String,
+    pub os_version: String,
+    /// x86 / x86_64 / arm64 ...
+    pub os_arch: String,
+    /// is_demo_user, has_custom_resolution, is_quick_play_* ...
+    pub features: HashMap<String, bool>,
+}
+
+impl RuleContext {
+    /// 当前系统的运行环境，不开启任何 feature
+    pub fn current() -> Self {
+        let os_name = match std::env::consts::OS {
+            "macos" => "osx",
+            other => other,
+        };
+        let os_arch = match std::env::consts::ARCH {
+            "aarch64" => "arm64",
+            "x86" => "x86",
+            other => other,
+        };
+        Self {
+            os_name: os_name.to_string(),
+            os_version: String::new(),
+            os_arch: os_arch.to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    pub fn with_feature(mut self, name: &str, value: bool) -> Self {
+        self.features.insert(name.to_string(), value);
+        self
+    }
+
+    /// natives 中 `${arch}` 的替换值
+    pub fn arch_bits(&self) -> &'static str {
+        if self.os_arch == "x86" { "32" } else { "64" }
+    }
+}
+
+impl Rule {
+    /// 该规则是否匹配当前环境
+    pub fn matches(&self, ctx: &RuleContext) -> bool {
+        if let Some(os) = &self.os {
+            if let Some(name) = &os.name
+                && name != &ctx.os_name
+            {
+                return false;
+            }
+            if let Some(arch) = &os.arch
+                && arch != &ctx.os_arch
+            {
+                return false;
+            }
+            if let Some(version) = &os.version
+                && !version_matches(version, &ctx.os_version)
+            {
+                return false;
+            }
+        }
+        if let Some(features) = &self.features {
+            for (name, value) in features {
+                if ctx.features.get(name).copied().unwrap_or(false) != *value {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// 按顺序应用规则，最后一条匹配的规则决定结果
+///
+/// 没有规则时默认允许；有规则但都不匹配时默认不允许
+pub fn is_allowed(rules: &[Rule], ctx: &RuleContext) -> bool {
+    if rules.is_empty() {
+        return true;
+    }
+    let mut allowed = false;
+    for rule in rules {
+        if rule.matches(ctx) {
+            allowed = rule.action == RuleAction::Allow;
+        }
+    }
+    allowed
+}
+
+/// os.version 是一个正则，实际只出现过 `^10\.` 这样的前缀匹配
+fn version_matches(pattern: &str, version: &str) -> bool {
+    let prefix = pattern.trim_start_matches('^').replace("\\.", ".");
+    version.starts_with(&prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> Vec<Rule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn context(os_name: &str, os_version: &str, os_arch: &str) -> RuleContext {
+        RuleContext {
+            os_name: os_name.to_string(),
+            os_version: os_version.to_string(),
+            os_arch: os_arch.to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn empty_rules_allow() {
+        assert!(is_allowed(&[], &context("linux", "", "x86_64")));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        // 旧版本 natives 的写法：除 osx 外都允许
+        let rules = rules(r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#);
+        assert!(is_allowed(&rules, &context("linux", "", "x86_64")));
+        assert!(!is_allowed(&rules, &context("osx", "", "arm64")));
+    }
+
+    #[test]
+    fn no_matching_rule_disallows() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "windows"}}]"#);
+        assert!(is_allowed(&rules, &context("windows", "", "x86_64")));
+        assert!(!is_allowed(&rules, &context("linux", "", "x86_64")));
+    }
+
+    #[test]
+    fn os_version_and_arch() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\.", "arch": "x86"}}]"#);
+        assert!(is_allowed(&rules, &context("windows", "10.0", "x86")));
+        assert!(!is_allowed(&rules, &context("windows", "6.1", "x86")));
+        assert!(!is_allowed(&rules, &context("windows", "10.0", "x86_64")));
+    }
+
+    #[test]
+    fn features() {
+        let rules = rules(r#"[{"action": "allow", "features": {"has_custom_resolution": true}}]"#);
+        let ctx = context("linux", "", "x86_64");
+        assert!(!is_allowed(&rules, &ctx));
+        assert!(is_allowed(&rules, &ctx.clone().with_feature("has_custom_resolution", true)));
+        assert!(!is_allowed(&rules, &ctx.with_feature("has_custom_resolution", false)));
+    }
+
+    #[test]
+    fn arch_bits() {
+        assert_eq!(context("windows", "", "x86").arch_bits(), "32");
+        assert_eq!(context("linux", "", "x86_64").arch_bits(), "64");
+    }
+}
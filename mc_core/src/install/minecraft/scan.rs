@@ -0,0 +1,333 @@
+//! 扫描其它启动器使用的 .minecraft 目录，识别其中已安装的版本
+//!
+//! 版本类型从版本 json 的库、主类和启动参数中识别，不需要读取 jar
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    install::minecraft::{
+        game_root::GameRoot,
+        version_json::{Argument, VersionJson},
+    },
+    instance::{LoaderKind, ModLoader},
+};
+
+/// 扫描到的一个已安装版本
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedVersion {
+    /// 版本所在的游戏目录
+    pub root: PathBuf,
+    pub id: String,
+    /// 对应的 Minecraft 版本
+    pub minecraft_version: String,
+    /// 为空时是原版
+    pub loader: Option<ModLoader>,
+    /// release、snapshot、old_beta 等
+    pub release_type: String,
+    /// 版本 json 可以合并且客户端 jar 存在
+    pub launchable: bool,
+}
+
+/// 启动器登记的其它游戏目录，启动器自己的目录不在其中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameRoots {
+    pub roots: Vec<PathBuf>,
+}
+
+impl GameRoots {
+    /// 登记本机上官方启动器的目录
+    pub fn discover() -> Self {
+        Self { roots: default_roots() }
+    }
+
+    /// 登记一个游戏目录，已经登记过时返回 false
+    pub fn add(&mut self, path: &Path) -> Result<bool> {
+        if !is_game_root(path) {
+            bail!("not a minecraft directory: {:?}", path);
+        }
+        let path = fs::canonicalize(path).context(format!("cannot open {:?}", path))?;
+        if self.roots.contains(&path) {
+            return Ok(false);
+        }
+        info!(target: "scan", "game root added: {:?}", path);
+        self.roots.push(path);
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let len = self.roots.len();
+        self.roots.retain(|root| root != path);
+        self.roots.len() != len
+    }
+
+    /// 扫描所有登记的目录，已经不存在的目录会被跳过
+    pub fn scan(&self) -> Vec<ScannedVersion> {
+        self.roots
+            .iter()
+            .filter(|root| root.is_dir())
+            .flat_map(|root| scan_root(&GameRoot::new(root)))
+            .collect()
+    }
+}
+
+/// 包含 versions 目录的才是游戏目录
+pub fn is_game_root(path: &Path) -> bool {
+    path.join("versions").is_dir()
+}
+
+/// 官方启动器在当前系统上的默认目录，只返回存在的目录
+pub fn default_roots() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(appdata) = env::var_os("APPDATA") {
+        candidates.push(PathBuf::from(appdata).join(".minecraft"));
+    }
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        candidates.push(home.join(".minecraft"));
+        candidates.push(home.join("Library/Application Support/minecraft"));
+        // Flatpak 版官方启动器
+        candidates.push(home.join(".var/app/com.mojang.Minecraft/.minecraft"));
+    }
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for candidate in candidates.into_iter().filter(|path| is_game_root(path)) {
+        if let Ok(path) = fs::canonicalize(&candidate)
+            && !roots.contains(&path)
+        {
+            roots.push(path);
+        }
+    }
+    roots
+}
+
+/// 扫描一个游戏目录中的所有版本，无法解析的版本会被跳过
+pub fn scan_root(root: &GameRoot) -> Vec<ScannedVersion> {
+    let versions: Vec<ScannedVersion> = root
+        .installed_versions()
+        .iter()
+        .filter_map(|id| match detect_version(root, id) {
+            Ok(version) => Some(version),
+            Err(e) => {
+                warn!(target: "scan", "skip version {} in {:?}: {:#}", id, root.path, e);
+                None
+            }
+        })
+        .collect();
+    info!(target: "scan", "found {} versions in {:?}", versions.len(), root.path);
+    versions
+}
+
+/// 在 root 中查找已安装的对应版本，加载器版本为空时只比较类型
+pub fn find_version(
+    root: &GameRoot,
+    minecraft_version: &str,
+    loader: Option<&ModLoader>,
+) -> Option<ScannedVersion> {
+    scan_root(root).into_iter().find(|version| {
+        version.launchable
+            && version.minecraft_version == minecraft_version
+            && match (&version.loader, loader) {
+                (None, None) => true,
+                (Some(found), Some(wanted)) => {
+                    found.kind == wanted.kind
+                        && (wanted.version.is_empty() || found.version == wanted.version)
+                }
+                _ => false,
+            }
+    })
+}
+
+/// 读取版本 json 并识别版本类型
+pub fn detect_version(root: &GameRoot, id: &str) -> Result<ScannedVersion> {
+    let raw = VersionJson::load(&root.version_json(id))?;
+    // 缺少 inheritsFrom 的父版本时仍然可以识别类型，只是不能启动
+    let effective = VersionJson::load_effective(root, id).ok();
+    let profile = effective.as_ref().unwrap_or(&raw);
+    let launchable = effective
+        .as_ref()
+        .is_some_and(|profile| root.version_jar(profile.jar_id()).is_file());
+
+    let minecraft_version = raw
+        .inherits_from
+        .clone()
+        .or_else(|| raw.jar.clone())
+        .or_else(|| argument_value(profile, "--fml.mcVersion"))
+        .unwrap_or_else(|| id.to_string());
+    let release_type = match raw.type_.as_str() {
+        "" => profile.type_.clone(),
+        type_ => type_.to_string(),
+    };
+    Ok(ScannedVersion {
+        root: root.path.clone(),
+        id: id.to_string(),
+        loader: detect_loader(profile, &minecraft_version),
+        minecraft_version,
+        release_type,
+        launchable,
+    })
+}
+
+/// 从库、启动参数和主类中识别模组加载器
+pub fn detect_loader(profile: &VersionJson, minecraft_version: &str) -> Option<ModLoader> {
+    let library = |group: &str, artifact: &str| {
+        profile.libraries.iter().find_map(|library| {
+            let mut parts = library.name.split(':');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(g), Some(a), Some(version)) if g == group && a == artifact => {
+                    Some(version.to_string())
+                }
+                _ => None,
+            }
+        })
+    };
+    let loader = |kind: LoaderKind, version: String| Some(ModLoader { kind, version });
+    // Forge 的库版本为 <mc>-<forge>
+    let strip_minecraft = |version: String| {
+        version
+            .strip_prefix(&format!("{}-", minecraft_version))
+            .map(|v| v.to_string())
+            .unwrap_or(version)
+    };
+
+    if let Some(version) = library("net.fabricmc", "fabric-loader") {
+        return loader(LoaderKind::Fabric, version);
+    }
+    if let Some(version) = library("org.quiltmc", "quilt-loader") {
+        return loader(LoaderKind::Quilt, version);
+    }
+    if let Some(version) = argument_value(profile, "--fml.neoForgeVersion")
+        .or_else(|| library("net.neoforged", "neoforge"))
+    {
+        return loader(LoaderKind::NeoForge, version);
+    }
+    // 1.20.1 的 NeoForge 沿用了 Forge 的参数
+    if let Some(version) = library("net.neoforged", "forge") {
+        return loader(LoaderKind::NeoForge, strip_minecraft(version));
+    }
+    if let Some(version) = argument_value(profile, "--fml.forgeVersion")
+        .or_else(|| library("net.minecraftforge", "forge").map(strip_minecraft))
+        .or_else(|| library("net.minecraftforge", "fmlloader").map(strip_minecraft))
+    {
+        return loader(LoaderKind::Forge, version);
+    }
+    if let Some(version) = library("com.mumfrey", "liteloader") {
+        return loader(LoaderKind::LiteLoader, version);
+    }
+    if let Some(version) = library("optifine", "OptiFine") {
+        // OptiFine 的版本为 <mc>_<edition>
+        let version = version
+            .strip_prefix(&format!("{}_", minecraft_version))
+            .map(|v| v.to_string())
+            .unwrap_or(version);
+        return loader(LoaderKind::OptiFine, version);
+    }
+
+    match profile.main_class.as_str() {
+        "net.fabricmc.loader.impl.launch.knot.KnotClient"
+        | "net.fabricmc.loader.launch.knot.KnotClient" => {
+            loader(LoaderKind::Fabric, String::new())
+        }
+        "org.quiltmc.loader.impl.launch.knot.KnotClient" => {
+            loader(LoaderKind::Quilt, String::new())
+        }
+        "cpw.mods.modlauncher.Launcher" | "cpw.mods.bootstraplauncher.BootstrapLauncher" => {
+            loader(LoaderKind::Forge, String::new())
+        }
+        _ if profile
+            .minecraft_arguments
+            .as_deref()
+            .is_some_and(|args| args.contains("cpw.mods.fml.common.launcher.FMLTweaker")) =>
+        {
+            loader(LoaderKind::Forge, String::new())
+        }
+        _ => None,
+    }
+}
+
+/// 游戏参数中 name 后面的值，例如 `--fml.forgeVersion 47.2.0`
+fn argument_value(profile: &VersionJson, name: &str) -> Option<String> {
+    let game = &profile.arguments.as_ref()?.game;
+    let mut plain = game.iter().filter_map(|argument| match argument {
+        Argument::Plain(value) => Some(value.as_str()),
+        _ => None,
+    });
+    plain.find(|value| *value == name)?;
+    plain.next().map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(json: &str) -> VersionJson {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn detect(json: &str) -> Option<(LoaderKind, String)> {
+        detect_loader(&profile(json), "1.20.1").map(|loader| (loader.kind, loader.version))
+    }
+
+    #[test]
+    fn loader_from_libraries() {
+        assert_eq!(
+            detect(r#"{"id": "fabric", "libraries": [{"name": "net.fabricmc:fabric-loader:0.15.0"}]}"#),
+            Some((LoaderKind::Fabric, "0.15.0".to_string()))
+        );
+        assert_eq!(
+            detect(r#"{"id": "quilt", "libraries": [{"name": "org.quiltmc:quilt-loader:0.23.0"}]}"#),
+            Some((LoaderKind::Quilt, "0.23.0".to_string()))
+        );
+        assert_eq!(
+            detect(r#"{"id": "forge", "libraries": [{"name": "net.minecraftforge:forge:1.20.1-47.2.0:universal"}]}"#),
+            Some((LoaderKind::Forge, "47.2.0".to_string()))
+        );
+        assert_eq!(
+            detect(r#"{"id": "neoforge", "libraries": [{"name": "net.neoforged:forge:1.20.1-47.1.79"}]}"#),
+            Some((LoaderKind::NeoForge, "47.1.79".to_string()))
+        );
+        assert_eq!(
+            detect(r#"{"id": "optifine", "libraries": [{"name": "optifine:OptiFine:1.20.1_HD_U_I6"}]}"#),
+            Some((LoaderKind::OptiFine, "HD_U_I6".to_string()))
+        );
+    }
+
+    #[test]
+    fn loader_from_arguments() {
+        assert_eq!(
+            detect(r#"{"id": "forge", "arguments": {"game": ["--fml.forgeVersion", "47.2.0"]}}"#),
+            Some((LoaderKind::Forge, "47.2.0".to_string()))
+        );
+        assert_eq!(
+            detect(r#"{"id": "neoforge", "arguments": {"game": ["--fml.neoForgeVersion", "20.4.190"]}}"#),
+            Some((LoaderKind::NeoForge, "20.4.190".to_string()))
+        );
+    }
+
+    #[test]
+    fn loader_from_main_class() {
+        assert_eq!(
+            detect(r#"{"id": "fabric", "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient"}"#),
+            Some((LoaderKind::Fabric, String::new()))
+        );
+        assert_eq!(
+            detect(
+                r#"{"id": "1.7.10-Forge", "mainClass": "net.minecraft.launchwrapper.Launch",
+                    "minecraftArguments": "--tweakClass cpw.mods.fml.common.launcher.FMLTweaker"}"#
+            ),
+            Some((LoaderKind::Forge, String::new()))
+        );
+        assert_eq!(
+            detect(
+                r#"{"id": "1.20.1", "mainClass": "net.minecraft.client.main.Main",
+                    "libraries": [{"name": "org.ow2.asm:asm:9.3"}]}"#
+            ),
+            None
+        );
+    }
+}
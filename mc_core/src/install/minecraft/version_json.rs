@@ -0,0 +1,450 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::install::minecraft::{
+    game_root::GameRoot,
+    rule::{Rule, RuleContext, is_allowed},
+};
+
+/// versions/<id>/<id>.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJson {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    /// 使用哪个版本的客户端 jar，缺省为自身 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jar: Option<String>,
+    #[serde(default, rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub main_class: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    /// 1.13 之前的启动参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<VersionDownloads>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndexInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Logging>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional { rules: Vec<Rule>, value: ArgumentValue },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl Argument {
+    /// 应用规则后得到的参数
+    pub fn values(&self, ctx: &RuleContext) -> Vec<&str> {
+        match self {
+            Argument::Plain(value) => vec![value.as_str()],
+            Argument::Conditional { rules, value } => {
+                if !is_allowed(rules, ctx) {
+                    return Vec::new();
+                }
+                match value {
+                    ArgumentValue::Single(value) => vec![value.as_str()],
+                    ArgumentValue::Many(values) => values.iter().map(|v| v.as_str()).collect(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionDownloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<DownloadInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<DownloadInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndexInfo {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    #[serde(default)]
+    pub component: String,
+    pub major_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logging {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<LoggingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// 例如 `-Dlog4j.configurationFile=${path}`
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(default, rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingFile {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    /// maven 坐标 group:artifact:version[:classifier][@ext]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<LibraryDownloads>,
+    /// 没有 downloads 时使用的 maven 仓库地址（Fabric 等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 旧版 natives，os 名 -> classifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natives: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<Extract>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryDownloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<DownloadInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifiers: Option<HashMap<String, DownloadInfo>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extract {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl Library {
+    pub fn is_allowed(&self, ctx: &RuleContext) -> bool {
+        self.rules.as_deref().map(|rules| is_allowed(rules, ctx)).unwrap_or(true)
+    }
+
+    /// 新版本中 natives 作为单独的库出现，classifier 以 natives- 开头
+    pub fn is_native(&self) -> bool {
+        self.natives.is_some()
+            || self
+                .name
+                .split(':')
+                .nth(3)
+                .is_some_and(|classifier| classifier.starts_with("natives-"))
+    }
+
+    /// 需要加入 classpath 的主 artifact
+    pub fn artifact(&self) -> Option<DownloadInfo> {
+        if let Some(downloads) = &self.downloads {
+            return downloads.artifact.clone();
+        }
+        if self.natives.is_some() {
+            return None;
+        }
+        let path = maven_path(&self.name)?;
+        let base = self
+            .url
+            .as_deref()
+            .unwrap_or("https://libraries.minecraft.net/");
+        Some(DownloadInfo {
+            url: format!("{}/{}", base.trim_end_matches('/'), path),
+            path: Some(path),
+            sha1: None,
+            size: None,
+        })
+    }
+
+    /// 旧版 natives 在当前系统对应的 artifact
+    pub fn native_artifact(&self, ctx: &RuleContext) -> Option<DownloadInfo> {
+        let classifier = self
+            .natives
+            .as_ref()?
+            .get(&ctx.os_name)?
+            .replace("${arch}", ctx.arch_bits());
+        self.downloads
+            .as_ref()?
+            .classifiers
+            .as_ref()?
+            .get(&classifier)
+            .cloned()
+    }
+}
+
+/// 将 maven 坐标转换为相对路径
+pub fn maven_path(name: &str) -> Option<String> {
+    let (coords, ext) = match name.split_once('@') {
+        Some((coords, ext)) => (coords, ext),
+        None => (name, "jar"),
+    };
+    let parts: Vec<&str> = coords.split(':').collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    let (group, artifact, version) = (parts[0], parts[1], parts[2]);
+    let file = match parts.get(3) {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, ext),
+        None => format!("{}-{}.{}", artifact, version, ext),
+    };
+    Some(format!(
+        "{}/{}/{}/{}",
+        group.replace('.', "/"),
+        artifact,
+        version,
+        file
+    ))
+}
+
+impl VersionJson {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("failed to read version json: {:?}", path))?;
+        serde_json::from_str(&content).context(format!("failed to parse version json: {:?}", path))
+    }
+
+    /// 客户端 jar 所属的版本 id
+    pub fn jar_id(&self) -> &str {
+        self.jar.as_deref().unwrap_or(&self.id)
+    }
+
+    /// 资源索引 id，旧版本只有 assets 字段
+    pub fn assets_id(&self) -> &str {
+        self.asset_index
+            .as_ref()
+            .map(|index| index.id.as_str())
+            .or(self.assets.as_deref())
+            .unwrap_or("legacy")
+    }
+
+    /// 读取版本并沿 inheritsFrom 合并，得到实际用于安装、校验和启动的版本信息
+    pub fn load_effective(root: &GameRoot, id: &str) -> Result<Self> {
+        let mut chain = vec![Self::load(&root.version_json(id))?];
+        while let Some(parent) = chain.last().unwrap().inherits_from.clone() {
+            if chain.iter().any(|v| v.id == parent) || chain.len() > 8 {
+                bail!("inheritsFrom loop in version {}", id);
+            }
+            chain.push(Self::load(&root.version_json(&parent))?);
+        }
+
+        let mut effective = chain.pop().unwrap();
+        while let Some(child) = chain.pop() {
+            effective = child.inherit(effective);
+        }
+        Ok(effective)
+    }
+
+    /// 以 parent 为基础合并 self
+    fn inherit(self, parent: VersionJson) -> VersionJson {
+        let jar = self
+            .jar
+            .or_else(|| {
+                let own_client = self.downloads.as_ref().and_then(|d| d.client.as_ref());
+                own_client.is_none().then(|| parent.jar_id().to_string())
+            });
+
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent), Some(child)) => {
+                parent.game.extend(child.game);
+                parent.jvm.extend(child.jvm);
+                Some(parent)
+            }
+            (parent, child) => child.or(parent),
+        };
+
+        // 子版本的库优先，同名同 classifier 的库只保留子版本
+        let mut libraries = self.libraries;
+        let keys: Vec<String> = libraries.iter().map(|l| library_key(&l.name)).collect();
+        libraries.extend(
+            parent
+                .libraries
+                .into_iter()
+                .filter(|l| !keys.contains(&library_key(&l.name))),
+        );
+
+        VersionJson {
+            id: self.id,
+            inherits_from: None,
+            jar,
+            type_: if self.type_.is_empty() { parent.type_ } else { self.type_ },
+            main_class: if self.main_class.is_empty() {
+                parent.main_class
+            } else {
+                self.main_class
+            },
+            arguments,
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            libraries,
+            downloads: self.downloads.or(parent.downloads),
+            asset_index: self.asset_index.or(parent.asset_index),
+            assets: self.assets.or(parent.assets),
+            java_version: self.java_version.or(parent.java_version),
+            logging: self.logging.or(parent.logging),
+            release_time: self.release_time.or(parent.release_time),
+            time: self.time.or(parent.time),
+        }
+    }
+}
+
+/// group:artifact[:classifier]，用于判断两个库是否是同一个库的不同版本
+fn library_key(name: &str) -> String {
+    let coords = name.split('@').next().unwrap_or(name);
+    let parts: Vec<&str> = coords.split(':').collect();
+    match parts.as_slice() {
+        [group, artifact, _, classifier, ..] => format!("{}:{}:{}", group, artifact, classifier),
+        [group, artifact, ..] => format!("{}:{}", group, artifact),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(json: &str) -> VersionJson {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn vanilla() -> VersionJson {
+        version(
+            r#"{
+                "id": "1.20.1",
+                "type": "release",
+                "mainClass": "net.minecraft.client.main.Main",
+                "arguments": {"game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"]},
+                "libraries": [{"name": "org.ow2.asm:asm:9.3"}, {"name": "com.google.guava:guava:31.1-jre"}],
+                "downloads": {"client": {"url": "https://example.com/client.jar"}},
+                "assetIndex": {"id": "5", "url": "https://example.com/5.json"},
+                "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17}
+            }"#,
+        )
+    }
+
+    #[test]
+    fn library_keys() {
+        assert_eq!(library_key("org.ow2.asm:asm:9.6"), "org.ow2.asm:asm");
+        assert_eq!(library_key("org.lwjgl:lwjgl:3.3.1:natives-linux"), "org.lwjgl:lwjgl:natives-linux");
+        assert_eq!(library_key("net.fabricmc:intermediary:1.20.1@jar"), "net.fabricmc:intermediary");
+    }
+
+    #[test]
+    fn child_inherits_parent() {
+        let child = version(
+            r#"{
+                "id": "fabric-loader-0.15.0-1.20.1",
+                "inheritsFrom": "1.20.1",
+                "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                "arguments": {"jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]},
+                "libraries": [{"name": "org.ow2.asm:asm:9.6"}, {"name": "net.fabricmc:fabric-loader:0.15.0"}]
+            }"#,
+        );
+        let merged = child.inherit(vanilla());
+        assert_eq!(merged.id, "fabric-loader-0.15.0-1.20.1");
+        assert_eq!(merged.inherits_from, None);
+        // 没有自己的客户端时使用父版本的 jar
+        assert_eq!(merged.jar_id(), "1.20.1");
+        assert_eq!(merged.type_, "release");
+        assert_eq!(merged.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        let arguments = merged.arguments.as_ref().unwrap();
+        assert_eq!(arguments.game.len(), 2);
+        assert_eq!(arguments.jvm.len(), 3);
+        let names: Vec<&str> = merged.libraries.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["org.ow2.asm:asm:9.6", "net.fabricmc:fabric-loader:0.15.0", "com.google.guava:guava:31.1-jre"]
+        );
+        assert_eq!(merged.assets_id(), "5");
+        assert_eq!(merged.java_version.unwrap().major_version, 17);
+    }
+
+    #[test]
+    fn child_with_own_client_keeps_own_jar() {
+        let child = version(
+            r#"{"id": "custom", "inheritsFrom": "1.20.1", "downloads": {"client": {"url": "https://example.com/custom.jar"}}}"#,
+        );
+        assert_eq!(child.inherit(vanilla()).jar_id(), "custom");
+    }
+
+    #[test]
+    fn load_effective_detects_loops() {
+        let base = std::env::temp_dir().join(format!("mctui-version-json-{}", std::process::id()));
+        let root = GameRoot::new(&base);
+        for (id, parent) in [("a", "b"), ("b", "a"), ("c", "1.20.1")] {
+            fs::create_dir_all(root.version_dir(id)).unwrap();
+            fs::write(root.version_json(id), format!(r#"{{"id": "{}", "inheritsFrom": "{}"}}"#, id, parent)).unwrap();
+        }
+        fs::create_dir_all(root.version_dir("1.20.1")).unwrap();
+        fs::write(root.version_json("1.20.1"), serde_json::to_string(&vanilla()).unwrap()).unwrap();
+
+        assert!(VersionJson::load_effective(&root, "a").is_err());
+        let merged = VersionJson::load_effective(&root, "c").unwrap();
+        assert_eq!(merged.main_class, "net.minecraft.client.main.Main");
+        assert_eq!(merged.jar_id(), "1.20.1");
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
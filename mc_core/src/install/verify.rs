@@ -0,0 +1,703 @@
+//! 校验已安装版本的游戏文件，并重新下载缺失或损坏的文件
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use log::info;
+
+use crate::{
+    download::download_pool::DownloadPool,
+    install::minecraft::{
+        asset_index::AssetIndex, game_root::GameRoot, rule::RuleContext,
+        version_json::VersionJson,
+    },
+    statue::Status,
+};
+
+/// 启动前补全文件时，超过这个时间没有文件下载完就放弃
+const DOWNLOAD_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameFileKind {
+    Client,
+    Library,
+    Native,
+    AssetIndex,
+    Asset,
+    LogConfig,
+}
+
+/// 一个需要校验的文件
+#[derive(Debug, Clone)]
+pub struct GameFile {
+    pub kind: GameFileKind,
+    pub path: PathBuf,
+    pub url: String,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileProblem {
+    Missing,
+    SizeMismatch { expected: u64, actual: u64 },
+    HashMismatch { expected: String, actual: String },
+    Unreadable(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub version_id: String,
+    /// 校验过的文件数量
+    pub checked: usize,
+    pub problems: Vec<(GameFile, FileProblem)>,
+    /// 资源索引本身有问题时无法列出资源文件
+    pub assets_skipped: bool,
+}
+
+/// 列出版本需要的所有文件（资源文件除外）
+pub fn game_files(root: &GameRoot, profile: &VersionJson, ctx: &RuleContext) -> Vec<GameFile> {
+    let mut files = Vec::new();
+
+    if let Some(client) = profile.downloads.as_ref().and_then(|d| d.client.as_ref()) {
+        files.push(GameFile {
+            kind: GameFileKind::Client,
+            path: root.version_jar(profile.jar_id()),
+            url: client.url.clone(),
+            sha1: client.sha1.clone(),
+            size: client.size,
+        });
+    }
+
+    for library in profile.libraries.iter().filter(|l| l.is_allowed(ctx)) {
+        let kind = if library.is_native() {
+            GameFileKind::Native
+        } else {
+            GameFileKind::Library
+        };
+        let artifacts = [library.artifact(), library.native_artifact(ctx)];
+        for artifact in artifacts.into_iter().flatten() {
+            let Some(path) = artifact.path.clone() else {
+                continue;
+            };
+            files.push(GameFile {
+                kind,
+                path: root.library(&path),
+                url: artifact.url,
+                sha1: artifact.sha1,
+                size: artifact.size,
+            });
+        }
+    }
+
+    if let Some(index) = &profile.asset_index {
+        files.push(GameFile {
+            kind: GameFileKind::AssetIndex,
+            path: root.asset_index(&index.id),
+            url: index.url.clone(),
+            sha1: index.sha1.clone(),
+            size: index.size,
+        });
+    }
+
+    if let Some(config) = profile.logging.as_ref().and_then(|l| l.client.as_ref()) {
+        files.push(GameFile {
+            kind: GameFileKind::LogConfig,
+            path: root.log_config(&config.file.id),
+            url: config.file.url.clone(),
+            sha1: config.file.sha1.clone(),
+            size: config.file.size,
+        });
+    }
+
+    files
+}
+
+/// 资源索引中列出的资源文件
+pub fn asset_files(root: &GameRoot, index: &AssetIndex) -> Vec<GameFile> {
+    index
+        .objects
+        .values()
+        .filter_map(|object| {
+            Some(GameFile {
+                kind: GameFileKind::Asset,
+                path: root.asset_object(&object.path()?),
+                url: object.url()?,
+                sha1: Some(object.hash.clone()),
+                size: Some(object.size),
+            })
+        })
+        .collect()
+}
+
+/// 计算文件的 sha1
+pub fn file_sha1(path: &std::path::Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = sha1_smol::Sha1::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.digest().to_string())
+}
+
+/// 校验单个文件，先比较大小再比较 sha1
+pub fn check_file(file: &GameFile) -> Option<FileProblem> {
+    let metadata = match fs::metadata(&file.path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Some(FileProblem::Missing),
+    };
+    if let Some(expected) = file.size
+        && metadata.len() != expected
+    {
+        return Some(FileProblem::SizeMismatch {
+            expected,
+            actual: metadata.len(),
+        });
+    }
+    if let Some(expected) = &file.sha1 {
+        match file_sha1(&file.path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => {}
+            Ok(actual) => {
+                return Some(FileProblem::HashMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+            Err(e) => return Some(FileProblem::Unreadable(e.to_string())),
+        }
+    }
+    None
+}
+
+/// 使用多个线程校验文件，checked 记录已经校验的数量
+pub fn verify_files(
+    files: Vec<GameFile>,
+    threads: usize,
+    checked: &AtomicUsize,
+) -> Vec<(GameFile, FileProblem)> {
+    let next = AtomicUsize::new(0);
+    let problems = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let id = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(id) else {
+                        break;
+                    };
+                    if let Some(problem) = check_file(file) {
+                        problems.lock().unwrap().push((file.clone(), problem));
+                    }
+                    checked.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    problems.into_inner().unwrap()
+}
+
+/// 校验一个已安装的版本
+pub fn verify_version(root: &GameRoot, id: &str, threads: usize) -> Result<VerifyReport> {
+    verify_version_with_progress(root, id, threads, &AtomicUsize::new(0), &AtomicUsize::new(0))
+}
+
+fn verify_version_with_progress(
+    root: &GameRoot,
+    id: &str,
+    threads: usize,
+    checked: &AtomicUsize,
+    total: &AtomicUsize,
+) -> Result<VerifyReport> {
+    let profile = VersionJson::load_effective(root, id)?;
+    let ctx = RuleContext::current();
+
+    let files = game_files(root, &profile, &ctx);
+    let index_file = files
+        .iter()
+        .find(|file| file.kind == GameFileKind::AssetIndex)
+        .cloned();
+    total.store(files.len(), Ordering::Relaxed);
+    let mut problems = verify_files(files, threads, checked);
+
+    // 资源索引正常时才继续校验资源文件
+    let index_broken = problems
+        .iter()
+        .any(|(file, _)| file.kind == GameFileKind::AssetIndex);
+    let mut assets_skipped = index_broken;
+    if let Some(index_file) = index_file
+        && !index_broken
+    {
+        match AssetIndex::load(&index_file.path) {
+            Ok(index) => {
+                let assets = asset_files(root, &index);
+                total.fetch_add(assets.len(), Ordering::Relaxed);
+                problems.extend(verify_files(assets, threads, checked));
+            }
+            // sha1 正确但内容无效时也需要重新下载
+            Err(e) => {
+                problems.push((index_file, FileProblem::Unreadable(format!("{:#}", e))));
+                assets_skipped = true;
+            }
+        }
+    }
+
+    let report = VerifyReport {
+        version_id: id.to_string(),
+        checked: checked.load(Ordering::Relaxed),
+        problems,
+        assets_skipped,
+    };
+    info!(target: "verify", "verified {}: {} files, {} problems", id, report.checked, report.problems.len());
+    Ok(report)
+}
+
+/// 启动前的快速检查，游戏文件完整校验，资源文件只比较大小
+pub fn check_launch_files(
+    root: &GameRoot,
+    profile: &VersionJson,
+    threads: usize,
+) -> Vec<(GameFile, FileProblem)> {
+    let checked = AtomicUsize::new(0);
+    let mut problems = verify_files(game_files(root, profile, &RuleContext::current()), threads, &checked);
+    let index_broken = problems
+        .iter()
+        .any(|(file, _)| file.kind == GameFileKind::AssetIndex);
+    if profile.asset_index.is_some()
+        && !index_broken
+        && let Ok(index) = AssetIndex::load(&root.asset_index(profile.assets_id()))
+    {
+        let assets = asset_files(root, &index)
+            .into_iter()
+            .map(|file| GameFile { sha1: None, ..file })
+            .collect();
+        problems.extend(verify_files(assets, threads, &checked));
+    }
+    problems
+}
+
+/// 在当前线程中下载一个文件并校验，用于启动前补全少量文件
+pub fn download_file(file: &GameFile) -> Result<()> {
+    if file.url.is_empty() {
+        bail!("missing file without download url: {:?}", file.path);
+    }
+    if let Some(parent) = file.path.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    let bytes = attohttpc::get(&file.url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .context(format!("failed to download {}", file.url))?;
+    fs::write(&file.path, bytes).context(format!("failed to write {:?}", file.path))?;
+    if let Some(problem) = check_file(file) {
+        bail!("downloaded file is invalid: {:?} {:?}", file.path, problem);
+    }
+    Ok(())
+}
+
+/// 把缺失或损坏的文件加入下载池并等待下载完成，用于启动前补全文件
+pub fn download_problems(problems: &[(GameFile, FileProblem)], pool: &DownloadPool) -> Result<()> {
+    for (file, problem) in problems {
+        if !queue_repair(file, problem, pool)? {
+            bail!("missing file without download url: {:?}", file.path);
+        }
+    }
+    let mut pending: Vec<&GameFile> = problems.iter().map(|(file, _)| file).collect();
+    let mut last_progress = Instant::now();
+    while !pending.is_empty() {
+        if let Some(file) = pending.iter().find(|file| pool.is_failed(&file.path)) {
+            bail!("failed to download {}", file.url);
+        }
+        let before = pending.len();
+        let mut invalid = None;
+        pending.retain(|file| match check_file(file) {
+            None => false,
+            // 大小正确说明已下载完，哈希不对就是文件有问题
+            Some(problem @ FileProblem::HashMismatch { .. }) if file.size.is_some() => {
+                invalid = Some((file.path.clone(), problem));
+                false
+            }
+            Some(_) => true,
+        });
+        if let Some((path, problem)) = invalid {
+            bail!("downloaded file is invalid: {:?} {:?}", path, problem);
+        }
+        if pending.len() != before {
+            last_progress = Instant::now();
+        } else if last_progress.elapsed() > DOWNLOAD_STALL_TIMEOUT {
+            bail!("download stalled: {}", pending[0].url);
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    Ok(())
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty() && !self.assets_skipped
+    }
+
+    pub fn count(&self, kind: GameFileKind) -> usize {
+        self.problems.iter().filter(|(file, _)| file.kind == kind).count()
+    }
+
+    pub fn missing(&self) -> usize {
+        self.problems
+            .iter()
+            .filter(|(_, problem)| *problem == FileProblem::Missing)
+            .count()
+    }
+
+    pub fn corrupt(&self) -> usize {
+        self.problems.len() - self.missing()
+    }
+
+    /// 删除损坏的文件，并通过下载池重新下载有问题的文件，返回添加的任务数
+    ///
+    /// 资源索引有问题时先等待下载池重新下载索引，再校验其中的资源文件
+    pub fn repair(&self, root: &GameRoot, pool: &DownloadPool, threads: usize) -> Result<usize> {
+        let mut count = 0;
+        let mut index_problem = None;
+        for (file, problem) in &self.problems {
+            if file.kind == GameFileKind::AssetIndex {
+                index_problem = Some((file.clone(), problem.clone()));
+            } else if queue_repair(file, problem, pool)? {
+                count += 1;
+            }
+        }
+
+        if let Some((file, problem)) = index_problem {
+            download_problems(&[(file.clone(), problem)], pool)?;
+            count += 1;
+            let index = AssetIndex::load(&file.path)?;
+            let assets = asset_files(root, &index);
+            for (file, problem) in verify_files(assets, threads, &AtomicUsize::new(0)) {
+                if queue_repair(&file, &problem, pool)? {
+                    count += 1;
+                }
+            }
+        }
+        info!(target: "verify", "repair {}: {} files queued", self.version_id, count);
+        Ok(count)
+    }
+}
+
+/// 把一个有问题的文件加入下载池，没有下载地址时返回 false
+fn queue_repair(file: &GameFile, problem: &FileProblem, pool: &DownloadPool) -> Result<bool> {
+    if file.url.is_empty() {
+        return Ok(false);
+    }
+    if let Some(parent) = file.path.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    if *problem != FileProblem::Missing {
+        let _ = fs::remove_file(&file.path);
+    }
+    pool.add_task(file.url.clone(), file.path.to_string_lossy().to_string());
+    Ok(true)
+}
+
+/// 在后台线程中校验版本
+pub struct Verifier {
+    checked: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+    rx: Option<Receiver<Result<VerifyReport, String>>>,
+    result: Option<Result<VerifyReport, String>>,
+    repair_rx: Option<Receiver<Result<usize, String>>>,
+}
+
+impl Verifier {
+    /// 开始校验，之前的结果会被清空
+    pub fn start(&mut self, root: GameRoot, id: String, threads: usize) {
+        let (tx, rx) = mpsc::channel();
+        self.checked = Arc::new(AtomicUsize::new(0));
+        self.total = Arc::new(AtomicUsize::new(0));
+        self.rx = Some(rx);
+        self.result = None;
+
+        let checked = self.checked.clone();
+        let total = self.total.clone();
+        thread::spawn(move || {
+            let result = verify_version_with_progress(&root, &id, threads, &checked, &total)
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    /// None 表示还没有开始校验，Progress 中为 (已校验, 总数)
+    pub fn get_state(&mut self) -> Option<Status<VerifyReport, (usize, usize), String>> {
+        if let Some(rx) = &self.rx
+            && let Ok(result) = rx.try_recv()
+        {
+            self.result = Some(result);
+            self.rx = None;
+        }
+        match &self.result {
+            Some(Ok(report)) => Some(Status::Success(report.clone())),
+            Some(Err(e)) => Some(Status::Failed(e.clone())),
+            None if self.rx.is_some() => Some(Status::Progress((
+                self.checked.load(Ordering::Relaxed),
+                self.total.load(Ordering::Relaxed),
+            ))),
+            None => None,
+        }
+    }
+
+    /// 校验中时为 (已校验, 总数)，不会复制校验结果
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.rx.as_ref().map(|_| {
+            (
+                self.checked.load(Ordering::Relaxed),
+                self.total.load(Ordering::Relaxed),
+            )
+        })
+    }
+
+    /// 在后台修复校验结果中的文件，之前的结果会被清空
+    pub fn repair(&mut self, root: GameRoot, report: VerifyReport, pool: DownloadPool, threads: usize) {
+        self.set_none();
+        let (tx, rx) = mpsc::channel();
+        self.repair_rx = Some(rx);
+        thread::spawn(move || {
+            let result = report
+                .repair(&root, &pool, threads)
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    /// None 表示没有在修复，Success 中为加入下载池的文件数
+    ///
+    /// 修复结果只返回一次，不影响同时进行的校验
+    pub fn repair_state(&mut self) -> Option<Status<usize, (), String>> {
+        let Ok(result) = self.repair_rx.as_ref()?.try_recv() else {
+            return Some(Status::Progress(()));
+        };
+        self.repair_rx = None;
+        Some(match result {
+            Ok(count) => Status::Success(count),
+            Err(e) => Status::Failed(e),
+        })
+    }
+
+    pub fn set_none(&mut self) {
+        self.rx = None;
+        self.result = None;
+        self.repair_rx = None;
+    }
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self {
+            checked: Arc::new(AtomicUsize::new(0)),
+            total: Arc::new(AtomicUsize::new(0)),
+            rx: None,
+            result: None,
+            repair_rx: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    const CONTENT: &[u8] = b"game file";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mctui-verify-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn sha1(data: &[u8]) -> String {
+        sha1_smol::Sha1::from(data).digest().to_string()
+    }
+
+    fn game_file(path: &Path, url: &str, data: &[u8]) -> GameFile {
+        GameFile {
+            kind: GameFileKind::Library,
+            path: path.to_path_buf(),
+            url: url.to_string(),
+            sha1: Some(sha1(data)),
+            size: Some(data.len() as u64),
+        }
+    }
+
+    #[test]
+    fn check_single_file() {
+        let dir = temp_dir("check");
+        let path = dir.join("a.jar");
+        let file = game_file(&path, "", CONTENT);
+        assert_eq!(check_file(&file), Some(FileProblem::Missing));
+
+        fs::write(&path, CONTENT).unwrap();
+        assert_eq!(check_file(&file), None);
+        assert_eq!(
+            check_file(&GameFile { sha1: Some(sha1(CONTENT).to_uppercase()), ..file.clone() }),
+            None
+        );
+
+        fs::write(&path, b"game filE").unwrap();
+        assert!(matches!(check_file(&file), Some(FileProblem::HashMismatch { .. })));
+        // 不知道 sha1 时只比较大小
+        assert_eq!(check_file(&GameFile { sha1: None, ..file.clone() }), None);
+
+        fs::write(&path, b"short").unwrap();
+        assert_eq!(
+            check_file(&file),
+            Some(FileProblem::SizeMismatch { expected: CONTENT.len() as u64, actual: 5 })
+        );
+        // 目录不算文件
+        assert_eq!(check_file(&game_file(&dir, "", CONTENT)), Some(FileProblem::Missing));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn verify_many_files() {
+        let dir = temp_dir("many");
+        let mut files = Vec::new();
+        for i in 0..20 {
+            let path = dir.join(format!("{}.jar", i));
+            match i % 4 {
+                0 => {}
+                1 => fs::write(&path, b"corrupt!!").unwrap(),
+                _ => fs::write(&path, CONTENT).unwrap(),
+            }
+            files.push(game_file(&path, "", CONTENT));
+        }
+        let checked = AtomicUsize::new(0);
+        let problems = verify_files(files, 3, &checked);
+        assert_eq!(checked.load(Ordering::Relaxed), 20);
+        assert_eq!(problems.len(), 10);
+        assert_eq!(problems.iter().filter(|(_, p)| *p == FileProblem::Missing).count(), 5);
+        assert!(verify_files(Vec::new(), 0, &checked).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn serve_files() -> TestServer {
+        TestServer::start(|request| match request.path.as_str() {
+            "/good" => Response::bytes(CONTENT),
+            "/bad" => Response::bytes(b"game filE".to_vec()),
+            _ => Response::status(404),
+        })
+    }
+
+    #[test]
+    fn download_problem_files() {
+        let dir = temp_dir("download");
+        let server = serve_files();
+        let pool = DownloadPool::new(4);
+
+        let missing = game_file(&dir.join("libs/missing.jar"), &format!("{}/good", server.url), CONTENT);
+        let corrupt = game_file(&dir.join("corrupt.jar"), &format!("{}/good", server.url), CONTENT);
+        fs::write(&corrupt.path, b"old").unwrap();
+        let problems = [missing.clone(), corrupt.clone()]
+            .into_iter()
+            .map(|file| {
+                let problem = check_file(&file).unwrap();
+                (file, problem)
+            })
+            .collect::<Vec<_>>();
+        download_problems(&problems, &pool).unwrap();
+        assert_eq!(check_file(&missing), None);
+        assert_eq!(check_file(&corrupt), None);
+
+        // 下载到的内容不对时报错
+        let bad = game_file(&dir.join("bad.jar"), &format!("{}/bad", server.url), CONTENT);
+        let e = download_problems(&[(bad, FileProblem::Missing)], &pool).unwrap_err();
+        assert!(e.to_string().contains("downloaded file is invalid"));
+
+        let no_url = game_file(&dir.join("local.jar"), "", CONTENT);
+        let e = download_problems(&[(no_url, FileProblem::Missing)], &pool).unwrap_err();
+        assert!(e.to_string().contains("without download url"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repair_downloads_asset_index_first() {
+        let dir = temp_dir("repair");
+        let root = GameRoot::new(&dir);
+        let hash = sha1(CONTENT);
+        let index = format!(
+            r#"{{"objects": {{"sounds/a.ogg": {{"hash": "{}", "size": {}}}}}}}"#,
+            hash,
+            CONTENT.len()
+        );
+        let index_bytes = index.clone().into_bytes();
+        let server = TestServer::start(move |request| match request.path.as_str() {
+            "/index.json" => Response::bytes(index_bytes.clone()),
+            _ => Response::status(404),
+        });
+        let index_file = GameFile {
+            kind: GameFileKind::AssetIndex,
+            path: root.asset_index("legacy"),
+            url: format!("{}/index.json", server.url),
+            sha1: Some(sha1(index.as_bytes())),
+            size: Some(index.len() as u64),
+        };
+        let report = VerifyReport {
+            version_id: "1.6.4".to_string(),
+            checked: 1,
+            problems: vec![(index_file.clone(), FileProblem::Missing)],
+            assets_skipped: true,
+        };
+        let pool = DownloadPool::new(2);
+        // 索引和其中缺失的资源文件
+        assert_eq!(report.repair(&root, &pool, 2).unwrap(), 2);
+        assert_eq!(check_file(&index_file), None);
+        let requests = server.requests();
+        assert_eq!((requests[0].method.as_str(), requests[0].path.as_str()), ("GET", "/index.json"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repair_state_reports_result() {
+        let dir = temp_dir("state");
+        let mut verifier = Verifier::default();
+        assert!(verifier.repair_state().is_none());
+        let report = VerifyReport {
+            version_id: "1.20.1".to_string(),
+            checked: 1,
+            problems: vec![(game_file(&dir.join("local.jar"), "", CONTENT), FileProblem::Missing)],
+            assets_skipped: false,
+        };
+        verifier.repair(GameRoot::new(&dir), report, DownloadPool::new(1), 1);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let state = loop {
+            match verifier.repair_state() {
+                Some(Status::Progress(())) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                state => break state,
+            }
+        };
+        // 没有下载地址的文件不会加入下载池
+        assert!(matches!(state, Some(Status::Success(0))));
+        assert!(verifier.repair_state().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+//! 游戏实例：独立的游戏目录和启动设置
+//!
+//! 实例保存在 `<.minecraft>/instances/<id>/` 下，清单为其中的 `instance.json`，
+//! versions、libraries 和 assets 仍然使用 [`GameRoot`] 中的目录，在所有实例间共享
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    account::microsoft_account::now_secs,
+    install::minecraft::{game_root::GameRoot, scan::ScannedVersion},
+    launch::LaunchOptions,
+};
+
+pub mod backup;
+pub mod launch;
+pub mod launcher_profiles;
+pub mod multimc;
+pub mod task;
+
+/// 实例清单的文件名
+pub const MANIFEST_FILE: &str = "instance.json";
+/// 默认的游戏目录，相对于实例目录
+pub const DEFAULT_GAME_DIR: &str = ".minecraft";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoaderKind {
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+    LiteLoader,
+    OptiFine,
+}
+
+impl LoaderKind {
+    pub fn get_name(&self) -> &str {
+        match self {
+            LoaderKind::Fabric => "Fabric",
+            LoaderKind::Quilt => "Quilt",
+            LoaderKind::Forge => "Forge",
+            LoaderKind::NeoForge => "NeoForge",
+            LoaderKind::LiteLoader => "LiteLoader",
+            LoaderKind::OptiFine => "OptiFine",
+        }
+    }
+}
+
+/// 模组加载器及其版本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModLoader {
+    pub kind: LoaderKind,
+    pub version: String,
+}
+
+/// 实例的启动设置，None 表示使用全局默认值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceSettings {
+    pub java_path: Option<PathBuf>,
+    /// 单位 MB
+    pub min_memory: Option<u32>,
+    /// 单位 MB
+    pub max_memory: Option<u32>,
+    pub jvm_args: Option<Vec<String>>,
+    pub game_args: Option<Vec<String>>,
+    /// 窗口大小 (宽, 高)
+    pub resolution: Option<(u32, u32)>,
+    /// 与全局的环境变量合并，同名时使用实例的值
+    pub env: BTreeMap<String, String>,
+}
+
+impl InstanceSettings {
+    /// 用本实例的设置覆盖全局默认值
+    pub fn merged(&self, defaults: &InstanceSettings) -> InstanceSettings {
+        let mut env = defaults.env.clone();
+        env.extend(self.env.clone());
+        InstanceSettings {
+            java_path: self.java_path.clone().or_else(|| defaults.java_path.clone()),
+            min_memory: self.min_memory.or(defaults.min_memory),
+            max_memory: self.max_memory.or(defaults.max_memory),
+            jvm_args: self.jvm_args.clone().or_else(|| defaults.jvm_args.clone()),
+            game_args: self.game_args.clone().or_else(|| defaults.game_args.clone()),
+            resolution: self.resolution.or(defaults.resolution),
+            env,
+        }
+    }
+
+    /// 把已设置的值写入启动选项，未设置的保持不变
+    pub fn apply(&self, options: &mut LaunchOptions) {
+        if let Some(java_path) = &self.java_path {
+            options.java_path = java_path.clone();
+        }
+        if self.min_memory.is_some() {
+            options.min_memory = self.min_memory;
+        }
+        if self.max_memory.is_some() {
+            options.max_memory = self.max_memory;
+        }
+        if let Some(jvm_args) = &self.jvm_args {
+            options.jvm_args = jvm_args.clone();
+        }
+        if let Some(game_args) = &self.game_args {
+            options.game_args = game_args.clone();
+        }
+        if self.resolution.is_some() {
+            options.resolution = self.resolution;
+        }
+        options
+            .env
+            .extend(self.env.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+}
+
+/// 一个游戏实例
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Instance {
+    /// 实例目录，不保存在清单中
+    #[serde(skip)]
+    pub path: PathBuf,
+    pub name: String,
+    /// 内置图标的名称，或实例目录中图片的文件名
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Minecraft 版本
+    pub version: String,
+    #[serde(default)]
+    pub loader: Option<ModLoader>,
+    /// 启动时使用的 versions 下的版本 id，为空时与 version 相同
+    ///
+    /// 安装模组加载器后通常是另一个 inheritsFrom 原版的版本
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// 版本所在的游戏目录，为空时使用启动器自己的目录
+    ///
+    /// 接管其它启动器安装的版本时指向那个启动器的 .minecraft
+    #[serde(default)]
+    pub game_root: Option<PathBuf>,
+    /// 游戏目录，相对路径相对于实例目录
+    #[serde(default = "default_game_directory")]
+    pub game_directory: PathBuf,
+    #[serde(default)]
+    pub settings: InstanceSettings,
+    /// 上次启动的时间，unix 秒
+    #[serde(default)]
+    pub last_played: Option<i64>,
+    /// 累计游戏时长，单位秒
+    #[serde(default)]
+    pub playtime: u64,
+}
+
+fn default_game_directory() -> PathBuf {
+    PathBuf::from(DEFAULT_GAME_DIR)
+}
+
+impl Instance {
+    /// 在 root 的 instances 目录下创建实例，目录名由名称生成
+    pub fn create(
+        root: &GameRoot,
+        name: &str,
+        version: &str,
+        loader: Option<ModLoader>,
+    ) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("instance name cannot be empty");
+        }
+        let path = unique_dir(&root.instances_dir(), &dir_name(name));
+        let instance = Self {
+            path,
+            name: name.to_string(),
+            icon: None,
+            version: version.to_string(),
+            loader,
+            version_id: None,
+            game_root: None,
+            game_directory: default_game_directory(),
+            settings: InstanceSettings::default(),
+            last_played: None,
+            playtime: 0,
+        };
+        fs::create_dir_all(instance.game_directory())
+            .context(format!("cannot create directory: {:?}", instance.game_directory()))?;
+        instance.save()?;
+        info!(target: "instance", "instance created: {} at {:?}", instance.name, instance.path);
+        Ok(instance)
+    }
+
+    /// 接管一个已安装的版本，不复制或下载任何文件
+    ///
+    /// 其它目录中的版本直接使用该目录作为游戏目录，版本隔离（versions/<id> 下已有存档、
+    /// 模组或配置）时使用版本目录；启动器自己目录中的版本与新建的实例相同
+    pub fn adopt(root: &GameRoot, version: &ScannedVersion) -> Result<Self> {
+        let own = same_path(&version.root, &root.path);
+        let minecraft_version = version.minecraft_version.as_str();
+        let mut instance = Self::create(root, &version.id, minecraft_version, version.loader.clone())?;
+        if version.id != minecraft_version {
+            instance.version_id = Some(version.id.clone());
+        }
+        if !own {
+            let source = GameRoot::new(&version.root);
+            let version_dir = source.version_dir(&version.id);
+            instance.game_directory = if is_isolated(&version_dir) {
+                version_dir
+            } else {
+                source.path.clone()
+            };
+            instance.game_root = Some(source.path);
+            // create 创建的空游戏目录已经没有用了
+            let _ = fs::remove_dir(instance.path.join(DEFAULT_GAME_DIR));
+        }
+        instance.save()?;
+        info!(target: "instance", "version {} adopted from {:?}", version.id, version.root);
+        Ok(instance)
+    }
+
+    /// 是否已经接管了该版本
+    pub fn is_adopted(&self, root: &GameRoot, version: &ScannedVersion) -> bool {
+        self.launch_version() == version.id && same_path(&self.game_root(root).path, &version.root)
+    }
+
+    /// 读取实例目录中的清单
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = path.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&file).context(format!("failed to read {:?}", file))?;
+        let mut instance: Self =
+            serde_json::from_str(&content).context(format!("failed to parse {:?}", file))?;
+        instance.path = path.to_path_buf();
+        Ok(instance)
+    }
+
+    /// 写入清单，先写临时文件再替换
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.path).context(format!("cannot create directory: {:?}", self.path))?;
+        let file = self.path.join(MANIFEST_FILE);
+        let temp = file.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&temp, content).context(format!("failed to write {:?}", temp))?;
+        fs::rename(&temp, &file).context(format!("failed to write {:?}", file))
+    }
+
+    /// 实例目录名，也作为实例的 id
+    pub fn get_id(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// 启动时使用的版本 id
+    pub fn launch_version(&self) -> &str {
+        self.version_id.as_deref().unwrap_or(&self.version)
+    }
+
+    /// 版本所在的游戏目录，root 为启动器自己的目录
+    pub fn game_root(&self, root: &GameRoot) -> GameRoot {
+        match &self.game_root {
+            Some(path) => GameRoot::new(path),
+            None => root.clone(),
+        }
+    }
+
+    /// 游戏目录的绝对路径
+    pub fn game_directory(&self) -> PathBuf {
+        self.path.join(&self.game_directory)
+    }
+
+    /// 根据全局默认设置和本实例的设置填写启动选项
+    pub fn apply_settings(&self, defaults: &InstanceSettings, options: &mut LaunchOptions) {
+        options.game_directory = self.game_directory();
+        self.settings.merged(defaults).apply(options);
+    }
+
+    /// 记录一次启动的时间
+    pub fn mark_launched(&mut self) {
+        self.last_played = Some(now_secs());
+    }
+
+    /// 累加一次游戏的时长
+    pub fn add_playtime(&mut self, duration: Duration) {
+        self.playtime += duration.as_secs();
+    }
+
+    /// 删除实例目录，包括其中的存档
+    pub fn delete(self) -> Result<()> {
+        fs::remove_dir_all(&self.path).context(format!("failed to delete {:?}", self.path))?;
+        info!(target: "instance", "instance deleted: {}", self.name);
+        Ok(())
+    }
+}
+
+/// root 中的所有实例，按名称排序，清单损坏的实例会被跳过
+pub fn list_instances(root: &GameRoot) -> Vec<Instance> {
+    let Ok(entries) = fs::read_dir(root.instances_dir()) else {
+        return Vec::new();
+    };
+    let mut instances: Vec<Instance> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .filter_map(|path| match Instance::load(&path) {
+            Ok(instance) => Some(instance),
+            Err(e) => {
+                warn!(target: "instance", "skip instance {:?}: {:#}", path, e);
+                None
+            }
+        })
+        .collect();
+    instances.sort_by(|a, b| a.name.cmp(&b.name));
+    instances
+}
+
+/// 接管扫描到的版本，已经接管过的版本会被跳过，返回新建的实例
+pub fn adopt_versions(root: &GameRoot, versions: &[ScannedVersion]) -> Result<Vec<Instance>> {
+    let existing = list_instances(root);
+    let mut adopted = Vec::new();
+    for version in versions {
+        if existing.iter().chain(&adopted).any(|i| i.is_adopted(root, version)) {
+            continue;
+        }
+        adopted.push(Instance::adopt(root, version)?);
+    }
+    Ok(adopted)
+}
+
+pub(crate) fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || fs::canonicalize(a).is_ok_and(|a| fs::canonicalize(b).is_ok_and(|b| a == b))
+}
+
+/// 版本目录中已有游戏数据时认为开启了版本隔离
+fn is_isolated(version_dir: &Path) -> bool {
+    ["saves", "mods", "config", "resourcepacks", "options.txt"]
+        .iter()
+        .any(|name| version_dir.join(name).exists())
+}
+
+/// 目录中的文件数量，用于显示进度
+pub(crate) fn count_files(path: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => count_files(&entry.path()),
+            Ok(file_type) if file_type.is_file() => 1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// 递归复制目录，每复制一个文件调用一次 on_file
+///
+/// 跳过符号链接，避免复制到目录外的文件或陷入循环
+pub(crate) fn copy_dir(from: &Path, to: &Path, on_file: &mut dyn FnMut()) -> Result<()> {
+    fs::create_dir_all(to).context(format!("cannot create directory: {:?}", to))?;
+    for entry in fs::read_dir(from).context(format!("cannot read directory: {:?}", from))? {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let target = to.join(name);
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            warn!(target: "instance", "skip symlink {:?}", path);
+        } else if file_type.is_dir() {
+            copy_dir(&path, &target, on_file)?;
+        } else {
+            fs::copy(&path, &target).context(format!("failed to copy {:?}", path))?;
+            on_file();
+        }
+    }
+    Ok(())
+}
+
+/// 把目录中的文件写入 zip，prefix 为 zip 中的目录，每写入一个文件调用一次 on_file
+///
+/// 和 [`copy_dir`] 一样跳过符号链接
+pub(crate) fn zip_dir<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    dir: &Path,
+    prefix: &str,
+    on_file: &mut dyn FnMut(),
+) -> Result<()> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in fs::read_dir(dir).context(format!("cannot read directory: {:?}", dir))? {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = format!("{}{}", prefix, name);
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            warn!(target: "instance", "skip symlink {:?}", path);
+        } else if file_type.is_dir() {
+            zip.add_directory(format!("{}/", name), options)?;
+            zip_dir(zip, &path, &format!("{}/", name), on_file)?;
+        } else {
+            zip.start_file(name, options)?;
+            let mut file = fs::File::open(&path).context(format!("cannot open {:?}", path))?;
+            io::copy(&mut file, zip).context(format!("failed to compress {:?}", path))?;
+            on_file();
+        }
+    }
+    Ok(())
+}
+
+/// 把实例名称转换为目录名，去掉路径和文件名中不允许的字符
+fn dir_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() {
+        "instance".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// 目录已存在时加上数字后缀
+fn unique_dir(parent: &Path, name: &str) -> PathBuf {
+    let mut path = parent.join(name);
+    let mut index = 2;
+    while path.exists() {
+        path = parent.join(format!("{}-{}", name, index));
+        index += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_dir_skips_symlinks() {
+        let base = std::env::temp_dir().join(format!("mctui-copy-dir-{}", std::process::id()));
+        let from = base.join("from");
+        let to = base.join("to");
+        fs::create_dir_all(from.join("config")).unwrap();
+        fs::write(from.join("config/a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(&base, from.join("loop")).unwrap();
+        std::os::unix::fs::symlink(from.join("config/a.txt"), from.join("link.txt")).unwrap();
+
+        assert_eq!(count_files(&from), 1);
+        let mut copied = 0;
+        copy_dir(&from, &to, &mut || copied += 1).unwrap();
+        assert_eq!(copied, 1);
+        assert!(to.join("config/a.txt").is_file());
+        assert!(!to.join("loop").exists() && !to.join("link.txt").exists());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,597 @@
+//! 复制实例、备份为压缩包和从备份恢复
+//!
+//! 备份中 `backup.json` 为清单，游戏目录的文件在 `.minecraft/` 下；
+//! tar.zst 的清单必须是第一个文件，恢复时才能先读取清单再解压
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::{info, warn};
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{CompressionLevel, compress},
+};
+use serde::{Deserialize, Serialize};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    account::microsoft_account::now_secs,
+    install::minecraft::game_root::GameRoot,
+    instance::{DEFAULT_GAME_DIR, Instance, same_path, task::Progress},
+};
+
+/// 备份清单的文件名
+pub const BACKUP_MANIFEST: &str = "backup.json";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+const SAVE_DIRS: [&str; 1] = ["saves"];
+const LOG_DIRS: [&str; 2] = ["logs", "crash-reports"];
+const CACHE_DIRS: [&str; 5] = [".cache", ".fabric", ".mixin.out", "shadercache", "webcache2"];
+/// 恢复备份时在游戏目录中使用的临时目录
+const RESTORE_DIR: &str = ".mctui-restore";
+/// 游戏目录就是 .minecraft 时，这些目录由所有版本共享，不属于实例
+const SHARED_DIRS: [&str; 5] = ["versions", "libraries", "assets", "runtime", "instances"];
+
+/// 复制实例时不复制的内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneOptions {
+    pub exclude_saves: bool,
+    pub exclude_logs: bool,
+    pub exclude_caches: bool,
+}
+
+impl CloneOptions {
+    /// 只复制模组、配置等，不复制存档、日志和缓存
+    pub fn minimal() -> Self {
+        Self { exclude_saves: true, exclude_logs: true, exclude_caches: true }
+    }
+
+    fn excluded(&self) -> Vec<&'static str> {
+        let mut excluded = Vec::new();
+        if self.exclude_saves {
+            excluded.extend(SAVE_DIRS);
+        }
+        if self.exclude_logs {
+            excluded.extend(LOG_DIRS);
+        }
+        if self.exclude_caches {
+            excluded.extend(CACHE_DIRS);
+        }
+        excluded
+    }
+}
+
+/// 备份的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupFormat {
+    Zip,
+    TarZst,
+}
+
+impl BackupFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            BackupFormat::Zip => "zip",
+            BackupFormat::TarZst => "tar.zst",
+        }
+    }
+
+    /// 按文件扩展名判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(BackupFormat::Zip)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(BackupFormat::TarZst)
+        } else {
+            None
+        }
+    }
+}
+
+/// 备份清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    /// 备份的时间，unix 秒
+    pub created: i64,
+    /// 备份中游戏文件的数量
+    pub files: usize,
+    /// 实例目录中的图标文件
+    #[serde(default)]
+    pub icon_file: Option<String>,
+    pub instance: Instance,
+}
+
+/// 复制实例，新实例使用独立的游戏目录
+pub fn clone_instance(
+    root: &GameRoot,
+    instance: &Instance,
+    name: &str,
+    options: CloneOptions,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Instance> {
+    let mut clone = Instance::create(root, name, &instance.version, instance.loader.clone())?;
+    clone.version_id = instance.version_id.clone();
+    clone.game_root = instance.game_root.clone();
+    clone.settings = instance.settings.clone();
+    clone.icon = instance.icon.clone();
+
+    let source = instance.game_directory();
+    let target = clone.game_directory();
+    let files = game_files(root, instance, &options.excluded());
+    let total = files.len();
+    progress((0, total));
+    let result = (|| {
+        if let Some(icon) = icon_file(instance) {
+            fs::copy(instance.path.join(&icon), clone.path.join(&icon))
+                .context(format!("failed to copy {:?}", icon))?;
+        }
+        for (i, file) in files.iter().enumerate() {
+            let to = target.join(file);
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)
+                    .context(format!("cannot create directory: {:?}", parent))?;
+            }
+            fs::copy(source.join(file), &to).context(format!("failed to copy {:?}", file))?;
+            progress((i + 1, total));
+        }
+        clone.save()
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&clone.path);
+        return Err(e);
+    }
+    info!(target: "instance", "instance {} cloned to {}", instance.name, clone.name);
+    Ok(clone)
+}
+
+/// 备份实例的清单、图标和游戏目录，格式由 dest 的扩展名决定
+pub fn backup_instance(
+    root: &GameRoot,
+    instance: &Instance,
+    dest: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<PathBuf> {
+    let format = BackupFormat::from_path(dest)
+        .ok_or_else(|| anyhow!("backup must be a .zip or .tar.zst file: {:?}", dest))?;
+    let files = game_files(root, instance, &[]);
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        created: now_secs(),
+        files: files.len(),
+        icon_file: icon_file(instance),
+        instance: instance.clone(),
+    };
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    let temp = dest.with_extension("tmp");
+    let result = match format {
+        BackupFormat::Zip => write_zip(instance, &manifest, &files, &temp, progress),
+        BackupFormat::TarZst => write_tar_zst(instance, &manifest, &files, &temp, progress),
+    };
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    fs::rename(&temp, dest).context(format!("failed to write {:?}", dest))?;
+    info!(target: "instance", "instance {} backed up to {:?}", instance.name, dest);
+    Ok(dest.to_path_buf())
+}
+
+/// 读取备份的清单
+pub fn read_manifest(path: &Path) -> Result<BackupManifest> {
+    let mut manifest = None;
+    read_backup(path, &mut |name, reader| {
+        if name == Path::new(BACKUP_MANIFEST) {
+            manifest = Some(serde_json::from_reader(reader).context("failed to parse backup manifest")?);
+        }
+        // 清单读到后就不用继续解压了
+        Ok(manifest.is_none())
+    })?;
+    manifest.ok_or_else(|| anyhow!("{:?} is not a backup", path))
+}
+
+/// 从备份恢复，target 为空时恢复为新实例
+///
+/// 恢复到已有实例时还原启动设置，游戏目录替换为备份中的内容。
+/// 先解压到临时目录，全部成功后再替换，失败时保留原来的文件
+pub fn restore_backup(
+    root: &GameRoot,
+    path: &Path,
+    target: Option<Instance>,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Instance> {
+    let manifest = read_manifest(path)?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        bail!("backup format {} is not supported", manifest.format_version);
+    }
+    let backup = manifest.instance;
+    let created = target.is_none();
+    let mut instance = match target {
+        Some(instance) => instance,
+        None => {
+            let mut instance = Instance::create(root, &backup.name, &backup.version, None)?;
+            instance.last_played = backup.last_played;
+            instance.playtime = backup.playtime;
+            instance
+        }
+    };
+    instance.version = backup.version;
+    instance.loader = backup.loader;
+    instance.version_id = backup.version_id;
+    instance.game_root = backup.game_root;
+    instance.settings = backup.settings;
+    instance.icon = backup.icon;
+
+    let game_dir = instance.game_directory();
+    // 临时目录放在游戏目录中，替换时只需要重命名
+    let work = game_dir.join(RESTORE_DIR);
+    let staged = work.join("new");
+    let staged_icon = work.join("icon");
+    let game_prefix = Path::new(DEFAULT_GAME_DIR);
+    let total = manifest.files;
+    let mut done = 0;
+    progress((done, total));
+    let _ = fs::remove_dir_all(&work);
+    let result = read_backup(path, &mut |name, reader| {
+        let target = if let Ok(file) = name.strip_prefix(game_prefix) {
+            done += 1;
+            progress((done, total));
+            staged.join(file)
+        } else if manifest.icon_file.as_deref().is_some_and(|icon| name == Path::new(icon)) {
+            staged_icon.clone()
+        } else {
+            return Ok(true);
+        };
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+        }
+        let mut file = fs::File::create(&target).context(format!("cannot create {:?}", target))?;
+        io::copy(reader, &mut file).context(format!("failed to extract {:?}", name))?;
+        Ok(true)
+    })
+    .and_then(|()| {
+        // 游戏目录是共享的 .minecraft 时保留共享目录
+        let shared = same_path(&game_dir, &instance.game_root(root).path);
+        swap_game_dir(&game_dir, &work, if shared { &SHARED_DIRS } else { &[] })?;
+        if let Some(icon) = &manifest.icon_file
+            && staged_icon.is_file()
+        {
+            fs::rename(&staged_icon, instance.path.join(icon))
+                .context(format!("failed to restore {:?}", icon))?;
+        }
+        Ok(())
+    });
+    let _ = fs::remove_dir_all(&work);
+    if let Err(e) = result {
+        if created {
+            let _ = fs::remove_dir_all(&instance.path);
+        }
+        return Err(e);
+    }
+    instance.save()?;
+    info!(target: "instance", "backup {:?} restored to {}", path, instance.name);
+    Ok(instance)
+}
+
+/// 用 work 中解压好的文件替换游戏目录的内容，keep 为保留的顶层目录
+///
+/// 原来的文件先移到 work 中，失败时移回
+fn swap_game_dir(game_dir: &Path, work: &Path, keep: &[&str]) -> Result<()> {
+    let staged = work.join("new");
+    let old = work.join("old");
+    fs::create_dir_all(&old).context(format!("cannot create directory: {:?}", old))?;
+    let mut moved_old = Vec::new();
+    let mut moved_new = Vec::new();
+    let result = (|| {
+        for entry in fs::read_dir(game_dir).context(format!("cannot read directory: {:?}", game_dir))? {
+            let name = entry?.file_name();
+            if name == RESTORE_DIR || keep.iter().any(|keep| name == *keep) {
+                continue;
+            }
+            fs::rename(game_dir.join(&name), old.join(&name))
+                .context(format!("failed to move {:?}", name))?;
+            moved_old.push(name);
+        }
+        if !staged.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&staged).context(format!("cannot read directory: {:?}", staged))? {
+            let name = entry?.file_name();
+            fs::rename(staged.join(&name), game_dir.join(&name))
+                .context(format!("failed to move {:?}", name))?;
+            moved_new.push(name);
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        for name in moved_new {
+            let _ = fs::rename(game_dir.join(&name), staged.join(&name));
+        }
+        for name in moved_old {
+            let _ = fs::rename(old.join(&name), game_dir.join(&name));
+        }
+    }
+    result
+}
+
+/// 游戏目录中要复制或备份的文件，相对于游戏目录，excluded 为不包含的顶层目录
+fn game_files(root: &GameRoot, instance: &Instance, excluded: &[&str]) -> Vec<PathBuf> {
+    let dir = instance.game_directory();
+    let shared = same_path(&dir, &instance.game_root(root).path);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if excluded.contains(&name.as_ref()) || (shared && SHARED_DIRS.contains(&name.as_ref())) {
+            continue;
+        }
+        collect_files(&dir, &entry.path(), &mut files);
+    }
+    files.sort();
+    files
+}
+
+/// 跳过符号链接，避免备份目录外的文件或陷入循环
+fn collect_files(base: &Path, path: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_symlink() {
+        warn!(target: "instance", "skip symlink {:?}", path);
+    } else if metadata.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            collect_files(base, &entry.path(), files);
+        }
+    } else if let Ok(relative) = path.strip_prefix(base) {
+        files.push(relative.to_path_buf());
+    }
+}
+
+/// 实例目录中存在的图标文件
+fn icon_file(instance: &Instance) -> Option<String> {
+    let icon = instance.icon.as_ref()?;
+    let path = safe_path(Path::new(icon))?;
+    instance.path.join(path).is_file().then(|| icon.clone())
+}
+
+/// 备份中的路径，不允许绝对路径和 ..
+fn safe_path(name: &Path) -> Option<PathBuf> {
+    name.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| name.to_path_buf())
+}
+
+/// 备份中游戏文件的路径，统一使用 /
+fn entry_name(file: &Path) -> String {
+    let parts: Vec<String> = file
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    format!("{}/{}", DEFAULT_GAME_DIR, parts.join("/"))
+}
+
+fn write_zip(
+    instance: &Instance,
+    manifest: &BackupManifest,
+    files: &[PathBuf],
+    temp: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let file = fs::File::create(temp).context(format!("cannot create {:?}", temp))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(BACKUP_MANIFEST, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+    if let Some(icon) = &manifest.icon_file {
+        zip.start_file(icon.as_str(), options)?;
+        copy_file(&instance.path.join(icon), &mut zip)?;
+    }
+    let dir = instance.game_directory();
+    progress((0, files.len()));
+    for (i, file) in files.iter().enumerate() {
+        zip.start_file(entry_name(file), options)?;
+        copy_file(&dir.join(file), &mut zip)?;
+        progress((i + 1, files.len()));
+    }
+    zip.finish().context("failed to finish zip")?;
+    Ok(())
+}
+
+fn write_tar_zst(
+    instance: &Instance,
+    manifest: &BackupManifest,
+    files: &[PathBuf],
+    temp: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    // 先写未压缩的 tar，再整体压缩
+    let tar_path = temp.with_extension("tar.tmp");
+    let file = fs::File::create(&tar_path).context(format!("cannot create {:?}", tar_path))?;
+    let result = (|| {
+        let mut tar = tar::Builder::new(file);
+        let content = serde_json::to_vec_pretty(manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created.max(0) as u64);
+        tar.append_data(&mut header, BACKUP_MANIFEST, content.as_slice())?;
+        if let Some(icon) = &manifest.icon_file {
+            tar.append_path_with_name(instance.path.join(icon), icon)?;
+        }
+        let dir = instance.game_directory();
+        progress((0, files.len()));
+        for (i, file) in files.iter().enumerate() {
+            tar.append_path_with_name(dir.join(file), entry_name(file))
+                .context(format!("failed to archive {:?}", file))?;
+            progress((i + 1, files.len()));
+        }
+        tar.into_inner()?.sync_all()?;
+
+        let source = fs::File::open(&tar_path).context(format!("cannot open {:?}", tar_path))?;
+        let target = fs::File::create(temp).context(format!("cannot create {:?}", temp))?;
+        let mut source = Checked::new(source);
+        let mut target = Checked::new(target);
+        compress(&mut source, &mut target, CompressionLevel::Fastest);
+        source.result()?;
+        target.result()?;
+        target.inner.sync_all()?;
+        Ok(())
+    })();
+    let _ = fs::remove_file(&tar_path);
+    result
+}
+
+/// 依次读取备份中的文件，on_entry 返回 false 时停止
+fn read_backup(
+    path: &Path,
+    on_entry: &mut dyn FnMut(&Path, &mut dyn Read) -> Result<bool>,
+) -> Result<()> {
+    let format = BackupFormat::from_path(path)
+        .ok_or_else(|| anyhow!("backup must be a .zip or .tar.zst file: {:?}", path))?;
+    let file = fs::File::open(path).context(format!("cannot open {:?}", path))?;
+    match format {
+        BackupFormat::Zip => {
+            let mut zip = ZipArchive::new(file).context(format!("failed to open {:?}", path))?;
+            // 清单总是最先处理
+            let mut names: Vec<String> = zip.file_names().map(str::to_string).collect();
+            names.sort_by_key(|name| name != BACKUP_MANIFEST);
+            for name in names {
+                let mut entry = zip.by_name(&name)?;
+                if entry.is_dir() {
+                    continue;
+                }
+                let Some(name) = entry.enclosed_name().and_then(|name| safe_path(&name)) else {
+                    continue;
+                };
+                if !on_entry(&name, &mut entry)? {
+                    break;
+                }
+            }
+        }
+        BackupFormat::TarZst => {
+            let decoder = StreamingDecoder::new(file)
+                .map_err(|e| anyhow!("failed to open {:?}: {}", path, e))?;
+            let mut tar = tar::Archive::new(decoder);
+            for entry in tar.entries().context(format!("failed to open {:?}", path))? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let Some(name) = safe_path(&entry.path()?) else {
+                    continue;
+                };
+                if !on_entry(&name, &mut entry)? {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(path: &Path, writer: &mut impl Write) -> Result<()> {
+    let mut file = fs::File::open(path).context(format!("cannot open {:?}", path))?;
+    io::copy(&mut file, writer).context(format!("failed to compress {:?}", path))?;
+    Ok(())
+}
+
+/// 记录读写错误而不是返回错误，压缩库遇到 io 错误会 panic
+struct Checked<T> {
+    inner: T,
+    error: Option<io::Error>,
+}
+
+impl<T> Checked<T> {
+    fn new(inner: T) -> Self {
+        Self { inner, error: None }
+    }
+
+    fn result(&mut self) -> Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Read> Read for Checked<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Ok(0);
+        }
+        self.inner.read(buf).or_else(|e| {
+            self.error = Some(e);
+            Ok(0)
+        })
+    }
+}
+
+impl<T: Write> Write for Checked<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_none()
+            && let Err(e) = self.inner.write_all(buf)
+        {
+            self.error = Some(e);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.error.is_none()
+            && let Err(e) = self.inner.flush()
+        {
+            self.error = Some(e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_path_rejects_escapes() {
+        assert_eq!(safe_path(Path::new("a/b.txt")), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(safe_path(Path::new("../a.txt")), None);
+        assert_eq!(safe_path(Path::new("a/../../b")), None);
+        assert_eq!(safe_path(Path::new("/etc/passwd")), None);
+        assert_eq!(safe_path(Path::new("./a")), None);
+    }
+
+    #[test]
+    fn restore_replaces_game_directory() {
+        let base = std::env::temp_dir().join(format!("mctui-restore-{}", std::process::id()));
+        let root = GameRoot::new(&base);
+        let instance = Instance::create(&root, "restore", "1.20.1", None).unwrap();
+        let game_dir = instance.game_directory();
+        fs::create_dir_all(game_dir.join("config")).unwrap();
+        fs::write(game_dir.join("config/a.txt"), "old").unwrap();
+        fs::write(game_dir.join("options.txt"), "options").unwrap();
+        let backup = base.join("backup.zip");
+        backup_instance(&root, &instance, &backup, &mut |_| {}).unwrap();
+
+        fs::write(game_dir.join("config/a.txt"), "new").unwrap();
+        fs::write(game_dir.join("config/b.txt"), "added").unwrap();
+        let restored = restore_backup(&root, &backup, Some(instance), &mut |_| {}).unwrap();
+        let game_dir = restored.game_directory();
+        assert_eq!(fs::read_to_string(game_dir.join("config/a.txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(game_dir.join("options.txt")).unwrap(), "options");
+        assert!(!game_dir.join("config/b.txt").exists());
+        assert!(!game_dir.join(RESTORE_DIR).exists());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
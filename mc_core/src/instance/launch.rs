@@ -0,0 +1,282 @@
+//! 在后台线程中准备实例的启动命令
+//!
+//! 刷新账号、选择 Java、解压 natives 等步骤可能需要联网或较长时间，
+//! 完成后由调用方通过 [`GameProcessManager`](crate::launch::process::GameProcessManager) 启动
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::AtomicUsize,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use crate::{
+    account::{
+        Account, base::AccountBase, local_yggdrasil::LocalYggdrasil,
+        microsoft_account::{LoginExpired, MicrosoftEndpoints},
+    },
+    download::download_pool::DownloadPool,
+    install::{
+        minecraft::{
+            asset_index::copy_legacy_assets, game_root::GameRoot, rule::RuleContext,
+            version_json::VersionJson,
+        },
+        verify::{check_launch_files, download_problems},
+    },
+    instance::{Instance, InstanceSettings},
+    java::{
+        JavaRegistry,
+        runtime::{RUNTIME_ALL_URL, install_runtime, runtime_component},
+    },
+    launch::{
+        LaunchCommand, LaunchOptions, authlib_injector,
+        jvm::select_java,
+        log4j::ensure_log_config,
+        memory::{JvmPreset, auto_memory},
+        natives::extract_natives,
+    },
+    statue::Status,
+};
+
+/// 启动时需要的全局设置
+#[derive(Clone)]
+pub struct LaunchContext {
+    pub root: GameRoot,
+    /// 实例未设置时使用的默认值
+    pub defaults: InstanceSettings,
+    /// 自动分配内存时留给系统的内存，单位 MB
+    pub memory_reserve: u32,
+    pub jvm_preset: JvmPreset,
+    /// Java 列表的缓存文件
+    pub java_cache: PathBuf,
+    /// 校验文件使用的线程数
+    pub threads: usize,
+    pub endpoints: MicrosoftEndpoints,
+    /// 离线账号使用的本地皮肤服务器
+    pub local_yggdrasil: Option<LocalYggdrasil>,
+    /// 下载缺失的文件和 Java 运行时
+    pub pool: DownloadPool,
+}
+
+/// 准备启动的步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchStep {
+    Account,
+    Version,
+    /// 检查并补全缺失或损坏的文件
+    Files,
+    Java,
+    /// 没有可用的 Java 时安装 Mojang 运行时
+    Runtime,
+    Natives,
+    AuthlibInjector,
+}
+
+/// 准备好的启动命令
+#[derive(Clone)]
+pub struct PreparedLaunch {
+    pub command: LaunchCommand,
+    /// 启动使用的账号，可能刷新过 access token
+    pub account: Account,
+    /// 账号是否有变化，有变化时需要保存
+    pub account_changed: bool,
+}
+
+/// 依次完成启动前的准备并生成启动命令
+///
+/// 账号刷新失败时 account 可能已被修改（例如清空了失效的 refresh token），需要保存
+pub fn prepare_launch(
+    instance: &Instance,
+    account: &mut Account,
+    ctx: &LaunchContext,
+    mut step: impl FnMut(LaunchStep),
+) -> Result<PreparedLaunch> {
+    step(LaunchStep::Account);
+    let account_changed = account.refresh_before_launch(&ctx.endpoints)?;
+
+    step(LaunchStep::Version);
+    // 接管的版本使用原来的游戏目录，已有的文件不会重新下载
+    let root = instance.game_root(&ctx.root);
+    let profile = VersionJson::load_effective(&root, instance.launch_version())
+        .context(format!("version {} is not installed", instance.launch_version()))?;
+    let mut options = LaunchOptions {
+        jvm_preset: ctx.jvm_preset,
+        ..LaunchOptions::default()
+    };
+    instance.apply_settings(&ctx.defaults, &mut options);
+    fs::create_dir_all(&options.game_directory)
+        .context(format!("cannot create directory: {:?}", options.game_directory))?;
+    if options.max_memory.is_none()
+        && let Some((min, max)) = auto_memory(&options.game_directory, ctx.memory_reserve)
+    {
+        options.min_memory = options.min_memory.or(Some(min.min(max)));
+        options.max_memory = Some(max);
+    }
+    match ensure_log_config(&root, &profile) {
+        Ok(config) => options.use_log_config = config.is_some(),
+        Err(e) => {
+            warn!(target: "launch", "log config unavailable, using plain output: {:#}", e);
+            options.use_log_config = false;
+        }
+    }
+
+    step(LaunchStep::Files);
+    // 资源索引补全后才能检查资源文件，所以最多检查两轮
+    for _ in 0..2 {
+        let problems = check_launch_files(&root, &profile, ctx.threads);
+        if problems.is_empty() {
+            break;
+        }
+        info!(target: "launch", "{} files missing or corrupt, downloading", problems.len());
+        download_problems(&problems, &ctx.pool)?;
+    }
+    let copied = copy_legacy_assets(&root, profile.assets_id(), &options.game_directory)?;
+    if copied > 0 {
+        info!(target: "launch", "copied {} legacy assets", copied);
+    }
+
+    step(LaunchStep::Java);
+    let java_override = instance.settings.merged(&ctx.defaults).java_path;
+    let mut registry = JavaRegistry::load(&ctx.java_cache).with_extra_dir(ctx.root.runtimes_dir());
+    if root != ctx.root {
+        registry = registry.with_extra_dir(root.runtimes_dir());
+    }
+    if registry.installations.is_empty() {
+        registry.refresh()?;
+    }
+    let selection = match select_java(&registry, &profile, java_override.as_deref()) {
+        Ok(selection) => selection,
+        // 缓存可能过期，重新扫描一次，仍然没有时安装版本需要的运行时
+        Err(_) if java_override.is_none() => {
+            registry.refresh()?;
+            match select_java(&registry, &profile, None) {
+                Ok(selection) => selection,
+                Err(e) => {
+                    step(LaunchStep::Runtime);
+                    let java = install_runtime(
+                        &ctx.pool,
+                        RUNTIME_ALL_URL,
+                        &ctx.root.runtimes_dir(),
+                        runtime_component(&profile),
+                        &AtomicUsize::new(0),
+                        &AtomicUsize::new(0),
+                    )
+                    .context(format!("{:#}", e))?;
+                    registry.add(&java)?;
+                    select_java(&registry, &profile, None)?
+                }
+            }
+        }
+        Err(e) => return Err(e),
+    };
+    options.java_path = selection.java.path.clone();
+    options.java_major = Some(selection.java.major_version);
+
+    step(LaunchStep::Natives);
+    extract_natives(&root, &profile, &RuleContext::current(), &root.natives_dir(&profile.id))?;
+
+    step(LaunchStep::AuthlibInjector);
+    options.agent_args = authlib_injector::prepare(
+        account,
+        &ctx.root,
+        authlib_injector::LATEST_URL,
+        ctx.local_yggdrasil.as_ref(),
+    )?;
+
+    let command = crate::launch::build_launch_command(&root, &profile, account, &options);
+    info!(
+        target: "launch",
+        "launch {}: {}",
+        instance.name,
+        command.display_hidden(account.get_access_token())
+    );
+    Ok(PreparedLaunch { command, account: account.clone(), account_changed })
+}
+
+enum LaunchMessage {
+    Step(LaunchStep),
+    Done(Result<Box<PreparedLaunch>, String>),
+    /// 登录已失效，账号的凭据已被清空
+    Expired(Box<Account>),
+}
+
+/// 在后台线程中准备启动
+#[derive(Default)]
+pub struct InstanceLauncher {
+    rx: Option<Receiver<LaunchMessage>>,
+    state: Option<Status<PreparedLaunch, LaunchStep, String>>,
+    /// 正在准备的实例 id
+    instance: Option<String>,
+    /// 登录失效的账号，需要保存
+    expired_account: Option<Account>,
+}
+
+impl InstanceLauncher {
+    pub fn start(&mut self, instance: Instance, account: Account, ctx: LaunchContext) {
+        let (tx, rx): (Sender<LaunchMessage>, _) = mpsc::channel();
+        self.rx = Some(rx);
+        self.state = Some(Status::Progress(LaunchStep::Account));
+        self.instance = Some(instance.get_id());
+        self.expired_account = None;
+        thread::spawn(move || {
+            let mut account = account;
+            let result = prepare_launch(&instance, &mut account, &ctx, |step| {
+                let _ = tx.send(LaunchMessage::Step(step));
+            });
+            if let Err(e) = &result
+                && e.is::<LoginExpired>()
+            {
+                let _ = tx.send(LaunchMessage::Expired(Box::new(account)));
+            }
+            let result = result.map(Box::new).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(LaunchMessage::Done(result));
+        });
+    }
+
+    /// None 表示没有进行中的启动
+    pub fn get_state(&mut self) -> Option<Status<PreparedLaunch, LaunchStep, String>> {
+        if let Some(rx) = &self.rx {
+            while let Ok(message) = rx.try_recv() {
+                self.state = Some(match message {
+                    LaunchMessage::Step(step) => Status::Progress(step),
+                    LaunchMessage::Expired(account) => {
+                        self.expired_account = Some(*account);
+                        continue;
+                    }
+                    LaunchMessage::Done(Ok(prepared)) => Status::Success(*prepared),
+                    LaunchMessage::Done(Err(e)) => Status::Failed(e),
+                });
+            }
+        }
+        match &self.state {
+            Some(Status::Success(prepared)) => Some(Status::Success(prepared.clone())),
+            Some(Status::Progress(step)) => Some(Status::Progress(*step)),
+            Some(Status::Failed(e)) => Some(Status::Failed(e.clone())),
+            None => None,
+        }
+    }
+
+    /// 正在准备或刚准备好的实例 id
+    pub fn instance_id(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// 取走登录失效的账号，启动失败后调用
+    pub fn take_expired_account(&mut self) -> Option<Account> {
+        self.expired_account.take()
+    }
+
+    /// 取走结果并回到初始状态
+    pub fn set_none(&mut self) {
+        self.rx = None;
+        self.state = None;
+        self.instance = None;
+        self.expired_account = None;
+    }
+}
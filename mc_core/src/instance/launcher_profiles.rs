@@ -0,0 +1,406 @@
+//! 官方启动器的 launcher_profiles.json
+//!
+//! 每个启动配置导入为一个实例，版本和文件仍然使用官方启动器的目录；
+//! 也可以把本启动器安装的版本写回官方启动器的目录，让官方启动器也能启动
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    install::minecraft::{
+        game_root::GameRoot,
+        scan::{detect_version, scan_root},
+        version_json::VersionJson,
+    },
+    instance::{DEFAULT_GAME_DIR, Instance, InstanceSettings, list_instances, same_path},
+};
+
+/// 微软商店版启动器使用的文件，存在时优先使用
+const STORE_PROFILES_FILE: &str = "launcher_profiles_microsoft_store.json";
+pub const PROFILES_FILE: &str = "launcher_profiles.json";
+/// 写回的配置的 key 前缀，用于识别已经写回的版本
+const PROFILE_KEY_PREFIX: &str = "mctui-";
+/// 导入的 data URI 图标保存的文件名
+const ICON_FILE: &str = "icon.png";
+
+/// 启动配置中的窗口大小
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProfileResolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 一个启动配置，不认识的字段原样保留
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherProfile {
+    #[serde(default)]
+    pub name: String,
+    /// custom、latest-release 或 latest-snapshot
+    #[serde(default, rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_args: Option<String>,
+    /// java 可执行文件的路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<ProfileResolution>,
+    /// 内置图标的名称或 data:image/png;base64 图片
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// ISO 8601 时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl LauncherProfile {
+    /// 显示的名称，最新版本的配置没有名称
+    pub fn display_name(&self) -> String {
+        match (self.name.trim(), self.type_.as_str()) {
+            ("", "latest-release") => "Latest release".to_string(),
+            ("", "latest-snapshot") => "Latest snapshot".to_string(),
+            ("", _) => self.last_version_id.clone().unwrap_or_default(),
+            (name, _) => name.to_string(),
+        }
+    }
+
+    /// 配置对应的已安装版本，最新版本的配置使用已安装的最新版本
+    pub fn resolve_version(&self, root: &GameRoot) -> Option<String> {
+        let latest = match self.last_version_id.as_deref() {
+            Some("latest-release") => "release",
+            Some("latest-snapshot") => "snapshot",
+            Some(id) => return Some(id.to_string()),
+            None => match self.type_.as_str() {
+                "latest-release" => "release",
+                "latest-snapshot" => "snapshot",
+                _ => return None,
+            },
+        };
+        // 最新快照也可能是正式版
+        scan_root(root)
+            .into_iter()
+            .filter(|version| version.launchable && version.loader.is_none())
+            .filter(|version| version.release_type == latest || version.release_type == "release")
+            .filter_map(|version| {
+                let release_time = VersionJson::load(&root.version_json(&version.id))
+                    .ok()?
+                    .release_time?;
+                Some((release_time, version.id))
+            })
+            .max()
+            .map(|(_, id)| id)
+    }
+
+    /// javaArgs 中的 -Xmx 和 -Xms 转换为内存设置，其余作为 JVM 参数
+    pub fn settings(&self) -> InstanceSettings {
+        let mut settings = InstanceSettings {
+            java_path: self.java_dir.clone().filter(|path| !path.as_os_str().is_empty()),
+            resolution: self.resolution.map(|r| (r.width, r.height)),
+            ..InstanceSettings::default()
+        };
+        let mut jvm_args = Vec::new();
+        for arg in self.java_args.as_deref().unwrap_or_default().split_whitespace() {
+            if let Some(size) = arg.strip_prefix("-Xmx").and_then(parse_memory) {
+                settings.max_memory = Some(size);
+            } else if let Some(size) = arg.strip_prefix("-Xms").and_then(parse_memory) {
+                settings.min_memory = Some(size);
+            } else {
+                jvm_args.push(arg.to_string());
+            }
+        }
+        if !jvm_args.is_empty() {
+            settings.jvm_args = Some(jvm_args);
+        }
+        settings
+    }
+}
+
+/// launcher_profiles.json，只读写 profiles，其余字段原样保留
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LauncherProfiles {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, LauncherProfile>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl LauncherProfiles {
+    /// 文件不存在时返回空的配置
+    pub fn load(file: &Path) -> Result<Self> {
+        if !file.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(file).context(format!("failed to read {:?}", file))?;
+        serde_json::from_str(&content).context(format!("failed to parse {:?}", file))
+    }
+
+    /// 先写临时文件再替换
+    pub fn save(&self, file: &Path) -> Result<()> {
+        let temp = file.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)
+            .context(format!("failed to write {:?}", temp))?;
+        fs::rename(&temp, file).context(format!("failed to write {:?}", file))
+    }
+}
+
+/// root 中官方启动器使用的配置文件
+pub fn profiles_file(root: &GameRoot) -> PathBuf {
+    let store = root.path.join(STORE_PROFILES_FILE);
+    if store.is_file() {
+        store
+    } else {
+        root.path.join(PROFILES_FILE)
+    }
+}
+
+/// roots 中官方启动器的目录（有启动配置文件的），不包括 root 自己
+pub fn official_roots(root: &GameRoot, roots: &[PathBuf]) -> Vec<GameRoot> {
+    roots
+        .iter()
+        .filter(|path| !same_path(path, &root.path))
+        .map(GameRoot::new)
+        .filter(|candidate| profiles_file(candidate).is_file())
+        .collect()
+}
+
+/// 导入 source 中的所有启动配置，已经导入过的配置会被跳过
+///
+/// root 为启动器自己的目录，source 为官方启动器的 .minecraft，两者可以相同
+pub fn import_profiles(root: &GameRoot, source: &GameRoot) -> Result<Vec<Instance>> {
+    let profiles = LauncherProfiles::load(&profiles_file(source))?;
+    let existing = list_instances(root);
+    let mut imported: Vec<Instance> = Vec::new();
+    for (key, profile) in &profiles.profiles {
+        let Some(version_id) = profile.resolve_version(source) else {
+            warn!(target: "instance", "skip profile {}: no installed version", key);
+            continue;
+        };
+        let game_directory = profile.game_dir.clone().unwrap_or_else(|| source.path.clone());
+        let duplicate = existing.iter().chain(&imported).any(|instance| {
+            instance.launch_version() == version_id
+                && same_path(&instance.game_root(root).path, &source.path)
+                && same_path(&instance.game_directory(), &game_directory)
+        });
+        if duplicate {
+            continue;
+        }
+        imported.push(import_profile(root, source, profile, &version_id, game_directory)?);
+    }
+    info!(target: "instance", "{} profiles imported from {:?}", imported.len(), source.path);
+    Ok(imported)
+}
+
+fn import_profile(
+    root: &GameRoot,
+    source: &GameRoot,
+    profile: &LauncherProfile,
+    version_id: &str,
+    game_directory: PathBuf,
+) -> Result<Instance> {
+    // 版本未安装时无法识别加载器，先按原版导入
+    let (minecraft_version, loader) = match detect_version(source, version_id) {
+        Ok(version) => (version.minecraft_version, version.loader),
+        Err(_) => (version_id.to_string(), None),
+    };
+    let mut instance = Instance::create(root, &profile.display_name(), &minecraft_version, loader)?;
+    if version_id != minecraft_version {
+        instance.version_id = Some(version_id.to_string());
+    }
+    if !same_path(&source.path, &root.path) {
+        instance.game_root = Some(source.path.clone());
+    }
+    instance.game_directory = game_directory;
+    // create 创建的空游戏目录已经没有用了
+    let _ = fs::remove_dir(instance.path.join(DEFAULT_GAME_DIR));
+    instance.settings = profile.settings();
+    instance.last_played = profile.last_used.as_deref().and_then(parse_timestamp);
+    instance.icon = match profile.icon.as_deref() {
+        Some(icon) if icon.starts_with("data:") => save_icon(&instance.path, icon),
+        icon => icon.map(str::to_string),
+    };
+    instance.save()?;
+    Ok(instance)
+}
+
+/// 把 root 中已安装且没有启动配置的版本写回官方启动器的目录 target，返回新增的数量
+///
+/// 官方启动器只在自己的 versions 中查找版本，所以同时复制版本 json 和 jar，
+/// 库和资源由官方启动器按 json 下载；配置的游戏目录指向 root
+pub fn write_back_versions(root: &GameRoot, target: &GameRoot) -> Result<usize> {
+    if same_path(&root.path, &target.path) {
+        bail!("cannot write back versions to the same directory: {:?}", root.path);
+    }
+    let file = profiles_file(target);
+    let mut profiles = LauncherProfiles::load(&file)?;
+    let mut added = 0;
+    for version in scan_root(root).into_iter().filter(|version| version.launchable) {
+        let key = format!("{}{}", PROFILE_KEY_PREFIX, version.id);
+        let exists = profiles.profiles.contains_key(&key)
+            || profiles
+                .profiles
+                .values()
+                .any(|profile| profile.last_version_id.as_deref() == Some(version.id.as_str()));
+        if exists {
+            continue;
+        }
+        copy_version(root, target, &version.id)?;
+        profiles.profiles.insert(
+            key,
+            LauncherProfile {
+                name: version.id.clone(),
+                type_: "custom".to_string(),
+                last_version_id: Some(version.id),
+                game_dir: Some(root.path.clone()),
+                ..LauncherProfile::default()
+            },
+        );
+        added += 1;
+    }
+    if added > 0 {
+        profiles.save(&file)?;
+    }
+    info!(target: "instance", "{} versions written to {:?}", added, file);
+    Ok(added)
+}
+
+/// 把版本及其 inheritsFrom 的父版本复制到 target，target 中已有的版本不覆盖
+fn copy_version(root: &GameRoot, target: &GameRoot, id: &str) -> Result<()> {
+    let mut next = Some(id.to_string());
+    let mut copied = 0;
+    while let Some(id) = next.take() {
+        if target.version_json(&id).is_file() {
+            break;
+        }
+        if copied > 8 {
+            bail!("inheritsFrom loop in version {}", id);
+        }
+        let dir = target.version_dir(&id);
+        fs::create_dir_all(&dir).context(format!("cannot create directory: {:?}", dir))?;
+        fs::copy(root.version_json(&id), target.version_json(&id))
+            .context(format!("failed to copy version {}", id))?;
+        if root.version_jar(&id).is_file() {
+            fs::copy(root.version_jar(&id), target.version_jar(&id))
+                .context(format!("failed to copy version {}", id))?;
+        }
+        copied += 1;
+        next = VersionJson::load(&root.version_json(&id))?.inherits_from;
+    }
+    Ok(())
+}
+
+/// 内存大小转换为 MB，例如 2G、2048M、524288k
+fn parse_memory(size: &str) -> Option<u32> {
+    let (number, unit) = size.split_at(size.find(|c: char| !c.is_ascii_digit())?);
+    let number: u64 = number.parse().ok()?;
+    let mb = match unit.to_ascii_lowercase().as_str() {
+        "g" => number * 1024,
+        "m" => number,
+        "k" => number / 1024,
+        _ => return None,
+    };
+    u32::try_from(mb).ok()
+}
+
+/// ISO 8601 时间转换为 unix 秒，忽略毫秒和时区
+fn parse_timestamp(time: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| time.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // 公历日期到 1970-01-01 的天数
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// 保存 data URI 图标到实例目录，返回文件名
+fn save_icon(dir: &Path, icon: &str) -> Option<String> {
+    let (_, data) = icon.split_once("base64,")?;
+    let bytes = STANDARD.decode(data.trim()).ok()?;
+    match fs::write(dir.join(ICON_FILE), bytes) {
+        Ok(()) => Some(ICON_FILE.to_string()),
+        Err(e) => {
+            warn!(target: "instance", "failed to save icon in {:?}: {}", dir, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_memory_units() {
+        assert_eq!(parse_memory("2G"), Some(2048));
+        assert_eq!(parse_memory("2048m"), Some(2048));
+        assert_eq!(parse_memory("524288k"), Some(512));
+        assert_eq!(parse_memory("2048"), None);
+        assert_eq!(parse_memory("G"), None);
+        assert_eq!(parse_memory("2T"), None);
+        assert_eq!(parse_memory("99999999G"), None);
+    }
+
+    #[test]
+    fn parse_timestamp_dates() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00.000Z"), Some(0));
+        assert_eq!(parse_timestamp("2000-03-01T00:00:00Z"), Some(951868800));
+        // 闰年的 2 月 29 日
+        assert_eq!(parse_timestamp("2024-02-29T12:34:56.789Z"), Some(1709210096));
+        assert_eq!(parse_timestamp("2023-12-31T23:59:59Z"), Some(1704067199));
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59Z"), Some(-1));
+        assert_eq!(parse_timestamp("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-01-01"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+
+    #[test]
+    fn write_back_to_official_root() {
+        let base = std::env::temp_dir().join(format!("mctui-write-back-{}", std::process::id()));
+        let root = GameRoot::new(base.join("mctui"));
+        let official = GameRoot::new(base.join("official"));
+        for (id, json) in [
+            ("1.20.1", r#"{"id": "1.20.1", "mainClass": "net.minecraft.client.main.Main"}"#),
+            ("fabric", r#"{"id": "fabric", "inheritsFrom": "1.20.1"}"#),
+        ] {
+            fs::create_dir_all(root.version_dir(id)).unwrap();
+            fs::write(root.version_json(id), json).unwrap();
+        }
+        fs::write(root.version_jar("1.20.1"), "jar").unwrap();
+        fs::create_dir_all(&official.path).unwrap();
+
+        assert!(write_back_versions(&root, &root).is_err());
+        assert_eq!(write_back_versions(&root, &official).unwrap(), 2);
+        assert!(official.version_json("fabric").is_file());
+        assert!(official.version_jar("1.20.1").is_file());
+        let profiles = LauncherProfiles::load(&official.path.join(PROFILES_FILE)).unwrap();
+        let profile = &profiles.profiles["mctui-fabric"];
+        assert_eq!(profile.last_version_id.as_deref(), Some("fabric"));
+        assert_eq!(profile.game_dir.as_deref(), Some(root.path.as_path()));
+        // 已经写回的版本不再重复添加
+        assert_eq!(write_back_versions(&root, &official).unwrap(), 0);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
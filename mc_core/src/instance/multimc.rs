@@ -0,0 +1,470 @@
+//! 导入和导出 MultiMC / Prism Launcher 的实例
+//!
+//! 实例目录中 `instance.cfg` 保存名称和启动设置，`mmc-pack.json` 列出组件，
+//! 游戏文件在 `.minecraft`（旧版本为 `minecraft`）中
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    install::minecraft::{game_root::GameRoot, scan::find_version},
+    instance::{
+        DEFAULT_GAME_DIR, Instance, InstanceSettings, LoaderKind, ModLoader, copy_dir, count_files,
+        task::Progress, zip_dir,
+    },
+};
+
+pub const CONFIG_FILE: &str = "instance.cfg";
+pub const PACK_FILE: &str = "mmc-pack.json";
+/// 实例中游戏目录的名称，旧版本 MultiMC 使用 minecraft
+const GAME_DIRS: [&str; 2] = [".minecraft", "minecraft"];
+
+const MINECRAFT_UID: &str = "net.minecraft";
+/// Fabric 和 Quilt 依赖的映射组件
+const INTERMEDIARY_UID: &str = "net.fabricmc.intermediary";
+/// 组件 uid 和对应的加载器
+const LOADER_UIDS: [(&str, LoaderKind); 5] = [
+    ("net.fabricmc.fabric-loader", LoaderKind::Fabric),
+    ("org.quiltmc.quilt-loader", LoaderKind::Quilt),
+    ("net.minecraftforge", LoaderKind::Forge),
+    ("net.neoforged", LoaderKind::NeoForge),
+    ("com.mumfrey.liteloader", LoaderKind::LiteLoader),
+];
+
+/// mmc-pack.json 中的一个组件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackComponent {
+    pub uid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub important: Option<bool>,
+}
+
+/// mmc-pack.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MmcPack {
+    #[serde(default)]
+    pub components: Vec<PackComponent>,
+    #[serde(default = "format_version")]
+    pub format_version: u32,
+}
+
+fn format_version() -> u32 {
+    1
+}
+
+impl MmcPack {
+    fn component(&self, uid: &str) -> Option<&PackComponent> {
+        self.components.iter().find(|component| component.uid == uid)
+    }
+
+    pub fn minecraft_version(&self) -> Option<&str> {
+        self.component(MINECRAFT_UID)?.version.as_deref()
+    }
+
+    /// 第一个能识别的加载器组件
+    pub fn loader(&self) -> Option<ModLoader> {
+        self.components.iter().find_map(|component| {
+            let (_, kind) = LOADER_UIDS.iter().find(|(uid, _)| *uid == component.uid)?;
+            Some(ModLoader {
+                kind: *kind,
+                version: component.version.clone().unwrap_or_default(),
+            })
+        })
+    }
+
+    /// 实例对应的组件列表，OptiFine 等不是组件的加载器会被忽略
+    pub fn from_instance(instance: &Instance) -> Self {
+        let component = |uid: &str, version: &str| PackComponent {
+            uid: uid.to_string(),
+            version: Some(version.to_string()),
+            cached_name: None,
+            important: None,
+        };
+        let mut components = vec![PackComponent {
+            important: Some(true),
+            ..component(MINECRAFT_UID, &instance.version)
+        }];
+        if let Some(loader) = &instance.loader {
+            match LOADER_UIDS.iter().find(|(_, kind)| *kind == loader.kind) {
+                Some((uid, kind)) => {
+                    if matches!(kind, LoaderKind::Fabric | LoaderKind::Quilt) {
+                        components.push(component(INTERMEDIARY_UID, &instance.version));
+                    }
+                    components.push(component(uid, &loader.version));
+                }
+                None => warn!(
+                    target: "instance",
+                    "{} is not a prism component, skipped",
+                    loader.kind.get_name()
+                ),
+            }
+        }
+        Self { components, format_version: format_version() }
+    }
+}
+
+/// 读取 instance.cfg，忽略分组和注释
+pub fn parse_config(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with(['[', '#', ';']))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// 生成 instance.cfg，所有键放在 General 分组中
+pub fn write_config(config: &BTreeMap<String, String>) -> String {
+    let mut text = String::from("[General]\n");
+    for (key, value) in config {
+        text.push_str(&format!("{}={}\n", key, value.replace('\n', " ")));
+    }
+    text
+}
+
+/// 一个 MultiMC / Prism 实例
+#[derive(Debug, Clone)]
+pub struct MultiMcInstance {
+    pub path: PathBuf,
+    pub config: BTreeMap<String, String>,
+    pub pack: MmcPack,
+}
+
+impl MultiMcInstance {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = path.join(CONFIG_FILE);
+        let config = fs::read_to_string(&file)
+            .map(|text| parse_config(&text))
+            .context(format!("failed to read {:?}", file))?;
+        let file = path.join(PACK_FILE);
+        let pack = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str(&text).context(format!("failed to parse {:?}", file))?,
+            // 很旧的实例没有 mmc-pack.json，版本在 IntendedVersion 中
+            Err(_) => MmcPack {
+                components: config
+                    .get("IntendedVersion")
+                    .map(|version| PackComponent {
+                        uid: MINECRAFT_UID.to_string(),
+                        version: Some(version.clone()),
+                        cached_name: None,
+                        important: None,
+                    })
+                    .into_iter()
+                    .collect(),
+                format_version: format_version(),
+            },
+        };
+        if pack.minecraft_version().is_none() {
+            bail!("no minecraft version in {:?}", path);
+        }
+        Ok(Self { path: path.to_path_buf(), config, pack })
+    }
+
+    pub fn name(&self) -> String {
+        match self.config.get("name") {
+            Some(name) if !name.trim().is_empty() => name.clone(),
+            _ => self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 实例中的游戏目录
+    pub fn game_directory(&self) -> Option<PathBuf> {
+        GAME_DIRS
+            .iter()
+            .map(|name| self.path.join(name))
+            .find(|path| path.is_dir())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.config.get(key).is_some_and(|value| value == "true")
+    }
+
+    fn number(&self, key: &str) -> Option<u32> {
+        self.config.get(key).and_then(|value| value.trim().parse().ok())
+    }
+
+    /// Override 开关打开的设置，其余使用全局默认值
+    pub fn settings(&self) -> InstanceSettings {
+        let mut settings = InstanceSettings::default();
+        if self.flag("OverrideJavaLocation") || self.flag("OverrideJava") {
+            settings.java_path = self
+                .config
+                .get("JavaPath")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from);
+        }
+        if self.flag("OverrideMemory") {
+            settings.min_memory = self.number("MinMemAlloc");
+            settings.max_memory = self.number("MaxMemAlloc");
+        }
+        if self.flag("OverrideJavaArgs") || self.flag("OverrideJava") {
+            settings.jvm_args = self
+                .config
+                .get("JvmArgs")
+                .map(|args| args.split_whitespace().map(str::to_string).collect());
+        }
+        if self.flag("OverrideWindow")
+            && let (Some(width), Some(height)) =
+                (self.number("MinecraftWinWidth"), self.number("MinecraftWinHeight"))
+        {
+            settings.resolution = Some((width, height));
+        }
+        settings
+    }
+}
+
+/// path 是实例目录时返回它本身，是 instances 目录时返回其中的所有实例
+pub fn find_instances(path: &Path) -> Vec<PathBuf> {
+    if path.join(CONFIG_FILE).is_file() {
+        return vec![path.to_path_buf()];
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut instances: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(CONFIG_FILE).is_file())
+        .collect();
+    instances.sort();
+    instances
+}
+
+/// 导入 path 下的所有实例
+///
+/// link 为 true 时实例直接使用原来的游戏目录，否则复制到新实例中
+pub fn import_instances(
+    root: &GameRoot,
+    path: &Path,
+    link: bool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Vec<Instance>> {
+    let sources = find_instances(path)
+        .iter()
+        .map(|path| MultiMcInstance::load(path))
+        .collect::<Result<Vec<_>>>()?;
+    if sources.is_empty() {
+        bail!("no multimc instance found in {:?}", path);
+    }
+    let total = if link {
+        0
+    } else {
+        sources
+            .iter()
+            .filter_map(|source| source.game_directory())
+            .map(|dir| count_files(&dir))
+            .sum()
+    };
+    let mut done = 0;
+    progress((done, total));
+    sources
+        .iter()
+        .map(|source| {
+            import_instance(root, source, link, &mut || {
+                done += 1;
+                progress((done, total));
+            })
+        })
+        .collect()
+}
+
+/// 导入一个实例，安装了对应的版本时自动关联
+pub fn import_instance(
+    root: &GameRoot,
+    source: &MultiMcInstance,
+    link: bool,
+    on_file: &mut dyn FnMut(),
+) -> Result<Instance> {
+    let minecraft_version = source.pack.minecraft_version().unwrap_or_default();
+    let loader = source.pack.loader();
+    let mut instance = Instance::create(root, &source.name(), minecraft_version, loader.clone())?;
+    instance.icon = source.config.get("iconKey").cloned();
+    instance.settings = source.settings();
+    instance.last_played = source
+        .config
+        .get("lastLaunchTime")
+        .and_then(|time| time.parse::<i64>().ok())
+        .filter(|time| *time > 0)
+        .map(|time| time / 1000);
+    instance.playtime = source
+        .config
+        .get("totalTimePlayed")
+        .and_then(|time| time.parse().ok())
+        .unwrap_or_default();
+    if let Some(version) = find_version(root, minecraft_version, loader.as_ref())
+        && version.id != minecraft_version
+    {
+        instance.version_id = Some(version.id);
+    }
+
+    if let Some(game_dir) = source.game_directory() {
+        if link {
+            let _ = fs::remove_dir(instance.game_directory());
+            instance.game_directory = game_dir;
+        } else if let Err(e) = copy_dir(&game_dir, &instance.game_directory(), on_file) {
+            let _ = fs::remove_dir_all(&instance.path);
+            return Err(e);
+        }
+    }
+    instance.save()?;
+    info!(target: "instance", "multimc instance imported: {} from {:?}", instance.name, source.path);
+    Ok(instance)
+}
+
+/// 导出为 Prism Launcher 可以导入的 zip
+pub fn export_prism(
+    instance: &Instance,
+    dest: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<PathBuf> {
+    let mut config = BTreeMap::new();
+    config.insert("ConfigVersion".to_string(), "1.2".to_string());
+    config.insert("InstanceType".to_string(), "OneSix".to_string());
+    config.insert("name".to_string(), instance.name.clone());
+    if let Some(icon) = &instance.icon {
+        config.insert("iconKey".to_string(), icon.clone());
+    }
+    if let Some(time) = instance.last_played {
+        config.insert("lastLaunchTime".to_string(), (time * 1000).to_string());
+    }
+    config.insert("totalTimePlayed".to_string(), instance.playtime.to_string());
+    let settings = &instance.settings;
+    if let Some(java_path) = &settings.java_path {
+        config.insert("OverrideJavaLocation".to_string(), "true".to_string());
+        config.insert("JavaPath".to_string(), java_path.display().to_string());
+    }
+    if settings.min_memory.is_some() || settings.max_memory.is_some() {
+        config.insert("OverrideMemory".to_string(), "true".to_string());
+        if let Some(min) = settings.min_memory {
+            config.insert("MinMemAlloc".to_string(), min.to_string());
+        }
+        if let Some(max) = settings.max_memory {
+            config.insert("MaxMemAlloc".to_string(), max.to_string());
+        }
+    }
+    if let Some(jvm_args) = &settings.jvm_args {
+        config.insert("OverrideJavaArgs".to_string(), "true".to_string());
+        config.insert("JvmArgs".to_string(), jvm_args.join(" "));
+    }
+    if let Some((width, height)) = settings.resolution {
+        config.insert("OverrideWindow".to_string(), "true".to_string());
+        config.insert("MinecraftWinWidth".to_string(), width.to_string());
+        config.insert("MinecraftWinHeight".to_string(), height.to_string());
+    }
+    let pack = serde_json::to_string_pretty(&MmcPack::from_instance(instance))?;
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    let temp = dest.with_extension("zip.tmp");
+    let file = fs::File::create(&temp).context(format!("cannot create {:?}", temp))?;
+    let mut zip = ZipWriter::new(file);
+    let prefix = format!("{}/", instance.get_id());
+    let options = SimpleFileOptions::default();
+    zip.start_file(format!("{}{}", prefix, CONFIG_FILE), options)?;
+    zip.write_all(write_config(&config).as_bytes())?;
+    zip.start_file(format!("{}{}", prefix, PACK_FILE), options)?;
+    zip.write_all(pack.as_bytes())?;
+
+    let game_dir = instance.game_directory();
+    let total = count_files(&game_dir);
+    let mut done = 0;
+    progress((done, total));
+    if game_dir.is_dir() {
+        zip_dir(&mut zip, &game_dir, &format!("{}{}/", prefix, DEFAULT_GAME_DIR), &mut || {
+            done += 1;
+            progress((done, total));
+        })?;
+    }
+    zip.finish().context("failed to finish zip")?;
+    fs::rename(&temp, dest).context(format!("failed to write {:?}", dest))?;
+    info!(target: "instance", "instance {} exported to {:?}", instance.name, dest);
+    Ok(dest.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(json: &str) -> MmcPack {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parse_instance_config() {
+        let text = "[General]\n# comment\n; comment\nname=\"My Pack\"\nJvmArgs=-Dfoo=bar -Xss1M\n  MaxMemAlloc = 4096 \nInvalidLine\n";
+        let config = parse_config(text);
+        assert_eq!(config.len(), 3);
+        assert_eq!(config["name"], "My Pack");
+        assert_eq!(config["JvmArgs"], "-Dfoo=bar -Xss1M");
+        assert_eq!(config["MaxMemAlloc"], "4096");
+        assert_eq!(parse_config(&write_config(&config)), config);
+    }
+
+    #[test]
+    fn pack_loader() {
+        let fabric = pack(
+            r#"{"components": [
+                {"uid": "net.minecraft", "version": "1.20.1", "important": true},
+                {"uid": "net.fabricmc.intermediary", "version": "1.20.1"},
+                {"uid": "net.fabricmc.fabric-loader", "version": "0.15.0"}
+            ], "formatVersion": 1}"#,
+        );
+        assert_eq!(fabric.minecraft_version(), Some("1.20.1"));
+        assert_eq!(
+            fabric.loader(),
+            Some(ModLoader { kind: LoaderKind::Fabric, version: "0.15.0".to_string() })
+        );
+
+        let vanilla = pack(r#"{"components": [{"uid": "net.minecraft", "version": "1.12.2"}]}"#);
+        assert_eq!(vanilla.format_version, 1);
+        assert_eq!(vanilla.loader(), None);
+        assert_eq!(pack(r#"{"components": []}"#).minecraft_version(), None);
+    }
+
+    #[test]
+    fn overridden_settings() {
+        let instance = MultiMcInstance {
+            path: PathBuf::from("instances/pack"),
+            config: parse_config(
+                "OverrideMemory=true\nMinMemAlloc=1024\nMaxMemAlloc=4096\n\
+                 OverrideJavaArgs=false\nJvmArgs=-Xss1M\n\
+                 OverrideWindow=true\nMinecraftWinWidth=1280\nMinecraftWinHeight=720\nname=",
+            ),
+            pack: pack(r#"{"components": []}"#),
+        };
+        let settings = instance.settings();
+        assert_eq!(settings.min_memory, Some(1024));
+        assert_eq!(settings.max_memory, Some(4096));
+        assert_eq!(settings.jvm_args, None);
+        assert_eq!(settings.resolution, Some((1280, 720)));
+        // 名称为空时使用目录名
+        assert_eq!(instance.name(), "pack");
+    }
+}
@@ -0,0 +1,78 @@
+//! 复制、打包实例文件等耗时操作的后台任务
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use anyhow::Result;
+
+use crate::statue::Status;
+
+/// (已处理的文件数, 文件总数)
+pub type Progress = (usize, usize);
+
+enum TaskMessage<T> {
+    Progress(Progress),
+    Done(Result<T, String>),
+}
+
+/// 在后台线程中执行一个实例文件操作
+pub struct InstanceTask<T> {
+    rx: Option<Receiver<TaskMessage<T>>>,
+    state: Option<Status<T, Progress, String>>,
+}
+
+impl<T: Clone + Send + 'static> InstanceTask<T> {
+    /// job 通过传入的回调报告进度
+    pub fn start<F>(&mut self, job: F)
+    where
+        F: FnOnce(&mut dyn FnMut(Progress)) -> Result<T> + Send + 'static,
+    {
+        let (tx, rx): (Sender<TaskMessage<T>>, _) = mpsc::channel();
+        self.rx = Some(rx);
+        self.state = Some(Status::Progress((0, 0)));
+        thread::spawn(move || {
+            let mut progress = |progress: Progress| {
+                let _ = tx.send(TaskMessage::Progress(progress));
+            };
+            let result = job(&mut progress).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(TaskMessage::Done(result));
+        });
+    }
+
+    /// None 表示没有进行中的任务
+    pub fn get_state(&mut self) -> Option<Status<T, Progress, String>> {
+        if let Some(rx) = &self.rx {
+            while let Ok(message) = rx.try_recv() {
+                self.state = Some(match message {
+                    TaskMessage::Progress(progress) => Status::Progress(progress),
+                    TaskMessage::Done(Ok(value)) => Status::Success(value),
+                    TaskMessage::Done(Err(e)) => Status::Failed(e),
+                });
+            }
+        }
+        match &self.state {
+            Some(Status::Success(value)) => Some(Status::Success(value.clone())),
+            Some(Status::Progress(progress)) => Some(Status::Progress(*progress)),
+            Some(Status::Failed(e)) => Some(Status::Failed(e.clone())),
+            None => None,
+        }
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        self.get_state().is_some_and(|state| state.is_progress())
+    }
+
+    /// 取走结果并回到初始状态
+    pub fn set_none(&mut self) {
+        self.rx = None;
+        self.state = None;
+    }
+}
+
+impl<T> Default for InstanceTask<T> {
+    fn default() -> Self {
+        Self { rx: None, state: None }
+    }
+}
@@ -0,0 +1,322 @@
+//! 查找本机安装的 Java
+
+pub mod runtime;
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// 一个 Java 安装
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JavaInstallation {
+    /// java 可执行文件
+    pub path: PathBuf,
+    /// JAVA_HOME
+    pub home: PathBuf,
+    pub major_version: u32,
+    pub version: String,
+    pub vendor: String,
+    pub arch: String,
+    /// 是否由启动器下载管理
+    #[serde(default)]
+    pub managed: bool,
+}
+
+/// 缓存的 Java 列表，调用 refresh 重新扫描
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct JavaRegistry {
+    pub installations: Vec<JavaInstallation>,
+    /// 启动器管理的运行时目录等额外扫描位置
+    #[serde(skip)]
+    pub extra_dirs: Vec<PathBuf>,
+    #[serde(skip)]
+    cache_path: Option<PathBuf>,
+}
+
+impl JavaRegistry {
+    /// 从缓存文件加载，缓存中已经不存在的 Java 会被移除
+    pub fn load(cache_path: &Path) -> Self {
+        let mut registry = fs::read_to_string(cache_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<JavaRegistry>(&content).ok())
+            .unwrap_or_default();
+        registry.installations.retain(|java| java.path.is_file());
+        registry.cache_path = Some(cache_path.to_path_buf());
+        registry
+    }
+
+    pub fn with_extra_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.extra_dirs.push(dir.into());
+        self
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+        }
+        let content = serde_json::to_string_pretty(self).context("failed to serialize java list")?;
+        fs::write(path, content).context(format!("failed to write java list: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 重新扫描所有位置并更新缓存
+    pub fn refresh(&mut self) -> Result<()> {
+        self.installations = discover(&self.extra_dirs);
+        info!(target: "java", "found {} java installations", self.installations.len());
+        self.save()
+    }
+
+    /// 手动添加一个 java 可执行文件
+    pub fn add(&mut self, java: &Path) -> Result<JavaInstallation> {
+        let installation = inspect(java)?;
+        if !self.installations.iter().any(|j| j.path == installation.path) {
+            self.installations.push(installation.clone());
+            self.save()?;
+        }
+        Ok(installation)
+    }
+
+    pub fn find_major(&self, major: u32) -> Vec<&JavaInstallation> {
+        self.installations
+            .iter()
+            .filter(|java| java.major_version == major)
+            .collect()
+    }
+}
+
+/// 扫描常见位置下的所有 Java
+pub fn discover(extra_dirs: &[PathBuf]) -> Vec<JavaInstallation> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        candidates.push(java_executable(Path::new(&home)));
+    }
+    if let Some(paths) = env::var_os("PATH") {
+        for dir in env::split_paths(&paths) {
+            candidates.push(dir.join(java_file_name()));
+        }
+    }
+
+    let mut roots: Vec<PathBuf> = vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/usr/lib64/jvm"),
+        PathBuf::from("/usr/java"),
+        PathBuf::from("/opt/java"),
+        PathBuf::from("/Library/Java/JavaVirtualMachines"),
+    ];
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        roots.push(home.join(".sdkman/candidates/java"));
+        roots.push(home.join(".asdf/installs/java"));
+        roots.push(home.join(".jdks"));
+    }
+    if let Some(sdkman) = env::var_os("SDKMAN_DIR").map(PathBuf::from) {
+        roots.push(sdkman.join("candidates/java"));
+    }
+    if let Some(program_files) = env::var_os("ProgramFiles").map(PathBuf::from) {
+        for vendor in ["Java", "Eclipse Adoptium", "Zulu", "Microsoft"] {
+            roots.push(program_files.join(vendor));
+        }
+    }
+    roots.extend(extra_dirs.iter().cloned());
+
+    for root in &roots {
+        candidates.extend(java_homes_under(root).iter().map(|home| java_executable(home)));
+    }
+
+    let mut seen = Vec::new();
+    let mut installations = Vec::new();
+    for candidate in candidates {
+        let Ok(real) = fs::canonicalize(&candidate) else {
+            continue;
+        };
+        if !real.is_file() || seen.contains(&real) {
+            continue;
+        }
+        seen.push(real.clone());
+        match inspect(&real) {
+            Ok(mut java) => {
+                java.managed = extra_dirs.iter().any(|dir| real.starts_with(dir));
+                installations.push(java);
+            }
+            Err(e) => warn!(target: "java", "skip {:?}: {:#}", real, e),
+        }
+    }
+    installations.sort_by(|a, b| b.major_version.cmp(&a.major_version).then(a.path.cmp(&b.path)));
+    installations
+}
+
+/// root 下两层以内的所有 Java home
+fn java_homes_under(root: &Path) -> Vec<PathBuf> {
+    let mut homes = Vec::new();
+    let Ok(entries) = fs::read_dir(root) else {
+        return homes;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let dir = entry.path();
+        // macOS: xxx.jdk/Contents/Home；Mojang 运行时: java-runtime-gamma/linux/java-runtime-gamma
+        let nested = [
+            dir.clone(),
+            dir.join("Contents/Home"),
+            dir.join("jre"),
+        ];
+        let mut found = false;
+        for home in nested {
+            if java_executable(&home).is_file() {
+                homes.push(home);
+                found = true;
+                break;
+            }
+        }
+        if !found && let Ok(children) = fs::read_dir(&dir) {
+            for child in children.filter_map(|entry| entry.ok()) {
+                for home in [child.path(), child.path().join(entry.file_name())] {
+                    if java_executable(&home).is_file() {
+                        homes.push(home);
+                    }
+                }
+            }
+        }
+    }
+    homes
+}
+
+fn java_file_name() -> &'static str {
+    if cfg!(windows) { "java.exe" } else { "java" }
+}
+
+fn java_executable(home: &Path) -> PathBuf {
+    home.join("bin").join(java_file_name())
+}
+
+/// 读取一个 java 可执行文件的版本信息
+pub fn inspect(java: &Path) -> Result<JavaInstallation> {
+    let path = fs::canonicalize(java).context(format!("java not found: {:?}", java))?;
+    // <home>/bin/java
+    let home = path
+        .parent()
+        .and_then(|bin| bin.parent())
+        .map(Path::to_path_buf)
+        .context("invalid java path")?;
+
+    let properties = read_release(&home).or_else(|| read_properties(&path));
+    let Some(properties) = properties else {
+        anyhow::bail!("cannot read java version: {:?}", path);
+    };
+    let version = properties.get("version").cloned().unwrap_or_default();
+    let major_version = parse_major_version(&version).context(format!("invalid java version: {}", version))?;
+
+    Ok(JavaInstallation {
+        path,
+        home,
+        major_version,
+        version,
+        vendor: properties.get("vendor").cloned().unwrap_or_default(),
+        arch: properties.get("arch").cloned().unwrap_or_default(),
+        managed: false,
+    })
+}
+
+/// JAVA_HOME/release 文件，例如 JAVA_VERSION="17.0.8"
+fn read_release(home: &Path) -> Option<HashMap<&'static str, String>> {
+    let content = fs::read_to_string(home.join("release")).ok()?;
+    let mut properties = HashMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "JAVA_VERSION" => properties.insert("version", value),
+            "IMPLEMENTOR" => properties.insert("vendor", value),
+            "OS_ARCH" => properties.insert("arch", value),
+            _ => None,
+        };
+    }
+    properties.contains_key("version").then_some(properties)
+}
+
+/// 没有 release 文件时运行 java -XshowSettings:properties -version
+fn read_properties(java: &Path) -> Option<HashMap<&'static str, String>> {
+    let output = Command::new(java)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+    // 属性输出在 stderr
+    let text = String::from_utf8_lossy(&output.stderr);
+    let mut properties = HashMap::new();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "java.version" => properties.insert("version", value),
+            "java.vendor" => properties.insert("vendor", value),
+            "os.arch" => properties.insert("arch", value),
+            _ => None,
+        };
+    }
+    properties.contains_key("version").then_some(properties)
+}
+
+/// 1.8.0_382 -> 8，17.0.8 -> 17，21 -> 21
+pub fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '_', '-', '+']);
+    let first: u32 = parts.next()?.parse().ok()?;
+    if first == 1 {
+        parts.next()?.parse().ok()
+    } else {
+        Some(first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_major_version("1.8.0_382"), Some(8));
+        assert_eq!(parse_major_version("1.8.0-internal"), Some(8));
+        assert_eq!(parse_major_version("17.0.2"), Some(17));
+        assert_eq!(parse_major_version("17.0.8+7"), Some(17));
+        assert_eq!(parse_major_version("21"), Some(21));
+        assert_eq!(parse_major_version("21-ea"), Some(21));
+        assert_eq!(parse_major_version(""), None);
+        assert_eq!(parse_major_version("1"), None);
+        assert_eq!(parse_major_version("openjdk"), None);
+    }
+
+    #[test]
+    fn parse_release_file() {
+        let home = std::env::temp_dir().join(format!("mctui-java-release-{}", std::process::id()));
+        fs::create_dir_all(&home).unwrap();
+        assert_eq!(read_release(&home), None);
+
+        fs::write(
+            home.join("release"),
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"1.8.0_382\"\nOS_ARCH=\"amd64\"\nOTHER=1\n",
+        )
+        .unwrap();
+        let properties = read_release(&home).unwrap();
+        assert_eq!(properties["version"], "1.8.0_382");
+        assert_eq!(properties["vendor"], "Eclipse Adoptium");
+        assert_eq!(properties["arch"], "amd64");
+        assert_eq!(parse_major_version(&properties["version"]), Some(8));
+
+        // 没有 JAVA_VERSION 时视为无法读取
+        fs::write(home.join("release"), "IMPLEMENTOR=\"Oracle\"\n").unwrap();
+        assert_eq!(read_release(&home), None);
+        fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -0,0 +1,483 @@
+//! 下载 Mojang 提供的 Java 运行时（版本 json 中的 javaVersion.component）
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    download::download_pool::DownloadPool,
+    install::{minecraft::version_json::VersionJson, verify::file_sha1},
+    statue::Status,
+};
+
+pub const RUNTIME_ALL_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// 没有声明 javaVersion 的旧版本使用 Java 8
+pub const LEGACY_COMPONENT: &str = "jre-legacy";
+
+/// 下载停滞多久后视为失败
+const STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// all.json: 平台 -> 组件 -> 可用版本
+pub type RuntimeIndex = HashMap<String, HashMap<String, Vec<RuntimeEntry>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeEntry {
+    pub manifest: RuntimeDownload,
+    pub version: RuntimeVersion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeVersion {
+    pub name: String,
+    #[serde(default)]
+    pub released: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeDownload {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+/// 组件 manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeManifest {
+    pub files: HashMap<String, RuntimeFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeFile {
+    Directory,
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeFileDownloads {
+    pub raw: RuntimeDownload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lzma: Option<RuntimeDownload>,
+}
+
+/// Mojang 运行时使用的平台名
+pub fn runtime_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+/// 版本需要的运行时组件
+pub fn runtime_component(profile: &VersionJson) -> &str {
+    profile
+        .java_version
+        .as_ref()
+        .map(|java| java.component.as_str())
+        .filter(|component| !component.is_empty())
+        .unwrap_or(LEGACY_COMPONENT)
+}
+
+/// 组件安装后的 JAVA_HOME
+pub fn runtime_home(runtimes_dir: &Path, component: &str) -> PathBuf {
+    runtimes_dir.join(component)
+}
+
+/// 已安装的组件对应的 java 可执行文件
+pub fn installed_runtime(runtimes_dir: &Path, component: &str) -> Option<PathBuf> {
+    let home = runtime_home(runtimes_dir, component);
+    home.join(".version").is_file().then(|| find_java(&home)).flatten()
+}
+
+/// 运行时目录中的 java，macOS 的运行时在 jre.bundle/Contents/Home 下
+fn find_java(home: &Path) -> Option<PathBuf> {
+    let exe = if cfg!(windows) { "javaw.exe" } else { "java" };
+    [home.to_path_buf(), home.join("jre.bundle").join("Contents").join("Home")]
+        .into_iter()
+        .map(|java_home| java_home.join("bin").join(exe))
+        .find(|java| java.is_file())
+}
+
+/// manifest 中的文件名只能是 home 下的相对路径
+fn runtime_path(home: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("unsafe file name in java runtime manifest: {:?}", name);
+    }
+    Ok(home.join(relative))
+}
+
+/// 链接目标相对于链接所在目录，可以用 `..`，但不能指到 home 之外
+fn check_link_target(name: &str, target: &str) -> Result<()> {
+    let mut depth = Path::new(name).components().count().saturating_sub(1);
+    for component in Path::new(target).components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => bail!("unsafe link target in java runtime manifest: {:?} -> {:?}", name, target),
+        }
+    }
+    Ok(())
+}
+
+fn get_text(url: &str) -> Result<String> {
+    attohttpc::get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .context(format!("failed to download {}", url))
+}
+
+fn get_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T> {
+    let text = get_text(url)?;
+    serde_json::from_str(&text).context(format!("failed to parse {}", url))
+}
+
+/// 校验 manifest 的 sha1 后解析
+fn parse_manifest(text: &str, download: &RuntimeDownload) -> Result<RuntimeManifest> {
+    let sha1 = sha1_smol::Sha1::from(text.as_bytes()).digest().to_string();
+    if !sha1.eq_ignore_ascii_case(&download.sha1) {
+        bail!("sha1 mismatch: {}", download.url);
+    }
+    serde_json::from_str(text).context(format!("failed to parse {}", download.url))
+}
+
+/// 获取组件在当前平台的 manifest
+pub fn fetch_manifest(index_url: &str, component: &str) -> Result<(RuntimeEntry, RuntimeManifest)> {
+    let platform = runtime_platform().context("java runtime is not available on this platform")?;
+    let index: RuntimeIndex = get_json(index_url)?;
+    let entry = index
+        .get(platform)
+        .and_then(|components| components.get(component))
+        .and_then(|entries| entries.first())
+        .cloned()
+        .context(format!("java runtime {} is not available for {}", component, platform))?;
+    let manifest = parse_manifest(&get_text(&entry.manifest.url)?, &entry.manifest)?;
+    Ok((entry, manifest))
+}
+
+struct PendingFile {
+    path: PathBuf,
+    download_path: PathBuf,
+    download: RuntimeDownload,
+    raw: RuntimeDownload,
+    lzma: bool,
+    executable: bool,
+}
+
+/// 下载并安装组件，返回 java 可执行文件，done/total 用于报告进度
+pub fn install_runtime(
+    pool: &DownloadPool,
+    index_url: &str,
+    runtimes_dir: &Path,
+    component: &str,
+    done: &AtomicUsize,
+    total: &AtomicUsize,
+) -> Result<PathBuf> {
+    let (entry, manifest) = fetch_manifest(index_url, component)?;
+    let home = runtime_home(runtimes_dir, component);
+    fs::create_dir_all(&home).context(format!("cannot create directory: {:?}", home))?;
+
+    // 先建目录，找出需要下载的文件
+    let mut pending = Vec::new();
+    let mut links = Vec::new();
+    let mut executables = Vec::new();
+    for (name, file) in &manifest.files {
+        let path = runtime_path(&home, name)?;
+        match file {
+            RuntimeFile::Directory => {
+                fs::create_dir_all(&path).context(format!("cannot create directory: {:?}", path))?;
+            }
+            RuntimeFile::Link { target } => {
+                check_link_target(name, target)?;
+                links.push((path, target.clone()));
+            }
+            RuntimeFile::File { executable, downloads } => {
+                if *executable {
+                    executables.push(path.clone());
+                }
+                if file_sha1(&path).is_ok_and(|sha1| sha1 == downloads.raw.sha1) {
+                    continue;
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .context(format!("cannot create directory: {:?}", parent))?;
+                }
+                // 优先下载体积更小的 lzma 压缩版本
+                // 临时文件名保留完整的文件名，避免 java.dll 和 java.exe 冲突
+                let (download, download_path, lzma) = match (&downloads.lzma, path.file_name()) {
+                    (Some(lzma), Some(name)) => {
+                        let name = format!("{}.lzma-download", name.to_string_lossy());
+                        (lzma.clone(), path.with_file_name(name), true)
+                    }
+                    _ => (downloads.raw.clone(), path.clone(), false),
+                };
+                pending.push(PendingFile {
+                    path,
+                    download_path,
+                    download,
+                    raw: downloads.raw.clone(),
+                    lzma,
+                    executable: *executable,
+                });
+            }
+        }
+    }
+
+    total.store(pending.len(), Ordering::Relaxed);
+    info!(target: "java", "installing {} {}: {} files to download", component, entry.version.name, pending.len());
+    for file in &pending {
+        let _ = fs::remove_file(&file.download_path);
+        pool.add_task(
+            file.download.url.clone(),
+            file.download_path.to_string_lossy().to_string(),
+        );
+    }
+    wait_downloads(pool, &pending, done)?;
+
+    for file in &pending {
+        if file.lzma {
+            decompress_lzma(&file.download_path, &file.path)?;
+            let _ = fs::remove_file(&file.download_path);
+        }
+        let sha1 = file_sha1(&file.path).context(format!("cannot read {:?}", file.path))?;
+        if sha1 != file.raw.sha1 {
+            bail!("sha1 mismatch: {:?}", file.path);
+        }
+        if file.executable {
+            set_executable(&file.path)?;
+        }
+    }
+    for path in &executables {
+        set_executable(path)?;
+    }
+    for (path, target) in &links {
+        create_link(path, target)?;
+    }
+
+    fs::write(home.join(".version"), &entry.version.name)
+        .context("failed to write runtime version")?;
+    info!(target: "java", "java runtime {} installed to {:?}", component, home);
+    installed_runtime(runtimes_dir, component)
+        .context(format!("java executable not found in {:?}", home))
+}
+
+/// 等待下载池把所有文件下载完
+fn wait_downloads(pool: &DownloadPool, pending: &[PendingFile], done: &AtomicUsize) -> Result<()> {
+    let mut last_progress = Instant::now();
+    loop {
+        let finished = pending
+            .iter()
+            .filter(|file| {
+                fs::metadata(&file.download_path).is_ok_and(|m| m.len() == file.download.size)
+            })
+            .count();
+        if finished != done.swap(finished, Ordering::Relaxed) {
+            last_progress = Instant::now();
+        }
+        if finished == pending.len() {
+            return Ok(());
+        }
+        if let Some(file) = pending.iter().find(|file| pool.is_failed(&file.download_path)) {
+            bail!("failed to download java runtime file: {}", file.download.url);
+        }
+        if last_progress.elapsed() > STALL_TIMEOUT {
+            bail!("java runtime download stalled");
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+fn decompress_lzma(from: &Path, to: &Path) -> Result<()> {
+    let mut input = BufReader::new(File::open(from).context(format!("cannot open {:?}", from))?);
+    let mut output = BufWriter::new(File::create(to).context(format!("cannot create {:?}", to))?);
+    lzma_rs::lzma_decompress(&mut input, &mut output)
+        .map_err(|e| anyhow!("failed to decompress {:?}: {:?}", from, e))
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .context(format!("cannot set permissions: {:?}", path))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_link(path: &Path, target: &str) -> Result<()> {
+    let _ = fs::remove_file(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    std::os::unix::fs::symlink(target, path).context(format!("cannot create link: {:?}", path))
+}
+
+#[cfg(not(unix))]
+fn create_link(path: &Path, target: &str) -> Result<()> {
+    // 没有符号链接权限时直接复制
+    let source = path.parent().map(|p| p.join(target)).unwrap_or_default();
+    if source.is_file() {
+        fs::copy(&source, path).context(format!("cannot copy {:?}", source))?;
+    }
+    Ok(())
+}
+
+/// 在后台线程中安装运行时
+pub struct RuntimeInstaller {
+    done: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+    rx: Option<Receiver<Result<PathBuf, String>>>,
+    result: Option<Result<PathBuf, String>>,
+}
+
+impl RuntimeInstaller {
+    /// 开始安装 component，之前的结果会被清空
+    pub fn start(&mut self, pool: DownloadPool, runtimes_dir: PathBuf, component: String) {
+        self.start_from_url(pool, RUNTIME_ALL_URL.to_string(), runtimes_dir, component);
+    }
+
+    pub fn start_from_url(
+        &mut self,
+        pool: DownloadPool,
+        index_url: String,
+        runtimes_dir: PathBuf,
+        component: String,
+    ) {
+        let (tx, rx) = mpsc::channel();
+        self.done = Arc::new(AtomicUsize::new(0));
+        self.total = Arc::new(AtomicUsize::new(0));
+        self.rx = Some(rx);
+        self.result = None;
+
+        let done = self.done.clone();
+        let total = self.total.clone();
+        thread::spawn(move || {
+            let result = install_runtime(&pool, &index_url, &runtimes_dir, &component, &done, &total)
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    /// None 表示还没有开始安装，Progress 中为 (已下载, 总数)
+    pub fn get_state(&mut self) -> Option<Status<PathBuf, (usize, usize), String>> {
+        if let Some(rx) = &self.rx
+            && let Ok(result) = rx.try_recv()
+        {
+            self.result = Some(result);
+            self.rx = None;
+        }
+        match &self.result {
+            Some(Ok(java)) => Some(Status::Success(java.clone())),
+            Some(Err(e)) => Some(Status::Failed(e.clone())),
+            None if self.rx.is_some() => Some(Status::Progress((
+                self.done.load(Ordering::Relaxed),
+                self.total.load(Ordering::Relaxed),
+            ))),
+            None => None,
+        }
+    }
+}
+
+impl Default for RuntimeInstaller {
+    fn default() -> Self {
+        Self {
+            done: Arc::new(AtomicUsize::new(0)),
+            total: Arc::new(AtomicUsize::new(0)),
+            rx: None,
+            result: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_java_in_mac_bundle() {
+        let dir = std::env::temp_dir().join(format!("mctui-runtime-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let exe = if cfg!(windows) { "javaw.exe" } else { "java" };
+        let home = runtime_home(&dir, "java-runtime-gamma");
+        let bin = home.join("jre.bundle/Contents/Home/bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join(exe), b"").unwrap();
+        // 没有 .version 时视为未安装完成
+        assert_eq!(installed_runtime(&dir, "java-runtime-gamma"), None);
+        fs::write(home.join(".version"), "17.0.8").unwrap();
+        assert_eq!(installed_runtime(&dir, "java-runtime-gamma"), Some(bin.join(exe)));
+
+        fs::create_dir_all(home.join("bin")).unwrap();
+        fs::write(home.join("bin").join(exe), b"").unwrap();
+        assert_eq!(
+            installed_runtime(&dir, "java-runtime-gamma"),
+            Some(home.join("bin").join(exe))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsafe_names() {
+        let home = Path::new("runtime");
+        assert_eq!(runtime_path(home, "bin/java").unwrap(), home.join("bin/java"));
+        assert!(runtime_path(home, "../java").is_err());
+        assert!(runtime_path(home, "bin/../../java").is_err());
+        assert!(runtime_path(home, "/etc/passwd").is_err());
+
+        assert!(check_link_target("jre.bundle/Contents/MacOS/libjli.dylib", "../Home/lib/libjli.dylib").is_ok());
+        assert!(check_link_target("bin/java", "../lib/java").is_ok());
+        assert!(check_link_target("bin/java", "../../java").is_err());
+        assert!(check_link_target("java", "../java").is_err());
+        assert!(check_link_target("bin/java", "/usr/bin/java").is_err());
+    }
+
+    #[test]
+    fn verifies_manifest_sha1() {
+        let text = r#"{"files": {"bin": {"type": "directory"}, "bin/java": {"type": "link", "target": "../lib/java"}}}"#;
+        let mut download = RuntimeDownload {
+            sha1: sha1_smol::Sha1::from(text.as_bytes()).digest().to_string(),
+            size: text.len() as u64,
+            url: "https://example.com/manifest.json".to_string(),
+        };
+        let manifest = parse_manifest(text, &download).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(matches!(manifest.files["bin/java"], RuntimeFile::Link { .. }));
+
+        download.sha1 = "0".repeat(40);
+        let e = parse_manifest(text, &download).unwrap_err();
+        assert!(e.to_string().contains("sha1 mismatch"));
+    }
+}
@@ -0,0 +1,421 @@
+//! 根据版本信息、账号和启动选项生成 Java 启动命令
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use log::warn;
+
+use crate::{
+    account::base::AccountBase,
+    launch::memory::{JvmPreset, filter_jvm_flags},
+    install::minecraft::{
+        asset_index::AssetIndex,
+        game_root::GameRoot,
+        rule::RuleContext,
+        version_json::{Argument, VersionJson},
+    },
+};
+
+pub mod authlib_injector;
+pub mod crash;
+pub mod jvm;
+pub mod log4j;
+pub mod memory;
+pub mod natives;
+pub mod process;
+
+/// 每次启动时的选项
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    pub java_path: PathBuf,
+    /// 游戏运行目录，存档、模组和配置都在这里
+    pub game_directory: PathBuf,
+    pub launcher_name: String,
+    pub launcher_version: String,
+    /// 单位 MB
+    pub min_memory: Option<u32>,
+    /// 单位 MB
+    pub max_memory: Option<u32>,
+    /// 窗口大小 (宽, 高)
+    pub resolution: Option<(u32, u32)>,
+    pub demo: bool,
+    /// 使用版本自带的 log4j 配置，游戏会以 XML 格式输出日志
+    pub use_log_config: bool,
+    /// 所选 Java 的主版本，用于过滤不支持的 JVM 参数
+    pub java_major: Option<u32>,
+    pub jvm_preset: JvmPreset,
+    /// `-javaagent` 等参数，例如 [`authlib_injector::prepare`] 的结果
+    pub agent_args: Vec<String>,
+    /// 追加在版本自带参数之前的 JVM 参数
+    pub jvm_args: Vec<String>,
+    /// 追加在版本自带参数之后的游戏参数
+    pub game_args: Vec<String>,
+    /// 游戏进程额外的环境变量
+    pub env: Vec<(String, String)>,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self {
+            java_path: PathBuf::from("java"),
+            game_directory: PathBuf::from(".minecraft"),
+            launcher_name: "mctui".to_string(),
+            launcher_version: env!("CARGO_PKG_VERSION").to_string(),
+            min_memory: None,
+            max_memory: None,
+            resolution: None,
+            demo: false,
+            use_log_config: true,
+            java_major: None,
+            jvm_preset: JvmPreset::Default,
+            agent_args: Vec::new(),
+            jvm_args: Vec::new(),
+            game_args: Vec::new(),
+            env: Vec::new(),
+        }
+    }
+}
+
+/// 生成好的启动命令
+#[derive(Debug, Clone)]
+pub struct LaunchCommand {
+    pub java: PathBuf,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub natives_dir: PathBuf,
+    pub env: Vec<(String, String)>,
+}
+
+impl LaunchCommand {
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.java);
+        command
+            .args(&self.args)
+            .current_dir(&self.working_dir)
+            .envs(self.env.iter().map(|(key, value)| (key, value)));
+        command
+    }
+
+    /// 日志中使用，隐藏 access token
+    pub fn display_hidden(&self, access_token: &str) -> String {
+        let mut line = self.java.display().to_string();
+        for arg in &self.args {
+            line.push(' ');
+            if !access_token.is_empty() && arg.contains(access_token) {
+                line.push_str(&arg.replace(access_token, "********"));
+            } else {
+                line.push_str(arg);
+            }
+        }
+        line
+    }
+}
+
+/// 生成启动命令，profile 应当是合并过 inheritsFrom 的版本信息
+pub fn build_launch_command<A: AccountBase>(
+    root: &GameRoot,
+    profile: &VersionJson,
+    account: &A,
+    options: &LaunchOptions,
+) -> LaunchCommand {
+    let ctx = rule_context(options);
+    let natives_dir = root.natives_dir(&profile.id);
+    let variables = launch_variables(root, profile, account, options, &natives_dir, &ctx);
+
+    let mut args = Vec::new();
+    if let Some(min) = options.min_memory {
+        args.push(format!("-Xms{}m", min));
+    }
+    if let Some(max) = options.max_memory {
+        args.push(format!("-Xmx{}m", max));
+    }
+    args.extend(options.agent_args.iter().cloned());
+    match options.java_major {
+        Some(major) => {
+            args.extend(options.jvm_preset.flags(major, options.max_memory));
+            args.extend(filter_jvm_flags(&options.jvm_args, major));
+        }
+        // 不知道 Java 版本时不使用预设
+        None => args.extend(options.jvm_args.iter().cloned()),
+    }
+
+    let (jvm, game, legacy) = profile_arguments(profile, &ctx);
+
+    args.extend(jvm.iter().map(|arg| substitute(arg, &variables)));
+    if options.use_log_config
+        && let Some(config) = profile.logging.as_ref().and_then(|l| l.client.as_ref())
+    {
+        let path = path_string(&root.log_config(&config.file.id));
+        args.push(config.argument.replace("${path}", &path));
+    }
+    args.push(profile.main_class.clone());
+    args.extend(game.iter().map(|arg| substitute(arg, &variables)));
+    if legacy {
+        if options.demo {
+            args.push("--demo".to_string());
+        }
+        if let Some((width, height)) = options.resolution {
+            args.extend([
+                "--width".to_string(),
+                width.to_string(),
+                "--height".to_string(),
+                height.to_string(),
+            ]);
+        }
+    }
+    args.extend(options.game_args.iter().cloned());
+
+    LaunchCommand {
+        java: options.java_path.clone(),
+        args,
+        working_dir: options.game_directory.clone(),
+        natives_dir,
+        env: options.env.clone(),
+    }
+}
+
+/// 规则中 features 的取值来自启动选项
+fn rule_context(options: &LaunchOptions) -> RuleContext {
+    RuleContext::current()
+        .with_feature("is_demo_user", options.demo)
+        .with_feature("has_custom_resolution", options.resolution.is_some())
+}
+
+/// 版本自带的 (JVM 参数, 游戏参数, 游戏参数是否来自 minecraftArguments)
+fn profile_arguments(profile: &VersionJson, ctx: &RuleContext) -> (Vec<String>, Vec<String>, bool) {
+    let modern = profile.arguments.as_ref().filter(|arguments| !arguments.jvm.is_empty());
+    // 没有 arguments.jvm 时（1.13 之前，或只写了 game 参数的版本）使用旧启动器的默认 JVM 参数
+    let jvm = match modern {
+        Some(arguments) => apply_arguments(&arguments.jvm, ctx),
+        None => legacy_jvm_arguments(ctx),
+    };
+    match (modern, &profile.minecraft_arguments) {
+        (None, Some(minecraft_arguments)) => {
+            let game = minecraft_arguments
+                .split_whitespace()
+                .map(|arg| arg.to_string())
+                .collect();
+            (jvm, game, true)
+        }
+        _ => {
+            let game = profile
+                .arguments
+                .as_ref()
+                .map(|arguments| apply_arguments(&arguments.game, ctx))
+                .unwrap_or_default();
+            (jvm, game, false)
+        }
+    }
+}
+
+fn apply_arguments(arguments: &[Argument], ctx: &RuleContext) -> Vec<String> {
+    arguments
+        .iter()
+        .flat_map(|argument| argument.values(ctx))
+        .map(|value| value.to_string())
+        .collect()
+}
+
+fn legacy_jvm_arguments(ctx: &RuleContext) -> Vec<String> {
+    let mut args = Vec::new();
+    if ctx.os_name == "osx" {
+        args.push("-XstartOnFirstThread".to_string());
+    }
+    if ctx.os_name == "windows" {
+        args.push(
+            "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump"
+                .to_string(),
+        );
+    }
+    args.extend([
+        "-Djava.library.path=${natives_directory}".to_string(),
+        "-Dminecraft.launcher.brand=${launcher_name}".to_string(),
+        "-Dminecraft.launcher.version=${launcher_version}".to_string(),
+        "-cp".to_string(),
+        "${classpath}".to_string(),
+    ]);
+    args
+}
+
+/// 启动参数中 `${...}` 的取值
+fn launch_variables<A: AccountBase>(
+    root: &GameRoot,
+    profile: &VersionJson,
+    account: &A,
+    options: &LaunchOptions,
+    natives_dir: &Path,
+    ctx: &RuleContext,
+) -> HashMap<&'static str, String> {
+    let assets_id = profile.assets_id();
+    // 1.7.2 之前的资源按原始文件名放在 virtual/legacy 或 resources 下
+    let game_assets = AssetIndex::load(&root.asset_index(assets_id))
+        .ok()
+        .and_then(|index| index.legacy_dir(root, assets_id, &options.game_directory))
+        .unwrap_or_else(|| match assets_id {
+            "legacy" | "pre-1.6" => root.assets_dir().join("virtual").join(assets_id),
+            _ => root.assets_dir(),
+        });
+    let (width, height) = options.resolution.unwrap_or((854, 480));
+
+    let mut variables = HashMap::new();
+    variables.insert("auth_player_name", account.get_username().to_string());
+    variables.insert("auth_uuid", account.get_uuid().to_string());
+    variables.insert("auth_access_token", account.get_access_token().to_string());
+    variables.insert(
+        "auth_session",
+        format!("token:{}:{}", account.get_access_token(), account.get_uuid()),
+    );
+    variables.insert("auth_xuid", String::new());
+    variables.insert("clientid", String::new());
+    variables.insert("user_type", account.get_user_type().to_string());
+    variables.insert("user_properties", "{}".to_string());
+    variables.insert("version_name", profile.id.clone());
+    variables.insert("version_type", profile.type_.clone());
+    variables.insert("game_directory", path_string(&options.game_directory));
+    variables.insert("assets_root", path_string(&root.assets_dir()));
+    variables.insert("game_assets", path_string(&game_assets));
+    variables.insert("assets_index_name", assets_id.to_string());
+    variables.insert("classpath", classpath(root, profile, ctx));
+    variables.insert("classpath_separator", classpath_separator().to_string());
+    variables.insert("library_directory", path_string(&root.libraries_dir()));
+    variables.insert("natives_directory", path_string(natives_dir));
+    variables.insert("launcher_name", options.launcher_name.clone());
+    variables.insert("launcher_version", options.launcher_version.clone());
+    variables.insert("resolution_width", width.to_string());
+    variables.insert("resolution_height", height.to_string());
+    variables
+}
+
+/// 所有允许的库加上客户端 jar
+pub fn classpath(root: &GameRoot, profile: &VersionJson, ctx: &RuleContext) -> String {
+    let mut entries: Vec<String> = Vec::new();
+    for library in profile.libraries.iter().filter(|l| l.is_allowed(ctx)) {
+        if let Some(path) = library.artifact().and_then(|artifact| artifact.path) {
+            let path = path_string(&root.library(&path));
+            if !entries.contains(&path) {
+                entries.push(path);
+            }
+        }
+    }
+    entries.push(path_string(&root.version_jar(profile.jar_id())));
+    entries.join(classpath_separator())
+}
+
+fn classpath_separator() -> &'static str {
+    if cfg!(windows) { ";" } else { ":" }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// 替换参数中的所有 `${name}`，未知的占位符保持原样
+pub fn substitute(arg: &str, variables: &HashMap<&'static str, String>) -> String {
+    let mut result = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        match variables.get(name) {
+            Some(value) => result.push_str(value),
+            None => {
+                warn!(target: "launch", "unknown launch placeholder: {}", name);
+                result.push_str(&rest[start..=start + end]);
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(json: &str) -> VersionJson {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn linux() -> RuleContext {
+        RuleContext {
+            os_name: "linux".to_string(),
+            os_version: String::new(),
+            os_arch: "x86_64".to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn substitute_placeholders() {
+        let variables = HashMap::from([
+            ("version_name", "1.20.1".to_string()),
+            ("game_directory", "/game".to_string()),
+        ]);
+        assert_eq!(substitute("${version_name}", &variables), "1.20.1");
+        assert_eq!(
+            substitute("--dir=${game_directory}/${version_name}!", &variables),
+            "--dir=/game/1.20.1!"
+        );
+        assert_eq!(substitute("${unknown}-${version_name}", &variables), "${unknown}-1.20.1");
+        assert_eq!(substitute("a${version_name", &variables), "a${version_name");
+        assert_eq!(substitute("plain", &variables), "plain");
+        assert_eq!(substitute("", &variables), "");
+    }
+
+    #[test]
+    fn legacy_minecraft_arguments() {
+        let profile = profile(
+            r#"{"id": "1.12.2", "minecraftArguments": "--username ${auth_player_name} --version ${version_name}"}"#,
+        );
+        let (jvm, game, legacy) = profile_arguments(&profile, &linux());
+        assert!(legacy);
+        assert!(jvm.contains(&"-Djava.library.path=${natives_directory}".to_string()));
+        assert!(jvm.ends_with(&["-cp".to_string(), "${classpath}".to_string()]));
+        assert_eq!(game, ["--username", "${auth_player_name}", "--version", "${version_name}"]);
+    }
+
+    #[test]
+    fn game_only_arguments_use_legacy_jvm() {
+        let profile = profile(r#"{"id": "custom", "arguments": {"game": ["--demo"]}}"#);
+        let (jvm, game, legacy) = profile_arguments(&profile, &linux());
+        assert!(!legacy);
+        assert!(jvm.ends_with(&["-cp".to_string(), "${classpath}".to_string()]));
+        assert_eq!(game, ["--demo"]);
+    }
+
+    #[test]
+    fn modern_arguments() {
+        let profile = profile(
+            r#"{
+                "id": "1.20.1",
+                "minecraftArguments": "--ignored",
+                "arguments": {
+                    "game": [
+                        "--username", "${auth_player_name}",
+                        {"rules": [{"action": "allow", "features": {"is_demo_user": true}}], "value": "--demo"}
+                    ],
+                    "jvm": [
+                        {"rules": [{"action": "allow", "os": {"name": "osx"}}], "value": ["-XstartOnFirstThread"]},
+                        "-cp", "${classpath}"
+                    ]
+                }
+            }"#,
+        );
+        let (jvm, game, legacy) = profile_arguments(&profile, &linux());
+        assert!(!legacy);
+        assert_eq!(jvm, ["-cp", "${classpath}"]);
+        assert_eq!(game, ["--username", "${auth_player_name}"]);
+
+        let demo = linux().with_feature("is_demo_user", true);
+        let (_, game, _) = profile_arguments(&profile, &demo);
+        assert_eq!(game, ["--username", "${auth_player_name}", "--demo"]);
+    }
+}
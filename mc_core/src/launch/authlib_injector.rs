@@ -0,0 +1,256 @@
+//! 外置登录账号启动时使用 authlib-injector 替换游戏的验证服务器
+//!
+//! 离线账号在开启本地皮肤服务器时也通过它使用 [`LocalYggdrasil`]
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::{info, warn};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    account::{Account, local_yggdrasil::LocalYggdrasil, yggdrasil_account::fetch_metadata},
+    install::minecraft::game_root::GameRoot,
+};
+
+/// 最新版本的信息
+pub const LATEST_URL: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Artifact {
+    pub build_number: u32,
+    pub version: String,
+    pub download_url: String,
+    pub checksums: Checksums,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Checksums {
+    pub sha256: String,
+}
+
+/// authlib-injector.jar 的保存位置
+pub fn jar_path(root: &GameRoot) -> PathBuf {
+    root.library("moe/yushi/authlibinjector/authlib-injector.jar")
+}
+
+/// 计算文件的 sha256
+pub fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// 记录 jar 的 sha256，无法获取最新版本信息时用来校验已下载的 jar
+fn checksum_path(jar: &Path) -> PathBuf {
+    jar.with_extension("jar.sha256")
+}
+
+/// 确保 jar 是最新版本且校验通过，返回 jar 的路径
+///
+/// 无法获取最新版本信息时，使用之前下载并校验过的 jar
+pub fn ensure_jar(root: &GameRoot, latest_url: &str) -> Result<PathBuf> {
+    let path = jar_path(root);
+    let artifact: Artifact = match attohttpc::get(latest_url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .context(format!("failed to download {}", latest_url))
+        .and_then(|text| serde_json::from_str(&text).context("failed to parse authlib-injector info"))
+    {
+        Ok(artifact) => artifact,
+        Err(e) => {
+            let recorded = fs::read_to_string(checksum_path(&path)).unwrap_or_default();
+            if !recorded.trim().is_empty()
+                && file_sha256(&path).is_ok_and(|sha256| sha256 == recorded.trim())
+            {
+                warn!(target: "launch", "using downloaded authlib-injector: {:#}", e);
+                return Ok(path);
+            }
+            return Err(e);
+        }
+    };
+    let expected = artifact.checksums.sha256.to_lowercase();
+    if file_sha256(&path).is_ok_and(|sha256| sha256 == expected) {
+        if !checksum_path(&path).is_file() {
+            fs::write(checksum_path(&path), &expected)
+                .context(format!("failed to write {:?}", checksum_path(&path)))?;
+        }
+        return Ok(path);
+    }
+
+    let bytes = attohttpc::get(&artifact.download_url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .context(format!("failed to download {}", artifact.download_url))?;
+    let sha256: String = Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if sha256 != expected {
+        bail!("sha256 mismatch of authlib-injector {}", artifact.version);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("cannot create directory: {:?}", parent))?;
+    }
+    fs::write(&path, &bytes).context(format!("failed to write {:?}", path))?;
+    fs::write(checksum_path(&path), &expected)
+        .context(format!("failed to write {:?}", checksum_path(&path)))?;
+    info!(target: "launch", "authlib-injector {} downloaded", artifact.version);
+    Ok(path)
+}
+
+/// `-javaagent` 和预先获取的元数据参数，避免游戏启动时再请求一次
+pub fn jvm_args(jar: &Path, api_root: &str, metadata: &str) -> Vec<String> {
+    vec![
+        format!("-javaagent:{}={}", jar.display(), api_root),
+        format!(
+            "-Dauthlibinjector.yggdrasil.prefetched={}",
+            STANDARD.encode(metadata)
+        ),
+    ]
+}
+
+/// 启动前调用，返回需要加入的 JVM 参数
+///
+/// 外置登录账号使用自己的服务器，离线账号在传入本地服务器时使用本地服务器，其它情况返回空
+pub fn prepare(
+    account: &Account,
+    root: &GameRoot,
+    latest_url: &str,
+    local: Option<&LocalYggdrasil>,
+) -> Result<Vec<String>> {
+    let (api_root, metadata) = match (account, local) {
+        (Account::Yggdrasil(account), _) => {
+            (account.api_root.clone(), fetch_metadata(&account.api_root)?)
+        }
+        (Account::Offline(_), Some(local)) => (local.api_root().to_string(), local.metadata()),
+        _ => return Ok(Vec::new()),
+    };
+    let jar = ensure_jar(root, latest_url)?;
+    Ok(jvm_args(&jar, &api_root, &metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        account::{offline_account::OfflineAccount, yggdrasil_account::YggdrasilAccount},
+        test_server::{Request, Response, TestServer},
+    };
+
+    const JAR: &[u8] = b"authlib-injector jar";
+
+    fn sha256(bytes: &[u8]) -> String {
+        Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// 模拟外置登录服务器和 authlib-injector 下载站，online 为 false 时无法获取最新版本信息
+    fn start_server(online: Arc<AtomicBool>, checksum: String) -> TestServer {
+        TestServer::start(move |request: &Request| match request.path.as_str() {
+            "/api/yggdrasil" => Response::json(200, json!({ "meta": { "serverName": "Test Skin" } })),
+            "/latest.json" if online.load(Ordering::SeqCst) => Response::json(200, json!({
+                "build_number": 53,
+                "version": "1.2.5",
+                "download_url": format!("http://{}/authlib-injector.jar", request.header("Host").unwrap()),
+                "checksums": { "sha256": checksum },
+            })),
+            "/authlib-injector.jar" => Response::bytes(JAR),
+            _ => Response::status(404),
+        })
+    }
+
+    fn yggdrasil_account(server: &TestServer) -> Account {
+        Account::Yggdrasil(YggdrasilAccount {
+            api_root: format!("{}/api/yggdrasil", server.url),
+            server_name: "Test Skin".to_string(),
+            login: "alice".to_string(),
+            username: "Alice".to_string(),
+            uuid: "a1".to_string(),
+            profiles: Vec::new(),
+            client_token: "client".to_string(),
+            access_token: "token".to_string(),
+        })
+    }
+
+    #[test]
+    fn prefetched_metadata_is_base64() {
+        let args = jvm_args(Path::new("a.jar"), "http://127.0.0.1/api", "{\"meta\":{}}");
+        assert_eq!(args[0], "-javaagent:a.jar=http://127.0.0.1/api");
+        assert_eq!(args[1], "-Dauthlibinjector.yggdrasil.prefetched=eyJtZXRhIjp7fX0=");
+    }
+
+    #[test]
+    fn prepare_downloads_and_falls_back() {
+        let base = std::env::temp_dir().join(format!("mctui-authlib-{}", std::process::id()));
+        let root = GameRoot::new(&base);
+        let online = Arc::new(AtomicBool::new(true));
+        let server = start_server(online.clone(), sha256(JAR).to_uppercase());
+        let latest_url = format!("{}/latest.json", server.url);
+        let account = yggdrasil_account(&server);
+
+        let args = prepare(&account, &root, &latest_url, None).unwrap();
+        let jar = jar_path(&root);
+        assert_eq!(args[0], format!("-javaagent:{}={}/api/yggdrasil", jar.display(), server.url));
+        let metadata = STANDARD.decode(args[1].split_once('=').unwrap().1).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&metadata).unwrap(),
+            json!({ "meta": { "serverName": "Test Skin" } })
+        );
+        assert_eq!(fs::read(&jar).unwrap(), JAR);
+        assert_eq!(fs::read_to_string(checksum_path(&jar)).unwrap(), sha256(JAR));
+
+        // 已是最新版本时不再下载
+        assert_eq!(ensure_jar(&root, &latest_url).unwrap(), jar);
+        let downloads = server.requests().iter().filter(|r| r.path == "/authlib-injector.jar").count();
+        assert_eq!(downloads, 1);
+
+        // 无法获取最新版本信息时使用已校验的 jar
+        online.store(false, Ordering::SeqCst);
+        assert_eq!(ensure_jar(&root, &latest_url).unwrap(), jar);
+        fs::write(&jar, b"corrupted").unwrap();
+        assert!(ensure_jar(&root, &latest_url).is_err());
+
+        // 离线账号没有本地服务器时不需要参数
+        let offline = Account::Offline(OfflineAccount::new("Steve".to_string()).unwrap());
+        assert!(prepare(&offline, &root, &latest_url, None).unwrap().is_empty());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn reject_mismatched_jar() {
+        let base = std::env::temp_dir().join(format!("mctui-authlib-mismatch-{}", std::process::id()));
+        let root = GameRoot::new(&base);
+        let server = start_server(Arc::new(AtomicBool::new(true)), sha256(b"other"));
+        let e = ensure_jar(&root, &format!("{}/latest.json", server.url)).unwrap_err();
+        assert_eq!(e.to_string(), "sha256 mismatch of authlib-injector 1.2.5");
+        assert!(!jar_path(&root).exists());
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,565 @@
+//! 游戏崩溃后查找崩溃报告并分析原因
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrashReportKind {
+    /// crash-reports/crash-*.txt
+    Minecraft,
+    /// JVM 自身崩溃生成的 hs_err_pid*.log
+    JvmFatal,
+}
+
+/// 解析后的崩溃报告
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub path: PathBuf,
+    pub kind: CrashReportKind,
+    pub description: String,
+    pub stack_trace: Vec<String>,
+    pub suspected_mods: Vec<String>,
+    /// System Details 中的键值对
+    pub system_details: Vec<(String, String)>,
+    pub content: String,
+}
+
+/// 已知的崩溃原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrashCause {
+    /// 以 Java 大版本表示，未知时为 None
+    WrongJavaVersion {
+        required: Option<u32>,
+        current: Option<u32>,
+    },
+    MissingDependency {
+        mod_id: String,
+        dependency: String,
+    },
+    DuplicateMod(String),
+    OutOfMemory,
+    GraphicsDriver(String),
+    MixinFailure {
+        mod_id: Option<String>,
+    },
+}
+
+/// 崩溃分析结果
+#[derive(Debug, Clone)]
+pub struct CrashAnalysis {
+    pub exit_code: Option<i32>,
+    pub report: Option<CrashReport>,
+    pub causes: Vec<CrashCause>,
+}
+
+/// 查找 since 之后生成的最新的崩溃报告
+pub fn find_crash_report(game_dir: &Path, since: SystemTime) -> Option<(PathBuf, CrashReportKind)> {
+    let mut candidates = Vec::new();
+    let crash_reports = game_dir.join("crash-reports");
+    for (dir, kind) in [
+        (crash_reports.as_path(), CrashReportKind::Minecraft),
+        (game_dir, CrashReportKind::JvmFatal),
+    ] {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let matched = match kind {
+                CrashReportKind::Minecraft => name.ends_with(".txt"),
+                CrashReportKind::JvmFatal => name.starts_with("hs_err_pid") && name.ends_with(".log"),
+            };
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            if let Some(modified) = modified
+                && matched
+                && modified >= since
+            {
+                candidates.push((modified, entry.path(), kind));
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .max_by_key(|(modified, _, _)| *modified)
+        .map(|(_, path, kind)| (path, kind))
+}
+
+pub fn parse_crash_report(path: &Path, kind: CrashReportKind) -> Result<CrashReport> {
+    let bytes = fs::read(path).context(format!("failed to read crash report: {:?}", path))?;
+    let content = String::from_utf8_lossy(&bytes).to_string();
+    Ok(match kind {
+        CrashReportKind::Minecraft => parse_minecraft_report(path, content),
+        CrashReportKind::JvmFatal => parse_jvm_report(path, content),
+    })
+}
+
+fn parse_minecraft_report(path: &Path, content: String) -> CrashReport {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut description = String::new();
+    let mut stack_trace = Vec::new();
+    let mut suspected_mods = Vec::new();
+    let mut system_details = Vec::new();
+
+    if let Some(start) = lines.iter().position(|line| line.starts_with("Description:")) {
+        description = lines[start]["Description:".len()..].trim().to_string();
+        // 描述后空一行即为异常堆栈
+        stack_trace = lines[start + 1..]
+            .iter()
+            .skip_while(|line| line.trim().is_empty())
+            .take_while(|line| !line.trim().is_empty())
+            .map(|line| line.to_string())
+            .collect();
+    }
+
+    // Suspected Mod(s): 后面跟随若干缩进行
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed
+            .strip_prefix("Suspected Mods:")
+            .or_else(|| trimmed.strip_prefix("Suspected Mod:"))
+        {
+            let rest = rest.trim();
+            if !rest.is_empty() && rest != "NONE" && rest != "Unknown" {
+                suspected_mods.push(rest.to_string());
+            }
+            for next in &lines[i + 1..] {
+                if !next.starts_with('\t') && !next.starts_with("  ") {
+                    break;
+                }
+                // 更深的缩进是该模组的附加信息
+                if next.starts_with("\t\t") || next.starts_with("    ") {
+                    continue;
+                }
+                let next = next.trim();
+                if !next.is_empty() && !next.starts_with("Stacktrace") && !next.starts_with("at ") {
+                    suspected_mods.push(next.to_string());
+                }
+            }
+        }
+    }
+
+    if let Some(start) = lines.iter().position(|line| line.contains("-- System Details --")) {
+        for line in &lines[start + 1..] {
+            if !line.starts_with('\t') {
+                continue;
+            }
+            if let Some((key, value)) = line.trim().split_once(": ") {
+                system_details.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    CrashReport {
+        path: path.to_path_buf(),
+        kind: CrashReportKind::Minecraft,
+        description,
+        stack_trace,
+        suspected_mods,
+        system_details,
+        content,
+    }
+}
+
+fn parse_jvm_report(path: &Path, content: String) -> CrashReport {
+    let lines: Vec<&str> = content.lines().collect();
+    let header: Vec<String> = lines
+        .iter()
+        .take_while(|line| line.starts_with('#') || line.trim().is_empty())
+        .map(|line| line.trim_start_matches('#').trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let description = header.first().cloned().unwrap_or_default();
+
+    let mut stack_trace = Vec::new();
+    if let Some(i) = header.iter().position(|line| line.starts_with("Problematic frame:")) {
+        stack_trace.extend(header.get(i + 1).cloned());
+    }
+    if let Some(start) = lines.iter().position(|line| line.starts_with("Native frames:")) {
+        stack_trace.extend(
+            lines[start + 1..]
+                .iter()
+                .take_while(|line| !line.trim().is_empty())
+                .map(|line| line.to_string()),
+        );
+    }
+
+    let system_details = header
+        .iter()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    CrashReport {
+        path: path.to_path_buf(),
+        kind: CrashReportKind::JvmFatal,
+        description,
+        stack_trace,
+        suspected_mods: Vec::new(),
+        system_details,
+        content,
+    }
+}
+
+/// 分析一次非正常退出，output 为游戏最后的输出
+pub fn analyze_crash(
+    game_dir: &Path,
+    since: SystemTime,
+    exit_code: Option<i32>,
+    output: &[String],
+) -> CrashAnalysis {
+    let report = find_crash_report(game_dir, since)
+        .and_then(|(path, kind)| parse_crash_report(&path, kind).ok());
+
+    let mut text = output.join("\n");
+    if let Some(report) = &report {
+        text.push('\n');
+        text.push_str(&report.content);
+    }
+    let mut causes = diagnose(&text);
+    if let Some(report) = report.as_ref().filter(|r| r.kind == CrashReportKind::JvmFatal) {
+        let frame = report.stack_trace.first().cloned().unwrap_or_default();
+        if is_graphics_frame(&frame) && !causes.iter().any(|c| matches!(c, CrashCause::GraphicsDriver(_))) {
+            causes.push(CrashCause::GraphicsDriver(frame));
+        }
+    }
+
+    CrashAnalysis {
+        exit_code,
+        report,
+        causes,
+    }
+}
+
+/// 在日志和崩溃报告中匹配已知的崩溃原因
+pub fn diagnose(text: &str) -> Vec<CrashCause> {
+    let mut causes = Vec::new();
+
+    if let Some(cause) = wrong_java_version(text) {
+        causes.push(cause);
+    }
+
+    for line in text.lines() {
+        if let Some(cause) = missing_dependency(line)
+            && !causes.contains(&cause)
+        {
+            causes.push(cause);
+        }
+    }
+
+    for line in text.lines() {
+        if let Some(mod_id) = duplicate_mod(line) {
+            let cause = CrashCause::DuplicateMod(mod_id);
+            if !causes.contains(&cause) {
+                causes.push(cause);
+            }
+        }
+    }
+
+    if text.contains("java.lang.OutOfMemoryError")
+        || text.contains("There is insufficient memory for the Java Runtime Environment")
+        || text.contains("Out of Memory Error")
+    {
+        causes.push(CrashCause::OutOfMemory);
+    }
+
+    if let Some(line) = text.lines().find(|line| is_graphics_error(line)) {
+        causes.push(CrashCause::GraphicsDriver(line.trim().to_string()));
+    }
+
+    if text.contains("MixinApplyError")
+        || text.contains("InvalidMixinException")
+        || text.contains("MixinTransformerError")
+        || text.contains("Mixin apply failed")
+        || text.contains("mixin.injection.throwables")
+    {
+        causes.push(CrashCause::MixinFailure {
+            mod_id: mixin_mod(text),
+        });
+    }
+
+    causes
+}
+
+/// class file version 减 44 即为 Java 大版本
+fn class_version_to_java(version: &str) -> Option<u32> {
+    let major: f32 = version.trim_end_matches(['.', ',', ')']).parse().ok()?;
+    (major as u32).checked_sub(44)
+}
+
+fn word_after<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    let start = text.find(marker)? + marker.len();
+    text[start..].split_whitespace().next()
+}
+
+fn wrong_java_version(text: &str) -> Option<CrashCause> {
+    if text.contains("UnsupportedClassVersionError") {
+        let required = word_after(text, "class file version ").and_then(class_version_to_java);
+        let current = word_after(text, "recognizes class file versions up to ")
+            .and_then(class_version_to_java);
+        return Some(CrashCause::WrongJavaVersion { required, current });
+    }
+    // 旧版 Forge / LaunchWrapper 无法在 Java 9 以上运行
+    if text.contains("cannot be cast to class java.net.URLClassLoader")
+        || text.contains("jdk.internal.loader.ClassLoaders$AppClassLoader cannot be cast")
+    {
+        return Some(CrashCause::WrongJavaVersion {
+            required: Some(8),
+            current: None,
+        });
+    }
+    // Fabric: "Minecraft 1.20.5 requires Java 21"
+    if let Some(version) = word_after(text, "requires Java ")
+        && let Ok(required) = version.trim_end_matches(['.', ',']).parse::<u32>()
+    {
+        return Some(CrashCause::WrongJavaVersion {
+            required: Some(required),
+            current: None,
+        });
+    }
+    None
+}
+
+/// 取出 'name' 或 (id) 中的内容
+fn quoted(text: &str, open: char, close: char) -> Option<&str> {
+    let start = text.find(open)? + open.len_utf8();
+    let end = text[start..].find(close)? + start;
+    Some(&text[start..end])
+}
+
+fn missing_dependency(line: &str) -> Option<CrashCause> {
+    let line = line.trim().trim_start_matches("- ");
+    // Fabric 旧版: requires mod fabric, which is missing!
+    if line.contains("which is missing") {
+        let dependency = word_after(line, "requires mod ")
+            .or_else(|| word_after(line, "of mod "))?
+            .trim_end_matches(',')
+            .to_string();
+        let mod_id = quoted(line, '(', ')').unwrap_or_default().to_string();
+        return Some(CrashCause::MissingDependency { mod_id, dependency });
+    }
+    // Fabric: Mod 'Sodium' (sodium) 0.5.3 requires version 0.14.x of 'Fabric Loader' (fabricloader)
+    if line.starts_with("Mod '") && line.contains(" requires ") {
+        let mod_id = quoted(line, '(', ')').unwrap_or_default().to_string();
+        let rest = &line[line.find(" requires ")? + " requires ".len()..];
+        let dependency = quoted(rest, '(', ')')
+            .or_else(|| quoted(rest, '\'', '\''))?
+            .to_string();
+        return Some(CrashCause::MissingDependency { mod_id, dependency });
+    }
+    // Forge: Mod ID: 'jei', Requested by: 'examplemod'
+    if line.starts_with("Mod ID: ") && line.contains("Requested by") {
+        let dependency = quoted(line, '\'', '\'')?.to_string();
+        let requested = &line[line.find("Requested by")?..];
+        let mod_id = quoted(requested, '\'', '\'').unwrap_or_default().to_string();
+        return Some(CrashCause::MissingDependency { mod_id, dependency });
+    }
+    None
+}
+
+fn duplicate_mod(line: &str) -> Option<String> {
+    let line = line.trim();
+    // Fabric: Mod ID 'sodium' is duplicated / Duplicate mod ID 'sodium'
+    if (line.contains("Duplicate mod") || line.contains("duplicate mod")) && line.contains('\'') {
+        return quoted(line, '\'', '\'').map(|s| s.to_string());
+    }
+    if line.contains("is duplicated") || line.contains("Found duplicate mods") {
+        return quoted(line, '\'', '\'')
+            .or_else(|| line.split(':').nth(1).map(|s| s.trim()))
+            .map(|s| s.to_string());
+    }
+    // Forge: DuplicateModsFoundException / Found a duplicate mod x
+    if line.contains("DuplicateModsFoundException") || line.contains("Found a duplicate mod") {
+        return Some(word_after(line, "duplicate mod ").unwrap_or("").to_string());
+    }
+    None
+}
+
+fn is_graphics_error(line: &str) -> bool {
+    line.contains("GLFW error")
+        || line.contains("WGL: The driver does not appear to support OpenGL")
+        || line.contains("Pixel format not accelerated")
+        || line.contains("No OpenGL context found")
+        || line.contains("Could not create context")
+        || line.contains("Failed to create window")
+}
+
+fn is_graphics_frame(frame: &str) -> bool {
+    let frame = frame.to_lowercase();
+    ["lwjgl", "glfw", "nvoglv", "atio6axx", "ig9icd", "ig7icd", "libgl", "opengl32", "amdvlk"]
+        .iter()
+        .any(|name| frame.contains(name))
+}
+
+/// Mixin [xxx.mixins.json:yyy] from mod zzz
+fn mixin_mod(text: &str) -> Option<String> {
+    word_after(text, "from mod ")
+        .map(|id| id.trim_end_matches(['.', ',', ']', ')']).to_string())
+        .or_else(|| {
+            let config = word_after(text, "Mixin [")?;
+            let name = config.split(['.', ':']).next()?;
+            Some(name.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINECRAFT_REPORT: &str = "---- Minecraft Crash Report ----
+// Who set us up the TNT?
+
+Time: 2024-01-01 12:00:00
+Description: Rendering overlay
+
+java.lang.NullPointerException: boom
+\tat foo.Bar.baz(Bar.java:1)
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+
+-- Head --
+Thread: Render thread
+Suspected Mods: NONE
+Stacktrace:
+\tat foo.Bar.baz(Bar.java:1)
+
+-- System Details --
+Details:
+\tMinecraft Version: 1.20.1
+\tJava Version: 17.0.8, Eclipse Adoptium
+";
+
+    const JVM_REPORT: &str = "#
+# A fatal error has been detected by the Java Runtime Environment:
+#
+#  EXCEPTION_ACCESS_VIOLATION (0xc0000005) at pc=0x00007ffb, pid=1234, tid=5678
+#
+# JRE version: OpenJDK Runtime Environment (17.0.8+7) (build 17.0.8+7)
+# Problematic frame:
+# C  [atio6axx.dll+0x1234]
+#
+
+---------------  T H R E A D  ---------------
+
+Native frames: (J=compiled Java code, j=interpreted, Vv=VM code, C=native code)
+C  [atio6axx.dll+0x1234]
+C  [lwjgl.dll+0x10]
+
+";
+
+    #[test]
+    fn parse_minecraft_crash_report() {
+        let report = parse_minecraft_report(Path::new("crash.txt"), MINECRAFT_REPORT.to_string());
+        assert_eq!(report.description, "Rendering overlay");
+        assert_eq!(
+            report.stack_trace,
+            ["java.lang.NullPointerException: boom", "\tat foo.Bar.baz(Bar.java:1)"]
+        );
+        assert!(report.suspected_mods.is_empty());
+        assert_eq!(
+            report.system_details,
+            [
+                ("Minecraft Version".to_string(), "1.20.1".to_string()),
+                ("Java Version".to_string(), "17.0.8, Eclipse Adoptium".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_suspected_mods() {
+        let content = "Suspected Mods: \n\tSodium (sodium)\n\t\tIssue tracker URL: https://example.com\n\tIris (iris)\nStacktrace:\n";
+        let report = parse_minecraft_report(Path::new("crash.txt"), content.to_string());
+        assert_eq!(report.suspected_mods, ["Sodium (sodium)", "Iris (iris)"]);
+    }
+
+    #[test]
+    fn parse_jvm_crash_report() {
+        let report = parse_jvm_report(Path::new("hs_err_pid1234.log"), JVM_REPORT.to_string());
+        assert_eq!(report.description, "A fatal error has been detected by the Java Runtime Environment:");
+        assert_eq!(
+            report.stack_trace,
+            ["C  [atio6axx.dll+0x1234]", "C  [atio6axx.dll+0x1234]", "C  [lwjgl.dll+0x10]"]
+        );
+        assert_eq!(
+            report.system_details,
+            [(
+                "JRE version".to_string(),
+                "OpenJDK Runtime Environment (17.0.8+7) (build 17.0.8+7)".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn diagnose_wrong_java_version() {
+        let text = "java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 52.0";
+        assert_eq!(
+            diagnose(text),
+            [CrashCause::WrongJavaVersion { required: Some(21), current: Some(8) }]
+        );
+        assert_eq!(
+            diagnose("Minecraft 1.20.5 requires Java 21 but Java 17 was detected"),
+            [CrashCause::WrongJavaVersion { required: Some(21), current: None }]
+        );
+    }
+
+    #[test]
+    fn diagnose_mod_problems() {
+        let text = "\
+ - Mod 'Sodium' (sodium) 0.5.3 requires version 0.14.x of 'Fabric Loader' (fabricloader), but only the wrong version is present
+Could not find required mod: examplemod requires mod fabric, which is missing!
+Mod ID: 'jei', Requested by: 'examplemod', Expected range: '[1.0,)'
+Duplicate mod ID 'sodium' found in mods";
+        assert_eq!(
+            diagnose(text),
+            [
+                CrashCause::MissingDependency {
+                    mod_id: "sodium".to_string(),
+                    dependency: "fabricloader".to_string(),
+                },
+                CrashCause::MissingDependency {
+                    mod_id: String::new(),
+                    dependency: "fabric".to_string(),
+                },
+                CrashCause::MissingDependency {
+                    mod_id: "examplemod".to_string(),
+                    dependency: "jei".to_string(),
+                },
+                CrashCause::DuplicateMod("sodium".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnose_memory_graphics_and_mixin() {
+        assert_eq!(diagnose("java.lang.OutOfMemoryError: Java heap space"), [CrashCause::OutOfMemory]);
+        assert_eq!(
+            diagnose("  GLFW error 65542: WGL: The driver does not appear to support OpenGL"),
+            [CrashCause::GraphicsDriver(
+                "GLFW error 65542: WGL: The driver does not appear to support OpenGL".to_string()
+            )]
+        );
+        let text = "MixinTransformerError: An unexpected critical error was encountered\nMixin [sodium.mixins.json:core.MixinX] from mod sodium failed";
+        assert_eq!(diagnose(text), [CrashCause::MixinFailure { mod_id: Some("sodium".to_string()) }]);
+        assert!(diagnose("[main/INFO]: Stopping!").is_empty());
+    }
+
+    #[test]
+    fn analyze_jvm_crash_in_graphics_driver() {
+        let dir = std::env::temp_dir().join(format!("mctui-crash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hs_err_pid1234.log"), JVM_REPORT).unwrap();
+        let analysis = analyze_crash(&dir, SystemTime::UNIX_EPOCH, Some(-1), &[]);
+        let report = analysis.report.unwrap();
+        assert_eq!(report.kind, CrashReportKind::JvmFatal);
+        assert_eq!(
+            analysis.causes,
+            [CrashCause::GraphicsDriver("C  [atio6axx.dll+0x1234]".to_string())]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+//! 为版本选择合适的 Java，并检查手动指定的 Java 是否兼容
+
+use std::{fmt, path::Path};
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+
+use crate::{
+    install::minecraft::version_json::VersionJson,
+    java::{JavaInstallation, JavaRegistry, inspect, runtime::runtime_component},
+};
+
+/// 没有声明 javaVersion 的旧版本需要 Java 8
+const LEGACY_JAVA: u32 = 8;
+
+/// 版本对 Java 的要求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JavaRequirement {
+    /// 最低主版本，同时也是推荐的版本
+    pub min: u32,
+    /// 能运行的最高主版本，None 表示不限制
+    pub max: Option<u32>,
+}
+
+impl JavaRequirement {
+    pub fn of(profile: &VersionJson) -> Self {
+        let min = profile
+            .java_version
+            .as_ref()
+            .map(|java| java.major_version)
+            .unwrap_or(LEGACY_JAVA);
+        // LaunchWrapper（旧版 Forge、OptiFine 等）在 Java 9 以后无法加载类
+        let max = uses_launchwrapper(profile).then_some(LEGACY_JAVA);
+        Self { min, max }
+    }
+
+    pub fn check(&self, major: u32) -> JavaCompatibility {
+        if major < self.min {
+            JavaCompatibility::Incompatible(JavaIssue::TooOld { required: self.min, current: major })
+        } else if let Some(max) = self.max
+            && major > max
+        {
+            JavaCompatibility::Incompatible(JavaIssue::TooNew { max, current: major })
+        } else if major > self.min {
+            JavaCompatibility::Warning(JavaIssue::NotRecommended { recommended: self.min, current: major })
+        } else {
+            JavaCompatibility::Compatible
+        }
+    }
+}
+
+fn uses_launchwrapper(profile: &VersionJson) -> bool {
+    profile.main_class == "net.minecraft.launchwrapper.Launch"
+        || profile
+            .libraries
+            .iter()
+            .any(|library| library.name.starts_with("net.minecraft:launchwrapper:"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JavaIssue {
+    TooOld { required: u32, current: u32 },
+    TooNew { max: u32, current: u32 },
+    /// 能启动，但不是版本推荐的 Java
+    NotRecommended { recommended: u32, current: u32 },
+}
+
+impl fmt::Display for JavaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JavaIssue::TooOld { required, current } => {
+                write!(f, "requires Java {} or newer, but Java {} is selected", required, current)
+            }
+            JavaIssue::TooNew { max, current } => {
+                write!(f, "cannot run on Java newer than {}, but Java {} is selected", max, current)
+            }
+            JavaIssue::NotRecommended { recommended, current } => {
+                write!(f, "Java {} is recommended, but Java {} is selected", recommended, current)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JavaCompatibility {
+    Compatible,
+    /// 可以启动，但可能出现问题
+    Warning(JavaIssue),
+    /// 无法启动
+    Incompatible(JavaIssue),
+}
+
+impl JavaCompatibility {
+    pub fn can_launch(&self) -> bool {
+        !matches!(self, JavaCompatibility::Incompatible(_))
+    }
+
+    pub fn issue(&self) -> Option<JavaIssue> {
+        match self {
+            JavaCompatibility::Compatible => None,
+            JavaCompatibility::Warning(issue) | JavaCompatibility::Incompatible(issue) => Some(*issue),
+        }
+    }
+}
+
+/// 选择的结果
+#[derive(Debug, Clone)]
+pub struct JavaSelection {
+    pub java: JavaInstallation,
+    pub compatibility: JavaCompatibility,
+    /// 是否来自实例的手动设置
+    pub overridden: bool,
+}
+
+/// 为版本选择 Java
+///
+/// java_override 为实例手动指定的 java 可执行文件，不兼容时返回错误；
+/// 否则从 registry 中选出最合适的 Java，找不到时提示需要安装的运行时组件
+pub fn select_java(
+    registry: &JavaRegistry,
+    profile: &VersionJson,
+    java_override: Option<&Path>,
+) -> Result<JavaSelection> {
+    let requirement = JavaRequirement::of(profile);
+
+    if let Some(path) = java_override {
+        let java = match registry.installations.iter().find(|java| java.path == path) {
+            Some(java) => java.clone(),
+            None => inspect(path)?,
+        };
+        let compatibility = requirement.check(java.major_version);
+        match compatibility {
+            JavaCompatibility::Incompatible(issue) => {
+                bail!("{} {}: {:?}", profile.id, issue, java.path)
+            }
+            JavaCompatibility::Warning(issue) => {
+                warn!(target: "java", "{} {}: {:?}", profile.id, issue, java.path)
+            }
+            JavaCompatibility::Compatible => {}
+        }
+        return Ok(JavaSelection { java, compatibility, overridden: true });
+    }
+
+    let best = registry
+        .installations
+        .iter()
+        .map(|java| (java, requirement.check(java.major_version)))
+        .filter(|(_, compatibility)| compatibility.can_launch())
+        // 推荐版本优先，其次是启动器管理的运行时，再其次是较低的主版本
+        .min_by_key(|(java, compatibility)| {
+            (
+                *compatibility != JavaCompatibility::Compatible,
+                !java.managed,
+                !is_64bit(&java.arch),
+                java.major_version,
+            )
+        });
+    let Some((java, compatibility)) = best else {
+        bail!(
+            "no compatible java found for {} (requires Java {}, runtime component {})",
+            profile.id,
+            requirement.min,
+            runtime_component(profile)
+        );
+    };
+    info!(target: "java", "selected java {} for {}: {:?}", java.version, profile.id, java.path);
+    Ok(JavaSelection { java: java.clone(), compatibility, overridden: false })
+}
+
+fn is_64bit(arch: &str) -> bool {
+    arch.is_empty() || arch.contains("64")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn profile(json: &str) -> VersionJson {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn java(major: u32, path: &str, managed: bool) -> JavaInstallation {
+        JavaInstallation {
+            path: PathBuf::from(path),
+            home: PathBuf::new(),
+            major_version: major,
+            version: major.to_string(),
+            vendor: String::new(),
+            arch: "amd64".to_string(),
+            managed,
+        }
+    }
+
+    fn registry(installations: Vec<JavaInstallation>) -> JavaRegistry {
+        let mut registry = JavaRegistry::default();
+        registry.installations = installations;
+        registry
+    }
+
+    #[test]
+    fn requirement_of_profile() {
+        let modern = profile(r#"{"id": "1.20.1", "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17}}"#);
+        assert_eq!(JavaRequirement::of(&modern), JavaRequirement { min: 17, max: None });
+        let legacy = profile(r#"{"id": "1.7.10"}"#);
+        assert_eq!(JavaRequirement::of(&legacy), JavaRequirement { min: 8, max: None });
+
+        // LaunchWrapper 只能在 Java 8 上运行
+        let forge = profile(
+            r#"{"id": "1.12.2-forge", "mainClass": "net.minecraft.launchwrapper.Launch"}"#,
+        );
+        let requirement = JavaRequirement::of(&forge);
+        assert_eq!(requirement, JavaRequirement { min: 8, max: Some(8) });
+        assert_eq!(requirement.check(8), JavaCompatibility::Compatible);
+        assert_eq!(
+            requirement.check(17),
+            JavaCompatibility::Incompatible(JavaIssue::TooNew { max: 8, current: 17 })
+        );
+        let optifine = profile(
+            r#"{"id": "1.8.9-optifine", "libraries": [{"name": "net.minecraft:launchwrapper:1.12"}]}"#,
+        );
+        assert_eq!(JavaRequirement::of(&optifine).max, Some(8));
+    }
+
+    #[test]
+    fn check_versions() {
+        let requirement = JavaRequirement { min: 17, max: None };
+        assert_eq!(
+            requirement.check(8),
+            JavaCompatibility::Incompatible(JavaIssue::TooOld { required: 17, current: 8 })
+        );
+        assert_eq!(requirement.check(17), JavaCompatibility::Compatible);
+        let newer = requirement.check(21);
+        assert!(newer.can_launch());
+        assert_eq!(newer.issue(), Some(JavaIssue::NotRecommended { recommended: 17, current: 21 }));
+    }
+
+    #[test]
+    fn select_from_registry() {
+        let modern = profile(r#"{"id": "1.20.1", "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17}}"#);
+        let registry = registry(vec![
+            java(8, "/java8", false),
+            java(21, "/java21", false),
+            java(17, "/java17", false),
+            java(17, "/runtime17", true),
+        ]);
+        let selection = select_java(&registry, &modern, None).unwrap();
+        assert_eq!(selection.java.path, PathBuf::from("/runtime17"));
+        assert!(!selection.overridden);
+
+        // 没有推荐版本时选择能启动的最低版本
+        let registry = registry_without(&registry, 17);
+        let selection = select_java(&registry, &modern, None).unwrap();
+        assert_eq!(selection.java.major_version, 21);
+        assert!(matches!(selection.compatibility, JavaCompatibility::Warning(_)));
+
+        let forge = profile(r#"{"id": "1.12.2-forge", "mainClass": "net.minecraft.launchwrapper.Launch"}"#);
+        let only_new = registry_without(&registry, 8);
+        let e = select_java(&only_new, &forge, None).unwrap_err();
+        assert!(e.to_string().contains("jre-legacy"));
+    }
+
+    fn registry_without(registry: &JavaRegistry, major: u32) -> JavaRegistry {
+        self::registry(
+            registry
+                .installations
+                .iter()
+                .filter(|java| java.major_version != major)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn override_with_mismatched_major() {
+        let registry = registry(vec![java(8, "/java8", false), java(21, "/java21", false)]);
+        let modern = profile(r#"{"id": "1.20.1", "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17}}"#);
+        let e = select_java(&registry, &modern, Some(Path::new("/java8"))).unwrap_err();
+        assert!(e.to_string().contains("requires Java 17"));
+
+        let selection = select_java(&registry, &modern, Some(Path::new("/java21"))).unwrap();
+        assert!(selection.overridden);
+        assert_eq!(selection.java.major_version, 21);
+        assert!(selection.compatibility.can_launch());
+
+        let forge = profile(r#"{"id": "1.12.2-forge", "mainClass": "net.minecraft.launchwrapper.Launch"}"#);
+        let e = select_java(&registry, &forge, Some(Path::new("/java21"))).unwrap_err();
+        assert!(e.to_string().contains("newer than 8"));
+        // 不在列表中也无法读取的 java
+        assert!(select_java(&registry, &forge, Some(Path::new("/missing/java"))).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! 解析 log4j XMLLayout 输出的游戏日志
+//!
+//! 使用版本自带的 `logging.client` 配置启动时，游戏会把日志以
+//! `<log4j:Event>` 的形式输出到 stdout
+
+use std::{fmt, path::PathBuf};
+
+use anyhow::{Context, Result};
+use log::info;
+use quick_xml::{Reader, events::Event};
+
+use crate::{
+    install::{
+        minecraft::{game_root::GameRoot, version_json::VersionJson},
+        verify::{GameFile, GameFileKind, check_file, download_file},
+    },
+    launch::process::OutputLine,
+};
+
+/// 确保版本的 log4j 配置文件存在且完整，不存在时下载，返回配置文件路径
+pub fn ensure_log_config(root: &GameRoot, profile: &VersionJson) -> Result<Option<PathBuf>> {
+    let Some(config) = profile.logging.as_ref().and_then(|l| l.client.as_ref()) else {
+        return Ok(None);
+    };
+    let file = GameFile {
+        kind: GameFileKind::LogConfig,
+        path: root.log_config(&config.file.id),
+        url: config.file.url.clone(),
+        sha1: config.file.sha1.clone(),
+        size: config.file.size,
+    };
+    if check_file(&file).is_some() {
+        download_file(&file).context("failed to download log config")?;
+        info!(target: "launch", "log config downloaded: {:?}", file.path);
+    }
+    Ok(Some(file.path))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    pub fn parse(level: &str) -> Self {
+        match level.to_ascii_uppercase().as_str() {
+            "TRACE" => LogLevel::Trace,
+            "DEBUG" => LogLevel::Debug,
+            "WARN" | "WARNING" => LogLevel::Warn,
+            "ERROR" => LogLevel::Error,
+            "FATAL" => LogLevel::Fatal,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 一条游戏日志
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    /// unix 毫秒时间戳，非 log4j 输出时为 None
+    pub timestamp: Option<i64>,
+    pub level: LogLevel,
+    pub thread: String,
+    pub logger: String,
+    pub message: String,
+    pub throwable: Option<String>,
+}
+
+impl LogRecord {
+    /// 没有经过 log4j 的普通输出
+    pub fn plain(line: &OutputLine) -> Self {
+        let level = match line {
+            OutputLine::Stdout(_) => LogLevel::Info,
+            OutputLine::Stderr(_) => LogLevel::Error,
+        };
+        Self {
+            timestamp: None,
+            level,
+            thread: String::new(),
+            logger: String::new(),
+            message: line.text().to_string(),
+            throwable: None,
+        }
+    }
+
+    /// 是否包含关键字（不区分大小写）
+    pub fn contains(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        self.message.to_lowercase().contains(&keyword)
+            || self.logger.to_lowercase().contains(&keyword)
+            || self.thread.to_lowercase().contains(&keyword)
+            || self
+                .throwable
+                .as_ref()
+                .is_some_and(|throwable| throwable.to_lowercase().contains(&keyword))
+    }
+}
+
+/// 未完成的事件最多缓存的字节数，超过时按普通输出处理
+const MAX_EVENT_SIZE: usize = 1024 * 1024;
+
+/// 逐行输入 stdout，拼接出完整的 `<log4j:Event>` 后解析
+#[derive(Debug, Default)]
+pub struct Log4jParser {
+    buffer: String,
+}
+
+impl Log4jParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一行输出，得到零条或多条日志
+    pub fn feed(&mut self, line: &OutputLine) -> Vec<LogRecord> {
+        let text = line.text();
+        if matches!(line, OutputLine::Stderr(_)) {
+            return vec![LogRecord::plain(line)];
+        }
+        let mut records = Vec::new();
+        if text.trim_start().starts_with("<log4j:Event") {
+            // 上一个事件没有结束
+            records = self.finish();
+        } else if self.buffer.is_empty() {
+            return vec![LogRecord::plain(line)];
+        } else {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(text);
+
+        if text.trim_end().ends_with("</log4j:Event>") {
+            let event = std::mem::take(&mut self.buffer);
+            match parse_event(&event) {
+                Some(record) => records.push(record),
+                None => records.push(LogRecord::plain(&OutputLine::Stdout(event))),
+            }
+        } else if self.buffer.len() > MAX_EVENT_SIZE {
+            records.extend(self.finish());
+        }
+        records
+    }
+
+    /// 取出未完成的内容，每行作为一条普通输出，游戏退出时调用
+    pub fn finish(&mut self) -> Vec<LogRecord> {
+        std::mem::take(&mut self.buffer)
+            .lines()
+            .map(|line| LogRecord::plain(&OutputLine::Stdout(line.to_string())))
+            .collect()
+    }
+}
+
+/// 把游戏的全部输出转换为日志
+pub fn parse_output(lines: &[OutputLine]) -> Vec<LogRecord> {
+    let mut parser = Log4jParser::new();
+    let mut records: Vec<LogRecord> = lines.iter().flat_map(|line| parser.feed(line)).collect();
+    records.extend(parser.finish());
+    records
+}
+
+#[derive(PartialEq)]
+enum Field {
+    None,
+    Message,
+    Throwable,
+}
+
+/// 解析一个完整的 `<log4j:Event>` 元素
+pub fn parse_event(xml: &str) -> Option<LogRecord> {
+    let mut reader = Reader::from_str(xml);
+    let mut record = LogRecord {
+        timestamp: None,
+        level: LogLevel::Info,
+        thread: String::new(),
+        logger: String::new(),
+        message: String::new(),
+        throwable: None,
+    };
+    let mut found = false;
+    let mut field = Field::None;
+
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(start) => match start.local_name().as_ref() {
+                b"Event" => {
+                    found = true;
+                    for attribute in start.attributes().flatten() {
+                        let value = attribute.unescape_value().ok()?.to_string();
+                        match attribute.key.local_name().as_ref() {
+                            b"logger" => record.logger = value,
+                            b"timestamp" => record.timestamp = value.parse().ok(),
+                            b"level" => record.level = LogLevel::parse(&value),
+                            b"thread" => record.thread = value,
+                            _ => {}
+                        }
+                    }
+                }
+                b"Message" => field = Field::Message,
+                b"Throwable" => field = Field::Throwable,
+                _ => {}
+            },
+            Event::End(_) => field = Field::None,
+            Event::CData(data) => {
+                let text = String::from_utf8_lossy(&data.into_inner()).to_string();
+                push_field(&mut record, &field, &text);
+            }
+            Event::Text(text) => {
+                let text = text.unescape().ok()?.to_string();
+                push_field(&mut record, &field, &text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    found.then_some(record)
+}
+
+fn push_field(record: &mut LogRecord, field: &Field, text: &str) {
+    match field {
+        Field::Message => record.message.push_str(text),
+        Field::Throwable => record
+            .throwable
+            .get_or_insert_with(String::new)
+            .push_str(text),
+        Field::None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdout(line: &str) -> OutputLine {
+        OutputLine::Stdout(line.to_string())
+    }
+
+    const EVENT: &str = r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000000" level="WARN" thread="Render thread">
+  <log4j:Message><![CDATA[Setting user: Steve]]></log4j:Message>
+  <log4j:Throwable><![CDATA[java.lang.Exception: test
+	at Main.main(Main.java:1)]]></log4j:Throwable>
+</log4j:Event>"#;
+
+    #[test]
+    fn parse_single_event() {
+        let record = parse_event(EVENT).unwrap();
+        assert_eq!(record.timestamp, Some(1700000000000));
+        assert_eq!(record.level, LogLevel::Warn);
+        assert_eq!(record.thread, "Render thread");
+        assert_eq!(record.logger, "net.minecraft.client.Minecraft");
+        assert_eq!(record.message, "Setting user: Steve");
+        assert!(record.throwable.unwrap().starts_with("java.lang.Exception: test"));
+        assert!(parse_event("not xml").is_none());
+    }
+
+    #[test]
+    fn parse_mixed_output() {
+        let mut lines = vec![stdout("plain before")];
+        lines.extend(EVENT.lines().map(stdout));
+        lines.push(OutputLine::Stderr("error output".to_string()));
+        let records = parse_output(&lines);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].message, "plain before");
+        assert_eq!(records[1].message, "Setting user: Steve");
+        assert_eq!(records[2].level, LogLevel::Error);
+    }
+
+    #[test]
+    fn unfinished_event_is_flushed_as_lines() {
+        let mut parser = Log4jParser::new();
+        assert!(parser.feed(&stdout(r#"<log4j:Event level="INFO">"#)).is_empty());
+        assert!(parser.feed(&stdout("partial")).is_empty());
+        let records = parser.finish();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].message, "partial");
+
+        // 新的事件开始时，不完整的上一个事件作为普通输出取出
+        let mut lines = vec![stdout(r#"<log4j:Event level="INFO">"#)];
+        lines.extend(EVENT.lines().map(stdout));
+        let records = parse_output(&lines);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].level, LogLevel::Warn);
+    }
+
+    #[test]
+    fn buffer_is_bounded() {
+        let mut parser = Log4jParser::new();
+        assert!(parser.feed(&stdout("<log4j:Event>")).is_empty());
+        let line = stdout(&"x".repeat(1024));
+        let mut flushed = Vec::new();
+        for _ in 0..2048 {
+            flushed.extend(parser.feed(&line));
+        }
+        assert!(!flushed.is_empty());
+        assert!(parser.buffer.len() <= MAX_EVENT_SIZE);
+    }
+}
@@ -0,0 +1,262 @@
+//! 自动分配游戏内存，以及常用的 JVM 参数预设
+
+use std::{fs, path::Path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// 可用内存足够时最少分配的内存，单位 MB
+const MIN_HEAP: u32 = 1024;
+/// 内存对齐的单位，可用内存不足这个大小时不自动分配
+const HEAP_ALIGN: u32 = 256;
+/// 超过这个大小后 GC 停顿反而更明显
+const MAX_AUTO_HEAP: u32 = 16384;
+/// 原版需要的内存
+const BASE_HEAP: u32 = 2048;
+/// 每个模组额外需要的内存
+const HEAP_PER_MOD: u32 = 48;
+
+/// 系统内存，单位 MB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub total: u32,
+    pub available: u32,
+}
+
+impl MemoryInfo {
+    /// 读取 /proc/meminfo，其他系统上返回 None
+    pub fn read() -> Option<Self> {
+        let content = fs::read_to_string("/proc/meminfo").ok()?;
+        Self::parse(&content)
+    }
+
+    /// 解析 /proc/meminfo 的内容，例如 `MemTotal:       16318480 kB`
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut total = None;
+        let mut available = None;
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let Ok(kb) = value.trim().trim_end_matches("kB").trim().parse::<u64>() else {
+                continue;
+            };
+            let mb = (kb / 1024) as u32;
+            match key {
+                "MemTotal" => total = Some(mb),
+                "MemAvailable" => available = Some(mb),
+                _ => {}
+            }
+        }
+        let total = total?;
+        Some(Self { total, available: available.unwrap_or(total) })
+    }
+}
+
+/// 游戏目录下的模组数量
+pub fn count_mods(game_dir: &Path) -> usize {
+    fs::read_dir(game_dir.join("mods"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "jar"))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// 根据可用内存和模组数量计算最大堆内存，reserve 为留给系统的内存，单位 MB
+///
+/// 扣除 reserve 后不足 HEAP_ALIGN 时返回 None
+pub fn auto_max_memory(memory: &MemoryInfo, mods: usize, reserve: u32) -> Option<u32> {
+    let wanted = BASE_HEAP
+        .saturating_add(HEAP_PER_MOD.saturating_mul(mods as u32))
+        .min(MAX_AUTO_HEAP);
+    // 不超过当前可用内存，也不超过总内存的四分之三
+    let limit = memory
+        .available
+        .saturating_sub(reserve)
+        .min((memory.total / 4 * 3).saturating_sub(reserve));
+    // 可用内存不足 MIN_HEAP 时不超过可用内存，对齐到 256MB
+    let heap = wanted.min(limit).max(MIN_HEAP.min(limit));
+    Some(heap / HEAP_ALIGN * HEAP_ALIGN).filter(|&heap| heap >= HEAP_ALIGN)
+}
+
+/// 自动计算 (最小, 最大) 堆内存
+///
+/// 无法读取系统内存或内存不足时返回 None，交给 JVM 决定
+pub fn auto_memory(game_dir: &Path, reserve: u32) -> Option<(u32, u32)> {
+    let memory = MemoryInfo::read()?;
+    let Some(max) = auto_max_memory(&memory, count_mods(game_dir), reserve) else {
+        warn!(
+            target: "launch",
+            "not enough memory to allocate automatically: {} MB available, {} MB reserved",
+            memory.available, reserve
+        );
+        return None;
+    };
+    Some(((max / 2).max(MIN_HEAP).min(max), max))
+}
+
+/// JVM 参数预设
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JvmPreset {
+    /// 不添加参数，使用 JVM 默认的 GC
+    #[default]
+    Default,
+    /// Aikar 的 G1 参数
+    Aikar,
+    /// 分代 ZGC，需要 Java 21
+    Zgc,
+    /// 内存较小的电脑
+    LowMemory,
+}
+
+impl JvmPreset {
+    pub const ALL: [JvmPreset; 4] = [
+        JvmPreset::Default,
+        JvmPreset::Aikar,
+        JvmPreset::Zgc,
+        JvmPreset::LowMemory,
+    ];
+
+    /// 支持的最低 Java 主版本
+    pub fn min_java(&self) -> u32 {
+        match self {
+            JvmPreset::Zgc => 21,
+            _ => 8,
+        }
+    }
+
+    pub fn supports(&self, java_major: u32) -> bool {
+        java_major >= self.min_java()
+    }
+
+    /// 预设对应的参数，Java 版本不支持时回退到默认预设
+    pub fn flags(&self, java_major: u32, max_memory: Option<u32>) -> Vec<String> {
+        if !self.supports(java_major) {
+            warn!(target: "launch", "{:?} preset requires Java {}, ignored", self, self.min_java());
+            return Vec::new();
+        }
+        let flags: Vec<&str> = match self {
+            JvmPreset::Default => Vec::new(),
+            JvmPreset::Aikar => {
+                // 大于 12G 时使用 Aikar 推荐的另一组参数
+                let large = max_memory.is_some_and(|max| max > 12288);
+                vec![
+                    "-XX:+UseG1GC",
+                    "-XX:+ParallelRefProcEnabled",
+                    "-XX:MaxGCPauseMillis=200",
+                    "-XX:+UnlockExperimentalVMOptions",
+                    "-XX:+DisableExplicitGC",
+                    "-XX:+AlwaysPreTouch",
+                    if large { "-XX:G1NewSizePercent=40" } else { "-XX:G1NewSizePercent=30" },
+                    if large { "-XX:G1MaxNewSizePercent=50" } else { "-XX:G1MaxNewSizePercent=40" },
+                    if large { "-XX:G1HeapRegionSize=16M" } else { "-XX:G1HeapRegionSize=8M" },
+                    if large { "-XX:G1ReservePercent=15" } else { "-XX:G1ReservePercent=20" },
+                    "-XX:G1HeapWastePercent=5",
+                    "-XX:G1MixedGCCountTarget=4",
+                    if large {
+                        "-XX:InitiatingHeapOccupancyPercent=20"
+                    } else {
+                        "-XX:InitiatingHeapOccupancyPercent=15"
+                    },
+                    "-XX:G1MixedGCLiveThresholdPercent=90",
+                    "-XX:G1RSetUpdatingPauseTimePercent=5",
+                    "-XX:SurvivorRatio=32",
+                    "-XX:+PerfDisableSharedMem",
+                    "-XX:MaxTenuringThreshold=1",
+                ]
+            }
+            JvmPreset::Zgc => {
+                let mut flags = vec!["-XX:+UseZGC"];
+                // Java 23 起 ZGC 默认分代，之后 ZGenerational 被废弃
+                if java_major < 23 {
+                    flags.push("-XX:+ZGenerational");
+                }
+                flags.push("-XX:+AlwaysPreTouch");
+                flags
+            }
+            JvmPreset::LowMemory => vec![
+                "-XX:+UseSerialGC",
+                "-XX:MinHeapFreeRatio=10",
+                "-XX:MaxHeapFreeRatio=30",
+                "-Xss512k",
+            ],
+        };
+        flags.into_iter().map(|flag| flag.to_string()).collect()
+    }
+}
+
+/// 去掉目标 Java 版本不支持的参数，避免 JVM 因未知选项无法启动
+pub fn filter_jvm_flags(flags: &[String], java_major: u32) -> Vec<String> {
+    flags
+        .iter()
+        .filter(|flag| {
+            let supported = flag_supported(flag, java_major);
+            if !supported {
+                warn!(target: "launch", "{} is not supported by Java {}, removed", flag, java_major);
+            }
+            supported
+        })
+        .cloned()
+        .collect()
+}
+
+fn flag_supported(flag: &str, java_major: u32) -> bool {
+    let name = flag
+        .trim_start_matches("-XX:")
+        .trim_start_matches(['+', '-'])
+        .split('=')
+        .next()
+        .unwrap_or_default();
+    if !flag.starts_with("-XX:") {
+        return true;
+    }
+    match name {
+        // 在对应版本中移除
+        "UseParNewGC" | "CMSIncrementalMode" | "CMSIncrementalPacing" => java_major < 10,
+        "AggressiveOpts" => java_major < 12,
+        "UseConcMarkSweepGC" | "CMSParallelRemarkEnabled" | "CMSClassUnloadingEnabled"
+        | "CMSInitiatingOccupancyFraction" | "UseCMSInitiatingOccupancyOnly" => java_major < 14,
+        "UseBiasedLocking" => java_major < 18,
+        "PermSize" | "MaxPermSize" => java_major < 17,
+        // 新版本才有
+        "UseZGC" => java_major >= 15,
+        "ZGenerational" => (21..24).contains(&java_major),
+        "UseShenandoahGC" => java_major >= 12,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(total: u32, available: u32) -> MemoryInfo {
+        MemoryInfo { total, available }
+    }
+
+    #[test]
+    fn parse_meminfo() {
+        let content = "MemTotal:       16318480 kB\nMemFree:         1000000 kB\nMemAvailable:    8159240 kB\n";
+        assert_eq!(MemoryInfo::parse(content), Some(memory(15936, 7968)));
+        assert_eq!(MemoryInfo::parse("MemTotal: 2048000 kB"), Some(memory(2000, 2000)));
+        assert_eq!(MemoryInfo::parse(""), None);
+    }
+
+    #[test]
+    fn auto_max_memory_limits() {
+        assert_eq!(auto_max_memory(&memory(16384, 12000), 0, 1024), Some(BASE_HEAP));
+        assert_eq!(auto_max_memory(&memory(16384, 12000), 100, 1024), Some(6656));
+        assert_eq!(auto_max_memory(&memory(65536, 65536), 1000, 1024), Some(MAX_AUTO_HEAP));
+        // 可用内存低于 MIN_HEAP 时不超过可用内存
+        assert_eq!(auto_max_memory(&memory(8192, 1500), 0, 1024), Some(HEAP_ALIGN));
+        assert_eq!(auto_max_memory(&memory(8192, 1800), 0, 1024), Some(768));
+        // 扣除预留后不足 HEAP_ALIGN 时不自动分配
+        assert_eq!(auto_max_memory(&memory(8192, 1200), 0, 1024), None);
+        assert_eq!(auto_max_memory(&memory(8192, 900), 0, 1024), None);
+        assert_eq!(auto_max_memory(&memory(1024, 1024), 0, 1024), None);
+    }
+}
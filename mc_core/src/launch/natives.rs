@@ -0,0 +1,74 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::install::minecraft::{
+    game_root::GameRoot, rule::RuleContext, version_json::VersionJson,
+};
+
+/// 把当前系统需要的 natives 解压到 dir，启动前调用
+pub fn extract_natives(
+    root: &GameRoot,
+    profile: &VersionJson,
+    ctx: &RuleContext,
+    dir: &Path,
+) -> Result<usize> {
+    fs::create_dir_all(dir).context(format!("cannot create natives directory: {:?}", dir))?;
+
+    let mut count = 0;
+    for library in profile.libraries.iter().filter(|l| l.is_allowed(ctx)) {
+        if !library.is_native() {
+            continue;
+        }
+        let artifact = library.native_artifact(ctx).or_else(|| library.artifact());
+        let Some(path) = artifact.and_then(|artifact| artifact.path) else {
+            continue;
+        };
+        let exclude = library
+            .extract
+            .as_ref()
+            .map(|extract| extract.exclude.clone())
+            .unwrap_or_default();
+        count += extract_jar(&root.library(&path), dir, &exclude)?;
+    }
+    info!(target: "launch", "extracted {} native files to {:?}", count, dir);
+    Ok(count)
+}
+
+fn extract_jar(jar: &Path, dir: &Path, exclude: &[String]) -> Result<usize> {
+    let file = File::open(jar).context(format!("cannot open native library: {:?}", jar))?;
+    let mut archive =
+        zip::ZipArchive::new(file).context(format!("invalid native library: {:?}", jar))?;
+
+    let mut count = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let name_str = name.to_string_lossy().replace('\\', "/");
+        if name_str.starts_with("META-INF/")
+            || exclude.iter().any(|prefix| name_str.starts_with(prefix.as_str()))
+        {
+            continue;
+        }
+        // natives 全部平铺在目录下
+        let Some(file_name) = name.file_name() else {
+            continue;
+        };
+        let target = dir.join(file_name);
+        let mut out =
+            File::create(&target).context(format!("cannot create native file: {:?}", target))?;
+        io::copy(&mut entry, &mut out)?;
+        count += 1;
+    }
+    Ok(count)
+}
@@ -0,0 +1,334 @@
+//! 管理已经启动的游戏进程
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    process::{Child, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+
+use crate::launch::{
+    LaunchCommand,
+    crash::{CrashAnalysis, analyze_crash},
+    log4j::{Log4jParser, LogRecord},
+};
+
+/// 每个游戏最多保留的输出行数
+const MAX_LOG_LINES: usize = 5000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+impl OutputLine {
+    pub fn text(&self) -> &str {
+        match self {
+            OutputLine::Stdout(line) | OutputLine::Stderr(line) => line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameState {
+    Running,
+    /// code 为 None 表示被信号结束
+    Exited { code: Option<i32> },
+}
+
+/// 发送给订阅者的事件
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    Output { id: usize, line: OutputLine },
+    Exited { id: usize, code: Option<i32>, duration: Duration },
+}
+
+/// 游戏进程的状态快照
+#[derive(Debug, Clone)]
+pub struct GameInfo {
+    pub id: usize,
+    pub name: String,
+    pub pid: u32,
+    /// 游戏运行目录，崩溃报告在这里查找
+    pub working_dir: PathBuf,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    pub state: GameState,
+}
+
+struct GameProcess {
+    info: GameInfo,
+    started: Instant,
+    child: Arc<Mutex<Child>>,
+    logs: LogBuffer<OutputLine>,
+    records: LogBuffer<LogRecord>,
+}
+
+type Subscribers = Arc<Mutex<Vec<Sender<GameEvent>>>>;
+type LogBuffer<T> = Arc<Mutex<VecDeque<T>>>;
+
+/// 持有所有游戏子进程，转发输出并记录退出状态
+#[derive(Default)]
+pub struct GameProcessManager {
+    games: Arc<Mutex<Vec<GameProcess>>>,
+    subscribers: Subscribers,
+}
+
+impl GameProcessManager {
+    /// 启动游戏，返回游戏 id
+    pub fn launch(&self, name: &str, command: &LaunchCommand) -> Result<usize> {
+        let mut child = command
+            .to_command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("failed to start java: {:?}", command.java))?;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let logs = Arc::new(Mutex::new(VecDeque::new()));
+        let records = Arc::new(Mutex::new(VecDeque::new()));
+        let child = Arc::new(Mutex::new(child));
+
+        let id = {
+            let mut games = self.games.lock().unwrap();
+            let id = games.len();
+            let pid = child.lock().unwrap().id();
+            games.push(GameProcess {
+                info: GameInfo {
+                    id,
+                    name: name.to_string(),
+                    pid,
+                    working_dir: command.working_dir.clone(),
+                    started_at: SystemTime::now(),
+                    duration: Duration::ZERO,
+                    state: GameState::Running,
+                },
+                started: Instant::now(),
+                child: child.clone(),
+                logs: logs.clone(),
+                records: records.clone(),
+            });
+            info!(target: "game", "game {} started: {} (pid {})", id, name, pid);
+            id
+        };
+
+        let readers = [
+            stdout.map(|out| {
+                self.spawn_reader(id, out, logs.clone(), records.clone(), OutputLine::Stdout)
+            }),
+            stderr.map(|err| {
+                self.spawn_reader(id, err, logs.clone(), records.clone(), OutputLine::Stderr)
+            }),
+        ];
+        self.spawn_waiter(id, child, readers.into_iter().flatten().collect());
+        Ok(id)
+    }
+
+    fn spawn_reader<R: Read + Send + 'static>(
+        &self,
+        id: usize,
+        output: R,
+        logs: LogBuffer<OutputLine>,
+        records: LogBuffer<LogRecord>,
+        wrap: fn(String) -> OutputLine,
+    ) -> thread::JoinHandle<()> {
+        let subscribers = self.subscribers.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(output);
+            let mut parser = Log4jParser::new();
+            let mut buffer = Vec::new();
+            // 游戏输出不一定是 UTF-8，按字节读取后再转换
+            while reader.read_until(b'\n', &mut buffer).unwrap_or(0) > 0 {
+                let text = String::from_utf8_lossy(&buffer)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                buffer.clear();
+                let line = wrap(text);
+                push_bounded(&logs, line.clone());
+                for record in parser.feed(&line) {
+                    push_bounded(&records, record);
+                }
+                broadcast(&subscribers, GameEvent::Output { id, line });
+            }
+            for record in parser.finish() {
+                push_bounded(&records, record);
+            }
+        })
+    }
+
+    fn spawn_waiter(
+        &self,
+        id: usize,
+        child: Arc<Mutex<Child>>,
+        readers: Vec<thread::JoinHandle<()>>,
+    ) {
+        let games = self.games.clone();
+        let subscribers = self.subscribers.clone();
+        thread::spawn(move || {
+            // 不持有锁等待，以便其他线程可以结束进程
+            let status = loop {
+                match child.lock().unwrap().try_wait() {
+                    Ok(Some(status)) => break Some(status),
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(target: "game", "failed to wait game {}: {}", id, e);
+                        break None;
+                    }
+                }
+                thread::sleep(Duration::from_millis(100));
+            };
+            let code = status.and_then(|status| status.code());
+            let duration = {
+                let mut games = games.lock().unwrap();
+                let game = &mut games[id];
+                game.info.duration = game.started.elapsed();
+                game.info.state = GameState::Exited { code };
+                game.info.duration
+            };
+            // 等待剩余输出读完再通知退出
+            for reader in readers {
+                let _ = reader.join();
+            }
+            info!(target: "game", "game {} exited with {:?} after {:?}", id, code, duration);
+            broadcast(&subscribers, GameEvent::Exited { id, code, duration });
+        });
+    }
+
+    /// 订阅所有游戏的输出和退出事件
+    pub fn subscribe(&self) -> Receiver<GameEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// 所有游戏的当前状态
+    pub fn list(&self) -> Vec<GameInfo> {
+        self.games
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|game| {
+                let mut info = game.info.clone();
+                if info.state == GameState::Running {
+                    info.duration = game.started.elapsed();
+                }
+                info
+            })
+            .collect()
+    }
+
+    pub fn get(&self, id: usize) -> Option<GameInfo> {
+        self.list().into_iter().nth(id)
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.list()
+            .iter()
+            .filter(|game| game.state == GameState::Running)
+            .count()
+    }
+
+    /// 游戏已经输出的内容
+    pub fn output(&self, id: usize) -> Vec<OutputLine> {
+        self.games
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|game| game.logs.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 游戏非正常退出时分析崩溃原因，正常运行或退出时返回 None
+    pub fn analyze_crash(&self, id: usize) -> Option<CrashAnalysis> {
+        let info = self.get(id)?;
+        let GameState::Exited { code } = info.state else {
+            return None;
+        };
+        if code == Some(0) {
+            return None;
+        }
+        let output: Vec<String> = self
+            .output(id)
+            .iter()
+            .map(|line| line.text().to_string())
+            .collect();
+        Some(analyze_crash(&info.working_dir, info.started_at, code, &output))
+    }
+
+    /// 解析后的游戏日志
+    pub fn records(&self, id: usize) -> Vec<LogRecord> {
+        self.games
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|game| game.records.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 请求游戏正常退出（unix 下发送 SIGTERM，其他系统直接结束）
+    pub fn terminate(&self, id: usize) -> Result<()> {
+        #[cfg(unix)]
+        {
+            let child = self.running_child(id)?;
+            // 持有锁期间等待线程无法回收进程，pid 不会被复用
+            let mut child = child.lock().unwrap();
+            if child.try_wait().context(format!("failed to wait game {}", id))?.is_some() {
+                bail!("game {} is not running", id);
+            }
+            // SAFETY: 只向自己启动且尚未回收的子进程发送信号
+            if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } != 0 {
+                bail!("failed to terminate game {}: {}", id, std::io::Error::last_os_error());
+            }
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            self.kill(id)
+        }
+    }
+
+    /// 强制结束游戏
+    pub fn kill(&self, id: usize) -> Result<()> {
+        self.running_child(id)?
+            .lock()
+            .unwrap()
+            .kill()
+            .context(format!("failed to kill game {}", id))
+    }
+
+    fn running_child(&self, id: usize) -> Result<Arc<Mutex<Child>>> {
+        let games = self.games.lock().unwrap();
+        match games.get(id) {
+            Some(game) if game.info.state == GameState::Running => Ok(game.child.clone()),
+            Some(_) => bail!("game {} is not running", id),
+            None => bail!("game {} not found", id),
+        }
+    }
+}
+
+fn push_bounded<T>(buffer: &Mutex<VecDeque<T>>, item: T) {
+    let mut buffer = buffer.lock().unwrap();
+    if buffer.len() >= MAX_LOG_LINES {
+        buffer.pop_front();
+    }
+    buffer.push_back(item);
+}
+
+fn broadcast(subscribers: &Subscribers, event: GameEvent) {
+    subscribers
+        .lock()
+        .unwrap()
+        .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
@@ -1,4 +1,10 @@
 pub mod account;
 pub mod download;
 pub mod install;
-pub mod statue;
\ No newline at end of file
+pub mod instance;
+pub mod java;
+pub mod launch;
+pub mod nbt;
+pub mod statue;
+#[cfg(test)]
+mod test_server;
\ No newline at end of file
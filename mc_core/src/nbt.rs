@@ -0,0 +1,474 @@
+//! 读写 NBT（Named Binary Tag），level.dat、servers.dat 和玩家数据都使用该格式
+//!
+//! 支持 gzip、zlib 压缩和未压缩的 Java 版大端序格式。
+//! 数据可以读取为 [`Tag`] 树，也可以通过 serde 直接读写为结构体：
+//!
+//! ```no_run
+//! use mc_core::nbt;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct LevelDat {
+//!     #[serde(rename = "Data")]
+//!     data: Data,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Data {
+//!     #[serde(rename = "LevelName")]
+//!     level_name: String,
+//! }
+//!
+//! let bytes = std::fs::read("level.dat").unwrap();
+//! let level: LevelDat = nbt::from_bytes(&bytes).unwrap();
+//! println!("{}", level.data.level_name);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::nbt::binary::{Reader, TAG_END, Writer};
+
+pub mod binary;
+pub mod de;
+pub mod mutf8;
+pub mod ser;
+
+/// 解压后的最大大小，防止压缩炸弹
+pub const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+pub type Compound = BTreeMap<String, Tag>;
+
+/// 一个标签，列表中的标签必须是同一种类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// 二进制格式中的类型 id
+    pub fn tag_type(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => binary::TAG_BYTE,
+            Tag::Short(_) => binary::TAG_SHORT,
+            Tag::Int(_) => binary::TAG_INT,
+            Tag::Long(_) => binary::TAG_LONG,
+            Tag::Float(_) => binary::TAG_FLOAT,
+            Tag::Double(_) => binary::TAG_DOUBLE,
+            Tag::ByteArray(_) => binary::TAG_BYTE_ARRAY,
+            Tag::String(_) => binary::TAG_STRING,
+            Tag::List(_) => binary::TAG_LIST,
+            Tag::Compound(_) => binary::TAG_COMPOUND,
+            Tag::IntArray(_) => binary::TAG_INT_ARRAY,
+            Tag::LongArray(_) => binary::TAG_LONG_ARRAY,
+        }
+    }
+
+    /// Compound 中名为 key 的标签
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        self.as_compound()?.get(key)
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(compound) => Some(compound),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Option<&mut Compound> {
+        match self {
+            Tag::Compound(compound) => Some(compound),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 任意整数类型的值，Byte 也用于保存布尔值
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(v) => Some(*v as i64),
+            Tag::Short(v) => Some(*v as i64),
+            Tag::Int(v) => Some(*v as i64),
+            Tag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Tag::Float(v) => Some(*v as f64),
+            Tag::Double(v) => Some(*v),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+}
+
+/// 压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+impl Compression {
+    /// 根据开头的字节判断
+    pub fn detect(data: &[u8]) -> Self {
+        match data {
+            [0x1F, 0x8B, ..] => Compression::Gzip,
+            // 标签类型小于 0x10，和 zlib 头不会冲突
+            [cmf, flg, ..]
+                if cmf & 0x0F == 8 && cmf >> 4 > 0 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31) =>
+            {
+                Compression::Zlib
+            }
+            _ => Compression::None,
+        }
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let reader: Box<dyn Read + '_> = match self {
+            Compression::None => return Ok(data.to_vec()),
+            Compression::Gzip => Box::new(GzDecoder::new(data)),
+            Compression::Zlib => Box::new(ZlibDecoder::new(data)),
+        };
+        let mut output = Vec::new();
+        reader
+            .take(MAX_DECOMPRESSED_SIZE + 1)
+            .read_to_end(&mut output)
+            .context("failed to decompress nbt")?;
+        if output.len() as u64 > MAX_DECOMPRESSED_SIZE {
+            bail!("decompressed nbt is larger than {} bytes", MAX_DECOMPRESSED_SIZE);
+        }
+        Ok(output)
+    }
+
+    fn compress(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        let level = flate2::Compression::default();
+        Ok(match self {
+            Compression::None => data,
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+        })
+    }
+}
+
+/// 一个 NBT 文件：根标签的名称和内容，以及原来的压缩方式
+#[derive(Debug, Clone, PartialEq)]
+pub struct NbtFile {
+    /// 根标签的名称，通常为空
+    pub name: String,
+    pub root: Tag,
+    pub compression: Compression,
+}
+
+impl NbtFile {
+    pub fn new(root: Tag, compression: Compression) -> Self {
+        Self { name: String::new(), root, compression }
+    }
+
+    /// 自动识别压缩方式，根标签之后的多余数据会被忽略
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let compression = Compression::detect(data);
+        let data = compression.decompress(data)?;
+        let mut reader = Reader::new(&data);
+        let tag_type = reader.u8().context("empty nbt data")?;
+        if tag_type == TAG_END {
+            bail!("nbt root tag cannot be an end tag");
+        }
+        let name = reader.string()?;
+        let root = reader.payload(tag_type, 0)?;
+        Ok(Self { name, root, compression })
+    }
+
+    /// 按原来的压缩方式编码
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut writer = Writer::default();
+        writer.u8(self.root.tag_type());
+        writer.string(&self.name)?;
+        writer.payload(&self.root)?;
+        self.compression.compress(writer.data)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path).context(format!("failed to read {:?}", path))?;
+        Self::from_bytes(&data).context(format!("failed to parse {:?}", path))
+    }
+
+    /// 先写临时文件再替换，避免写入中断损坏存档
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let temp = path.with_extension("nbt.tmp");
+        fs::write(&temp, self.to_bytes()?).context(format!("failed to write {:?}", temp))?;
+        fs::rename(&temp, path).context(format!("failed to write {:?}", path))
+    }
+}
+
+/// 把 NBT 数据直接读取为结构体
+pub fn from_bytes<T: for<'de> Deserialize<'de>>(data: &[u8]) -> Result<T> {
+    from_tag(&NbtFile::from_bytes(data)?.root)
+}
+
+/// 把结构体编码为 NBT 数据，根标签名称为空
+pub fn to_bytes<T: Serialize>(value: &T, compression: Compression) -> Result<Vec<u8>> {
+    NbtFile::new(to_tag(value)?, compression).to_bytes()
+}
+
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: &'de Tag) -> Result<T> {
+    Ok(T::deserialize(de::TagDeserializer::new(tag))?)
+}
+
+pub fn to_tag<T: Serialize>(value: &T) -> Result<Tag> {
+    match value.serialize(ser::TagSerializer)? {
+        Some(tag) => Ok(tag),
+        None => bail!("cannot serialize an empty value as nbt"),
+    }
+}
+
+/// serde 转换时的错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::nbt::ser::IntArray;
+
+    fn sample() -> Tag {
+        let mut nested = Compound::new();
+        nested.insert("name".to_string(), Tag::String("nested".to_string()));
+        nested.insert("empty".to_string(), Tag::List(Vec::new()));
+        let mut root = Compound::new();
+        root.insert("byte".to_string(), Tag::Byte(-1));
+        root.insert("short".to_string(), Tag::Short(i16::MIN));
+        root.insert("int".to_string(), Tag::Int(i32::MAX));
+        root.insert("long".to_string(), Tag::Long(i64::MIN));
+        root.insert("float".to_string(), Tag::Float(0.5));
+        root.insert("double".to_string(), Tag::Double(-1e300));
+        root.insert("bytes".to_string(), Tag::ByteArray(vec![-128, 0, 127]));
+        root.insert("string".to_string(), Tag::String("a\0中文😀".to_string()));
+        root.insert("list".to_string(), Tag::List(vec![Tag::Int(1), Tag::Int(2)]));
+        root.insert("compounds".to_string(), Tag::List(vec![Tag::Compound(nested.clone())]));
+        root.insert("compound".to_string(), Tag::Compound(nested));
+        root.insert("ints".to_string(), Tag::IntArray(vec![i32::MIN, 0, i32::MAX]));
+        root.insert("longs".to_string(), Tag::LongArray(vec![i64::MIN, i64::MAX]));
+        Tag::Compound(root)
+    }
+
+    #[test]
+    fn round_trip() {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zlib] {
+            let file = NbtFile { name: "root".to_string(), root: sample(), compression };
+            let bytes = file.to_bytes().unwrap();
+            assert_eq!(Compression::detect(&bytes), compression);
+            assert_eq!(NbtFile::from_bytes(&bytes).unwrap(), file);
+        }
+    }
+
+    /// 规范中的 hello_world.nbt
+    #[test]
+    fn hello_world() {
+        let mut bytes = vec![10, 0, 11];
+        bytes.extend(b"hello world");
+        bytes.extend([8, 0, 4]);
+        bytes.extend(b"name");
+        bytes.extend([0, 9]);
+        bytes.extend(b"Bananrama");
+        bytes.push(0);
+        let file = NbtFile::from_bytes(&bytes).unwrap();
+        assert_eq!(file.name, "hello world");
+        assert_eq!(file.root.get("name").and_then(Tag::as_str), Some("Bananrama"));
+        assert_eq!(file.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn modified_utf8() {
+        assert_eq!(mutf8::encode("\0"), [0xC0, 0x80]);
+        assert_eq!(mutf8::encode("😀"), [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(mutf8::decode(&[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]).unwrap(), "😀");
+        for text in ["", "ascii", "a\0b", "中文", "\u{7FF}\u{800}\u{FFFF}", "😀🎮"] {
+            assert_eq!(mutf8::decode(&mutf8::encode(text)).unwrap(), text);
+        }
+        assert!(mutf8::decode(&[0xE0, 0x80]).is_err());
+        assert!(mutf8::decode(&[0xFF]).is_err());
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum GameType {
+        Survival,
+        Creative,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Player {
+        name: String,
+        hardcore: bool,
+        slot: u8,
+        seed: u64,
+        health: f32,
+        uuid: IntArray,
+        pos: Vec<f64>,
+        game_type: GameType,
+        spawn: Option<i32>,
+        tags: Vec<String>,
+        extra: Tag,
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let player = Player {
+            name: "Steve".to_string(),
+            hardcore: true,
+            slot: 255,
+            seed: u64::MAX,
+            health: 20.0,
+            uuid: IntArray(vec![1, 2, 3, 4]),
+            pos: vec![0.5, 64.0, -0.5],
+            game_type: GameType::Creative,
+            spawn: None,
+            tags: Vec::new(),
+            extra: sample(),
+        };
+        let tag = to_tag(&player).unwrap();
+        assert_eq!(tag.get("Hardcore"), Some(&Tag::Byte(1)));
+        assert_eq!(tag.get("Slot"), Some(&Tag::Byte(-1)));
+        assert_eq!(tag.get("Uuid"), Some(&Tag::IntArray(vec![1, 2, 3, 4])));
+        assert_eq!(tag.get("GameType"), Some(&Tag::String("Creative".to_string())));
+        assert_eq!(tag.get("Spawn"), None);
+        assert_eq!(tag.get("Extra"), Some(&sample()));
+
+        let bytes = to_bytes(&player, Compression::Gzip).unwrap();
+        assert_eq!(from_bytes::<Player>(&bytes).unwrap(), player);
+        assert_eq!(NbtFile::from_bytes(&bytes).unwrap().root, tag);
+    }
+
+    #[test]
+    fn serde_json_interop() {
+        let json: Tag = serde_json::from_str(r#"{"a": 1, "b": [1.5], "c": "text"}"#).unwrap();
+        assert_eq!(json.get("a"), Some(&Tag::Long(1)));
+        assert_eq!(json.get("b"), Some(&Tag::List(vec![Tag::Double(1.5)])));
+        assert!(to_tag(&serde_json::json!([1, "mixed"])).is_err());
+    }
+
+    #[test]
+    fn invalid_data() {
+        let bytes = NbtFile::new(sample(), Compression::None).to_bytes().unwrap();
+        for len in 0..bytes.len() {
+            assert!(NbtFile::from_bytes(&bytes[..len]).is_err());
+        }
+        let compressed = NbtFile::new(sample(), Compression::Gzip).to_bytes().unwrap();
+        for len in 0..compressed.len() {
+            assert!(NbtFile::from_bytes(&compressed[..len]).is_err());
+        }
+
+        // 超长或负数的长度
+        for tag_type in [7, 11, 12] {
+            assert!(NbtFile::from_bytes(&[tag_type, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF]).is_err());
+            assert!(NbtFile::from_bytes(&[tag_type, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        }
+        assert!(NbtFile::from_bytes(&[9, 0, 0, 10, 0x7F, 0xFF, 0xFF, 0xFF]).is_err());
+        assert!(NbtFile::from_bytes(&[9, 0, 0, 0, 0, 0, 0, 1]).is_err());
+        assert!(NbtFile::from_bytes(&[9, 0, 0, 13, 0, 0, 0, 1, 0]).is_err());
+        assert!(NbtFile::from_bytes(&[0]).is_err());
+
+        // 每层列表都声明最大长度，预分配不能随嵌套层数放大
+        let mut lists = vec![9, 0, 0];
+        for _ in 0..64 {
+            lists.extend([9, 0x00, 0x03, 0x00, 0x00]);
+        }
+        lists.resize(lists.len() + (1 << 20), 0);
+        assert!(NbtFile::from_bytes(&lists).is_err());
+        let mut compounds = vec![9, 0, 0, 10, 0, 0x10, 0, 0];
+        compounds.resize(compounds.len() + (1 << 20), 0);
+        let file = NbtFile::from_bytes(&compounds).unwrap();
+        assert!(matches!(file.root, Tag::List(list) if list.len() == 1 << 20));
+
+        // 嵌套过深
+        let mut deep = vec![9, 0, 0];
+        for _ in 0..=binary::MAX_DEPTH {
+            deep.extend([9, 0, 0, 0, 1]);
+        }
+        deep.extend([0, 0, 0, 0, 0]);
+        assert!(NbtFile::from_bytes(&deep).is_err());
+
+        // 随机修改字节
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for _ in 0..2000 {
+            let mut data = bytes.clone();
+            for _ in 0..4 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let index = (seed % data.len() as u64) as usize;
+                data[index] = (seed >> 32) as u8;
+            }
+            let _ = NbtFile::from_bytes(&data);
+        }
+    }
+}
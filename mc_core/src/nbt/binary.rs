@@ -0,0 +1,227 @@
+//! 大端序 NBT 的读写
+//!
+//! 读取时所有长度都先和剩余的字节数比较再分配内存，嵌套层数有上限，
+//! 损坏或恶意构造的数据只会返回错误
+
+use anyhow::{Context, Result, bail};
+
+use crate::nbt::{Compound, Tag, mutf8};
+
+/// 与游戏相同的最大嵌套层数
+pub const MAX_DEPTH: usize = 512;
+
+pub const TAG_END: u8 = 0;
+pub const TAG_BYTE: u8 = 1;
+pub const TAG_SHORT: u8 = 2;
+pub const TAG_INT: u8 = 3;
+pub const TAG_LONG: u8 = 4;
+pub const TAG_FLOAT: u8 = 5;
+pub const TAG_DOUBLE: u8 = 6;
+pub const TAG_BYTE_ARRAY: u8 = 7;
+pub const TAG_STRING: u8 = 8;
+pub const TAG_LIST: u8 = 9;
+pub const TAG_COMPOUND: u8 = 10;
+pub const TAG_INT_ARRAY: u8 = 11;
+pub const TAG_LONG_ARRAY: u8 = 12;
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            bail!("unexpected end of nbt data at byte {}", self.pos);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        mutf8::decode(self.take(len)?)
+    }
+
+    /// 数组和列表的长度，每个元素至少占 element_size 字节
+    fn length(&mut self, element_size: usize) -> Result<usize> {
+        let len = self.i32()?;
+        let Ok(len) = usize::try_from(len) else {
+            bail!("negative length {} at byte {}", len, self.pos);
+        };
+        if len.saturating_mul(element_size) > self.remaining() {
+            bail!("length {} exceeds the remaining data at byte {}", len, self.pos);
+        }
+        Ok(len)
+    }
+
+    /// 读取一个标签的内容
+    pub fn payload(&mut self, tag_type: u8, depth: usize) -> Result<Tag> {
+        if depth > MAX_DEPTH {
+            bail!("nbt nested deeper than {}", MAX_DEPTH);
+        }
+        Ok(match tag_type {
+            TAG_BYTE => Tag::Byte(self.u8()? as i8),
+            TAG_SHORT => Tag::Short(self.i16()?),
+            TAG_INT => Tag::Int(self.i32()?),
+            TAG_LONG => Tag::Long(self.i64()?),
+            TAG_FLOAT => Tag::Float(f32::from_be_bytes(self.array()?)),
+            TAG_DOUBLE => Tag::Double(f64::from_be_bytes(self.array()?)),
+            TAG_BYTE_ARRAY => {
+                let len = self.length(1)?;
+                Tag::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            TAG_STRING => Tag::String(self.string()?),
+            TAG_LIST => {
+                let element_type = self.u8()?;
+                let len = self.length(element_size(element_type))?;
+                if element_type == TAG_END && len > 0 {
+                    bail!("list of end tags with length {}", len);
+                }
+                // 长度只保证每个元素至少占 element_size 字节，预分配时按内存中的大小再限制一次
+                let mut list = Vec::with_capacity(len.min(self.remaining() / size_of::<Tag>()));
+                for _ in 0..len {
+                    list.push(self.payload(element_type, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            TAG_COMPOUND => {
+                let mut compound = Compound::new();
+                loop {
+                    let tag_type = self.u8()?;
+                    if tag_type == TAG_END {
+                        break;
+                    }
+                    let name = self.string()?;
+                    let value = self
+                        .payload(tag_type, depth + 1)
+                        .with_context(|| format!("in {:?}", name))?;
+                    compound.insert(name, value);
+                }
+                Tag::Compound(compound)
+            }
+            TAG_INT_ARRAY => {
+                let len = self.length(4)?;
+                Tag::IntArray((0..len).map(|_| self.i32()).collect::<Result<_>>()?)
+            }
+            TAG_LONG_ARRAY => {
+                let len = self.length(8)?;
+                Tag::LongArray((0..len).map(|_| self.i64()).collect::<Result<_>>()?)
+            }
+            _ => bail!("unknown tag type {} at byte {}", tag_type, self.pos),
+        })
+    }
+}
+
+/// 每种标签的内容至少占用的字节数
+fn element_size(tag_type: u8) -> usize {
+    match tag_type {
+        TAG_END | TAG_BYTE | TAG_COMPOUND => 1,
+        TAG_SHORT | TAG_STRING => 2,
+        TAG_INT | TAG_FLOAT | TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY => 4,
+        TAG_LIST => 5,
+        _ => 8,
+    }
+}
+
+#[derive(Default)]
+pub struct Writer {
+    pub data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn string(&mut self, value: &str) -> Result<()> {
+        let bytes = mutf8::encode(value);
+        let Ok(len) = u16::try_from(bytes.len()) else {
+            bail!("string is too long for nbt: {} bytes", bytes.len());
+        };
+        self.data.extend(len.to_be_bytes());
+        self.data.extend(bytes);
+        Ok(())
+    }
+
+    fn length(&mut self, len: usize) -> Result<()> {
+        let Ok(len) = i32::try_from(len) else {
+            bail!("array is too long for nbt: {} elements", len);
+        };
+        self.data.extend(len.to_be_bytes());
+        Ok(())
+    }
+
+    pub fn payload(&mut self, tag: &Tag) -> Result<()> {
+        match tag {
+            Tag::Byte(v) => self.data.push(*v as u8),
+            Tag::Short(v) => self.data.extend(v.to_be_bytes()),
+            Tag::Int(v) => self.data.extend(v.to_be_bytes()),
+            Tag::Long(v) => self.data.extend(v.to_be_bytes()),
+            Tag::Float(v) => self.data.extend(v.to_be_bytes()),
+            Tag::Double(v) => self.data.extend(v.to_be_bytes()),
+            Tag::ByteArray(v) => {
+                self.length(v.len())?;
+                self.data.extend(v.iter().map(|&b| b as u8));
+            }
+            Tag::String(v) => self.string(v)?,
+            Tag::List(list) => {
+                let element_type = list.first().map_or(TAG_END, Tag::tag_type);
+                if list.iter().any(|tag| tag.tag_type() != element_type) {
+                    bail!("nbt list elements must have the same type");
+                }
+                self.u8(element_type);
+                self.length(list.len())?;
+                for tag in list {
+                    self.payload(tag)?;
+                }
+            }
+            Tag::Compound(compound) => {
+                for (name, tag) in compound {
+                    self.u8(tag.tag_type());
+                    self.string(name)?;
+                    self.payload(tag)?;
+                }
+                self.u8(TAG_END);
+            }
+            Tag::IntArray(v) => {
+                self.length(v.len())?;
+                v.iter().for_each(|i| self.data.extend(i.to_be_bytes()));
+            }
+            Tag::LongArray(v) => {
+                self.length(v.len())?;
+                v.iter().for_each(|i| self.data.extend(i.to_be_bytes()));
+            }
+        }
+        Ok(())
+    }
+}
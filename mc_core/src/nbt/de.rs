@@ -0,0 +1,326 @@
+//! 用 serde 从 [`Tag`] 读取任意值
+//!
+//! 无符号整数按位从同样宽度的有符号整数转换，Byte 可以读取为 bool，
+//! 数组可以读取为 Vec，结构体中缺少的 Option 字段为 None
+
+use std::fmt;
+
+use serde::{
+    Deserialize,
+    de::{
+        self, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    },
+    forward_to_deserialize_any,
+};
+
+use crate::nbt::{
+    Compound, Error, Tag,
+    ser::{ByteArray, IntArray, LongArray},
+};
+
+/// Tag 通过这个名称从 [`TagDeserializer`] 读取原始的标签类型
+const TAG_TOKEN: &str = "__nbt_tag";
+
+#[derive(Clone, Copy)]
+pub struct TagDeserializer<'de> {
+    tag: &'de Tag,
+}
+
+impl<'de> TagDeserializer<'de> {
+    pub fn new(tag: &'de Tag) -> Self {
+        Self { tag }
+    }
+
+    fn invalid_type(&self, expected: &str) -> Error {
+        Error(format!("expected {}, found nbt tag type {}", expected, self.tag.tag_type()))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for TagDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn compound_access<'de>(
+    compound: &'de Compound,
+) -> MapDeserializer<'de, impl Iterator<Item = (&'de str, TagDeserializer<'de>)>, Error> {
+    MapDeserializer::new(
+        compound
+            .iter()
+            .map(|(key, tag)| (key.as_str(), TagDeserializer::new(tag))),
+    )
+}
+
+impl<'de> de::Deserializer<'de> for TagDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Byte(v) => visitor.visit_i8(*v),
+            Tag::Short(v) => visitor.visit_i16(*v),
+            Tag::Int(v) => visitor.visit_i32(*v),
+            Tag::Long(v) => visitor.visit_i64(*v),
+            Tag::Float(v) => visitor.visit_f32(*v),
+            Tag::Double(v) => visitor.visit_f64(*v),
+            Tag::ByteArray(v) => visitor.visit_seq(SeqDeserializer::new(v.iter().copied())),
+            Tag::String(v) => visitor.visit_borrowed_str(v),
+            Tag::List(v) => visitor.visit_seq(SeqDeserializer::new(v.iter().map(TagDeserializer::new))),
+            Tag::Compound(v) => visitor.visit_map(compound_access(v)),
+            Tag::IntArray(v) => visitor.visit_seq(SeqDeserializer::new(v.iter().copied())),
+            Tag::LongArray(v) => visitor.visit_seq(SeqDeserializer::new(v.iter().copied())),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Byte(v) => visitor.visit_bool(*v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Byte(v) => visitor.visit_u8(*v as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Short(v) => visitor.visit_u16(*v as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Int(v) => visitor.visit_u32(*v as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::Long(v) => visitor.visit_u64(*v as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::ByteArray(v) => visitor.visit_byte_buf(v.iter().map(|&b| b as u8).collect()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// 标签存在时总是 Some
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name == TAG_TOKEN {
+            visitor.visit_enum(self)
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
+    }
+
+    /// 单元变体为字符串，其它变体为只有一个键的 Compound
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.tag {
+            Tag::String(v) => visitor.visit_enum(v.as_str().into_deserializer()),
+            Tag::Compound(v) if v.len() == 1 => {
+                visitor.visit_enum(MapAccessDeserializer::new(compound_access(v)))
+            }
+            _ => Err(self.invalid_type("enum")),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Tag 的反序列化：变体为标签类型 id，内容为标签本身
+impl<'de> EnumAccess<'de> for TagDeserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let tag_type: de::value::U8Deserializer<Error> = self.tag.tag_type().into_deserializer();
+        Ok((seed.deserialize(tag_type)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for TagDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Err(self.invalid_type("unit variant"))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value, Error> {
+        Err(self.invalid_type("tuple variant"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(self.invalid_type("struct variant"))
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Tag;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an nbt tag")
+    }
+
+    /// 从 TagDeserializer 读取时保留原来的类型
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Tag, A::Error> {
+        use crate::nbt::binary::*;
+
+        let (tag_type, variant): (u8, _) = data.variant()?;
+        Ok(match tag_type {
+            TAG_BYTE => Tag::Byte(variant.newtype_variant()?),
+            TAG_SHORT => Tag::Short(variant.newtype_variant()?),
+            TAG_INT => Tag::Int(variant.newtype_variant()?),
+            TAG_LONG => Tag::Long(variant.newtype_variant()?),
+            TAG_FLOAT => Tag::Float(variant.newtype_variant()?),
+            TAG_DOUBLE => Tag::Double(variant.newtype_variant()?),
+            TAG_BYTE_ARRAY => Tag::ByteArray(variant.newtype_variant()?),
+            TAG_STRING => Tag::String(variant.newtype_variant()?),
+            TAG_LIST => Tag::List(variant.newtype_variant()?),
+            TAG_COMPOUND => Tag::Compound(variant.newtype_variant()?),
+            TAG_INT_ARRAY => Tag::IntArray(variant.newtype_variant()?),
+            TAG_LONG_ARRAY => Tag::LongArray(variant.newtype_variant()?),
+            _ => return Err(de::Error::custom(format!("unknown tag type {}", tag_type))),
+        })
+    }
+
+    /// 其它格式的反序列化器会直接调用 visit_newtype_struct
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Tag, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Tag, E> {
+        Ok(Tag::Byte(v as i8))
+    }
+
+    fn visit_i8<E: de::Error>(self, v: i8) -> Result<Tag, E> {
+        Ok(Tag::Byte(v))
+    }
+
+    fn visit_i16<E: de::Error>(self, v: i16) -> Result<Tag, E> {
+        Ok(Tag::Short(v))
+    }
+
+    fn visit_i32<E: de::Error>(self, v: i32) -> Result<Tag, E> {
+        Ok(Tag::Int(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Tag, E> {
+        Ok(Tag::Long(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Tag, E> {
+        Ok(Tag::Long(v as i64))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<Tag, E> {
+        Ok(Tag::Float(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Tag, E> {
+        Ok(Tag::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Tag, E> {
+        Ok(Tag::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Tag, E> {
+        Ok(Tag::String(v))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Tag, A::Error> {
+        let mut list = Vec::new();
+        while let Some(tag) = seq.next_element()? {
+            list.push(tag);
+        }
+        Ok(Tag::List(list))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Tag, A::Error> {
+        let mut compound = Compound::new();
+        while let Some((key, tag)) = map.next_entry()? {
+            compound.insert(key, tag);
+        }
+        Ok(Tag::Compound(compound))
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(TAG_TOKEN, TagVisitor)
+    }
+}
+
+/// 数组包装类型也可以从列表读取
+macro_rules! array_deserialize {
+    ($type:ident) => {
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Deserialize::deserialize(deserializer).map($type)
+            }
+        }
+    };
+}
+
+array_deserialize!(ByteArray);
+array_deserialize!(IntArray);
+array_deserialize!(LongArray);
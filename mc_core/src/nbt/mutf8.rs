@@ -0,0 +1,59 @@
+//! Java 的 Modified UTF-8
+//!
+//! 与 UTF-8 的区别：`\0` 编码为 `C0 80`，BMP 以外的字符按 UTF-16 代理对分别编码为 3 字节
+
+use anyhow::{Result, bail};
+
+/// 解码，不成对的代理项替换为 U+FFFD
+pub fn decode(bytes: &[u8]) -> Result<String> {
+    // 大部分字符串只有 ASCII
+    if bytes.iter().all(|b| (1..0x80).contains(b)) {
+        return Ok(String::from_utf8_lossy(bytes).into_owned());
+    }
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let continuation = |offset: usize| match bytes.get(i + offset) {
+            Some(c) if c & 0xC0 == 0x80 => Ok((c & 0x3F) as u16),
+            _ => bail!("invalid modified utf-8 at byte {}", i),
+        };
+        let (unit, len) = match b {
+            0x00..=0x7F => (b as u16, 1),
+            0xC0..=0xDF => (((b & 0x1F) as u16) << 6 | continuation(1)?, 2),
+            0xE0..=0xEF => (
+                ((b & 0x0F) as u16) << 12 | continuation(1)? << 6 | continuation(2)?,
+                3,
+            ),
+            _ => bail!("invalid modified utf-8 at byte {}", i),
+        };
+        units.push(unit);
+        i += len;
+    }
+    Ok(char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect())
+}
+
+/// 编码
+pub fn encode(text: &str) -> Vec<u8> {
+    if text.bytes().all(|b| (1..0x80).contains(&b)) {
+        return text.as_bytes().to_vec();
+    }
+    let mut bytes = Vec::with_capacity(text.len() + 8);
+    for unit in text.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    bytes
+}
@@ -0,0 +1,586 @@
+//! 用 serde 把任意值转换为 [`Tag`]
+//!
+//! NBT 没有无符号整数，u8、u16、u32、u64 按位转换为同样宽度的有符号整数；
+//! None 和 () 不产生标签，在结构体和 map 中会被跳过。
+//! 数组默认写为列表，需要 ByteArray、IntArray、LongArray 时使用 [`ByteArray`] 等包装类型
+
+use serde::{
+    Serialize,
+    ser::{self, Impossible},
+};
+
+use crate::nbt::{Compound, Error, Tag, binary};
+
+/// 包装类型和 Tag 自身通过这些名称告诉序列化器写为对应的数组
+pub(crate) const BYTE_ARRAY_TOKEN: &str = "__nbt_byte_array";
+pub(crate) const INT_ARRAY_TOKEN: &str = "__nbt_int_array";
+pub(crate) const LONG_ARRAY_TOKEN: &str = "__nbt_long_array";
+
+/// 写为 ByteArray 的字节数组
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ByteArray(pub Vec<i8>);
+
+/// 写为 IntArray 的整数数组，例如 UUID
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntArray(pub Vec<i32>);
+
+/// 写为 LongArray 的整数数组
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LongArray(pub Vec<i64>);
+
+impl Serialize for ByteArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BYTE_ARRAY_TOKEN, &self.0)
+    }
+}
+
+impl Serialize for IntArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(INT_ARRAY_TOKEN, &self.0)
+    }
+}
+
+impl Serialize for LongArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(LONG_ARRAY_TOKEN, &self.0)
+    }
+}
+
+/// 其它格式（例如 JSON）中数组与列表相同
+impl Serialize for Tag {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Tag::Byte(v) => serializer.serialize_i8(*v),
+            Tag::Short(v) => serializer.serialize_i16(*v),
+            Tag::Int(v) => serializer.serialize_i32(*v),
+            Tag::Long(v) => serializer.serialize_i64(*v),
+            Tag::Float(v) => serializer.serialize_f32(*v),
+            Tag::Double(v) => serializer.serialize_f64(*v),
+            Tag::ByteArray(v) => serializer.serialize_newtype_struct(BYTE_ARRAY_TOKEN, v),
+            Tag::String(v) => serializer.serialize_str(v),
+            Tag::List(v) => v.serialize(serializer),
+            Tag::Compound(v) => v.serialize(serializer),
+            Tag::IntArray(v) => serializer.serialize_newtype_struct(INT_ARRAY_TOKEN, v),
+            Tag::LongArray(v) => serializer.serialize_newtype_struct(LONG_ARRAY_TOKEN, v),
+        }
+    }
+}
+
+/// 输出 None 表示没有值
+pub struct TagSerializer;
+
+fn value<T: Serialize + ?Sized>(value: &T) -> Result<Tag, Error> {
+    value
+        .serialize(TagSerializer)?
+        .ok_or_else(|| Error("nbt lists cannot contain empty values".to_string()))
+}
+
+/// 把列表转换为对应的数组
+fn into_array(tag: Tag, token: &str) -> Result<Tag, Error> {
+    let Tag::List(list) = tag else {
+        return Err(Error(format!("{} must be a sequence", token)));
+    };
+    let invalid = || Error(format!("invalid element in {}", token));
+    Ok(match token {
+        BYTE_ARRAY_TOKEN => Tag::ByteArray(
+            list.into_iter()
+                .map(|tag| match tag {
+                    Tag::Byte(v) => Ok(v),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        INT_ARRAY_TOKEN => Tag::IntArray(
+            list.into_iter()
+                .map(|tag| match tag {
+                    Tag::Int(v) => Ok(v),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => Tag::LongArray(
+            list.into_iter()
+                .map(|tag| match tag {
+                    Tag::Long(v) => Ok(v),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+/// 一个键值对组成的 Compound，用于枚举的变体
+fn variant(name: &str, tag: Tag) -> Tag {
+    Tag::Compound(Compound::from([(name.to_string(), tag)]))
+}
+
+impl ser::Serializer for TagSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = VariantSerializer<CompoundSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(v as i8)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Long(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(v as i8)))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Short(v as i16)))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Int(v as i32)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Long(v as i64)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::ByteArray(v.iter().map(|&b| b as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        match name {
+            BYTE_ARRAY_TOKEN | INT_ARRAY_TOKEN | LONG_ARRAY_TOKEN => {
+                into_array(self::value(value)?, name).map(Some)
+            }
+            _ => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        Ok(value.serialize(self)?.map(|tag| self::variant(variant, tag)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(VariantSerializer { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(CompoundSerializer { compound: Compound::new(), key: None })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(VariantSerializer { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+pub struct SeqSerializer(Vec<Tag>);
+
+impl SeqSerializer {
+    fn finish(self) -> Result<Tag, Error> {
+        let element_type = self.0.first().map_or(binary::TAG_END, Tag::tag_type);
+        if self.0.iter().any(|tag| tag.tag_type() != element_type) {
+            return Err(Error("nbt list elements must have the same type".to_string()));
+        }
+        Ok(Tag::List(self.0))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, element: &T) -> Result<(), Error> {
+        self.0.push(value(element)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish().map(Some)
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, element: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, element)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, field: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, field)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct CompoundSerializer {
+    compound: Compound,
+    /// serialize_key 和 serialize_value 分开调用时暂存的键
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("serialize_value called before serialize_key".to_string()))?;
+        if let Some(tag) = value.serialize(TagSerializer)? {
+            self.compound.insert(key, tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+impl ser::SerializeStruct for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        if let Some(tag) = value.serialize(TagSerializer)? {
+            self.compound.insert(key.to_string(), tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// 枚举的元组和结构体变体，写为 { 变体名: 内容 }
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, field: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, field)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(variant(self.variant, self.inner.finish()?)))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<CompoundSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(variant(self.variant, Tag::Compound(self.inner.compound))))
+    }
+}
+
+/// Compound 的键只能是字符串，整数键转换为字符串
+struct KeySerializer;
+
+impl KeySerializer {
+    fn unsupported() -> Error {
+        Error("nbt compound keys must be strings".to_string())
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Self::unsupported())
+    }
+}
@@ -1,17 +1,25 @@
 //! Data struct for the application
 pub mod account;
 pub mod download;
+pub mod game;
 
 
 
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Error, Result};
 use directories::ProjectDirs;
 use log::{info, warn};
+use mc_core::{
+    account::{credential::CredentialStore, local_yggdrasil::LocalYggdrasil},
+    download::download_pool::DownloadPool,
+    install::minecraft::{game_root::GameRoot, scan::GameRoots},
+    instance::{InstanceSettings, launch::LaunchContext},
+    launch::memory::JvmPreset,
+};
 use rat_salsa::{SalsaAppContext, SalsaContext};
 use rat_theme4::{create_salsa_theme, theme::SalsaTheme};
 use rat_widget::menu::MenuLineState;
@@ -23,6 +31,8 @@ use crate::{data::account::AccountSetting, event::AppEvent};
 pub struct AppData {
     pub menu_selected: MenuLineState,
     pub download_data: download::DownloadData,
+    pub game_data: game::GameData,
+    pub account_data: account::AccountData,
 }
 
 impl AppData {}
@@ -34,6 +44,8 @@ impl Default for AppData {
         Self {
             menu_selected: menu_selected,
             download_data: download::DownloadData::default(),
+            game_data: game::GameData::default(),
+            account_data: account::AccountData::default(),
         }
     }
 }
@@ -45,8 +57,18 @@ pub struct Settings {
     pub mspt: u64,              // milliseconds per tick event
     pub download_thread: usize, // download threads
     pub theme_name: String,
+    pub minecraft_dir: PathBuf, // .minecraft directory
+    pub game_roots: GameRoots,   // .minecraft directories of other launchers
+    pub max_memory: Option<u32>, // MB, None for auto
+    pub memory_reserve: u32,     // MB left to the system when auto allocating
+    pub jvm_preset: JvmPreset,
+    // launch settings for instances that do not override them
+    pub instance_defaults: InstanceSettings,
     // account settings
     pub account_setting: AccountSetting,
+    // tokens are kept out of settings.toml
+    #[serde(skip)]
+    pub credential_store: CredentialStore,
     #[serde(skip)]
     pub theme: SalsaTheme,
     #[serde(skip)]
@@ -59,10 +81,12 @@ impl Settings {
             .context(format!("cannot create settings directory: {:?}", path))?;
         let file = path.join("settings.toml");
         let mut setting = Self::default();
+        let mut migrated = false;
 
         if file.is_file() {
             if let Ok(content) = fs::read_to_string(file) {
                 setting = toml::from_str(&content).context("failed to parse settings file")?;
+                migrated = setting.account_setting.migrate();
                 setting.theme = create_salsa_theme(&setting.theme_name);
                 info!(target:"MCTui", "Settings loaded.");
             } else {
@@ -71,6 +95,23 @@ impl Settings {
         } else {
             warn!(target:"MCTui", "Settings file not found, using default settings.");
         }
+
+        let passphrase = env::var(PASSPHRASE_ENV).ok();
+        let credentials = path.join(CREDENTIALS_FILE);
+        setting.credential_store = CredentialStore::open(&credentials, passphrase.as_deref());
+        let accounts = &mut setting.account_setting;
+        if let Err(e) = setting
+            .credential_store
+            .load_accounts(accounts.all_accounts.iter_mut().chain(accounts.current_account.as_mut()))
+        {
+            // 口令错误或文件损坏时锁定，避免保存时覆盖原来的凭据
+            warn!(target:"MCTui", "Failed to load account credentials: {:?}", e);
+            setting.credential_store = CredentialStore::Locked(credentials);
+        }
+        if migrated {
+            info!(target:"MCTui", "Accounts migrated.");
+            setting.save(path)?;
+        }
         return Ok(setting);
     }
 
@@ -84,6 +125,10 @@ impl Settings {
     pub fn save(&self, path: &Path) -> Result<()> {
         fs::create_dir_all(path)
             .context(format!("cannot create settings directory: {:?}", path))?;
+        let accounts = &self.account_setting;
+        self.credential_store
+            .save_accounts(accounts.all_accounts.iter().chain(accounts.current_account.as_ref()))
+            .context("failed to save account credentials")?;
         let file = path.join("settings.toml");
         let content = toml::to_string_pretty(self).context("failed to serialize settings")?;
         fs::write(&file, content)
@@ -98,6 +143,48 @@ impl Settings {
         };
         Ok(())
     }
+
+    /// 启动实例使用的全局设置，实例默认值中未设置的最大内存使用全局设置
+    pub fn launch_context(
+        &self,
+        local_yggdrasil: Option<LocalYggdrasil>,
+        pool: DownloadPool,
+    ) -> LaunchContext {
+        let mut defaults = self.instance_defaults.clone();
+        defaults.max_memory = defaults.max_memory.or(self.max_memory);
+        let java_cache = match ProjectDirs::from_path(PathBuf::from("mctui")) {
+            Some(proj_dirs) => proj_dirs.cache_dir().join(JAVA_CACHE_FILE),
+            None => PathBuf::from(JAVA_CACHE_FILE),
+        };
+        LaunchContext {
+            root: GameRoot::new(&self.minecraft_dir),
+            defaults,
+            memory_reserve: self.memory_reserve,
+            jvm_preset: self.jvm_preset,
+            java_cache,
+            threads: self.download_thread,
+            endpoints: self.account_setting.microsoft_endpoints.clone(),
+            local_yggdrasil,
+            pool,
+        }
+    }
+}
+
+/// 加密保存的账号凭据
+const CREDENTIALS_FILE: &str = "credentials.bin";
+
+/// Java 列表的缓存文件
+const JAVA_CACHE_FILE: &str = "java.json";
+
+/// 系统密钥环不可用时，用于加密凭据文件的口令，未设置时在界面中输入
+const PASSPHRASE_ENV: &str = "MCTUI_PASSPHRASE";
+
+/// mctui 自己的 .minecraft 目录
+fn default_minecraft_dir() -> PathBuf {
+    if let Some(proj_dirs) = ProjectDirs::from_path(PathBuf::from("mctui")) {
+        return proj_dirs.data_dir().join(".minecraft");
+    };
+    PathBuf::from(".minecraft")
 }
 
 impl SalsaContext<AppEvent, Error> for Settings {
@@ -116,7 +203,14 @@ impl Default for Settings {
             mspt: 10,
             download_thread: 8,
             theme_name: "Reds Shell".to_string(),
+            minecraft_dir: default_minecraft_dir(),
+            game_roots: GameRoots::discover(),
+            max_memory: None,
+            memory_reserve: 1024,
+            jvm_preset: JvmPreset::Default,
+            instance_defaults: InstanceSettings::default(),
             account_setting: AccountSetting::default(),
+            credential_store: CredentialStore::Memory,
             theme: create_salsa_theme("Reds Shell"),
             ctx: SalsaAppContext::default(),
         }
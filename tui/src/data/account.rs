@@ -1,10 +1,100 @@
-use mc_core::account::Account;
+use std::path::Path;
+
+use anyhow::Result;
+use mc_core::account::{
+    Account, AccountStatus,
+    local_yggdrasil::LocalYggdrasil,
+    microsoft_account::{MicrosoftEndpoints, MicrosoftLogin},
+    offline_account::OfflineAccount,
+    skin::PlayerHeads,
+    validator::AccountValidator,
+    yggdrasil_account::YggdrasilLogin,
+};
+use rat_widget::{
+    table::{TableState, selection::RowSelection},
+    text_input::TextInputState,
+    textarea::TextAreaState,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct AccountSetting {
     pub current_account: Option<Account>,
     pub all_accounts: Vec<Account>,
+    // microsoft login endpoints, can point to a local server for testing
+    #[serde(default)]
+    pub microsoft_endpoints: MicrosoftEndpoints,
+    /// 启动离线账号时使用本地皮肤服务器
+    #[serde(default)]
+    pub local_skin_server: bool,
+}
+
+impl AccountSetting {
+    /// 添加账号并设为当前账号，相同 uuid 的账号会被替换
+    pub fn add_account(&mut self, account: Account) {
+        self.all_accounts.retain(|a| !a.same_as(&account));
+        self.all_accounts.push(account.clone());
+        self.current_account = Some(account);
+    }
+
+    /// 迁移旧版本保存的账号，返回是否有修改
+    pub fn migrate(&mut self) -> bool {
+        let mut changed = false;
+        for account in self.all_accounts.iter_mut().chain(self.current_account.as_mut()) {
+            changed |= account.migrate();
+        }
+        changed
+    }
+
+    /// 更新已有账号的信息（例如刷新后的 token），不改变当前账号
+    pub fn update_account(&mut self, account: Account) {
+        for a in self.all_accounts.iter_mut().filter(|a| a.same_as(&account)) {
+            *a = account.clone();
+        }
+        if let Some(current) = &mut self.current_account
+            && current.same_as(&account)
+        {
+            *current = account;
+        }
+    }
+
+    /// 把列表中的账号设为当前账号
+    pub fn select_account(&mut self, index: usize) -> bool {
+        let Some(account) = self.all_accounts.get(index) else {
+            return false;
+        };
+        self.current_account = Some(account.clone());
+        true
+    }
+
+    /// 删除账号，删除的是当前账号时改用列表中的第一个
+    pub fn remove_account(&mut self, index: usize) -> Option<Account> {
+        if index >= self.all_accounts.len() {
+            return None;
+        }
+        let account = self.all_accounts.remove(index);
+        if self.is_current(&account) {
+            self.current_account = self.all_accounts.first().cloned();
+        }
+        Some(account)
+    }
+
+    /// 所有离线账号，提供给本地皮肤服务器
+    pub fn offline_accounts(&self) -> Vec<OfflineAccount> {
+        self.all_accounts
+            .iter()
+            .filter_map(|account| match account {
+                Account::Offline(account) => Some(account.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_current(&self, account: &Account) -> bool {
+        self.current_account
+            .as_ref()
+            .is_some_and(|current| current.same_as(account))
+    }
 }
 
 impl Default for AccountSetting {
@@ -12,6 +102,119 @@ impl Default for AccountSetting {
         Self {
             current_account: None,
             all_accounts: Vec::new(),
+            microsoft_endpoints: MicrosoftEndpoints::default(),
+            local_skin_server: false,
+        }
+    }
+}
+
+/// 账号页面当前的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccountMode {
+    #[default]
+    Browse,
+    AddOffline,
+    /// 修改离线账号的昵称
+    Rename(usize),
+    /// 第三方登录，数字为当前输入框：服务器、账号、密码
+    AddYggdrasil(usize),
+    /// 设置离线账号的皮肤文件
+    SetSkin(usize),
+    /// 设置正版登录使用的 Azure 应用客户端 ID
+    ClientId,
+    /// 输入凭据文件的口令
+    Passphrase,
+}
+
+#[derive(Default)]
+pub struct AccountData {
+    pub account_table: TableState<RowSelection>,
+    pub mode: AccountMode,
+    pub username_input: TextInputState,
+    pub server_input: TextInputState,
+    pub login_input: TextInputState,
+    pub password_input: TextInputState,
+    pub skin_input: TextInputState,
+    pub client_id_input: TextInputState,
+    pub passphrase_input: TextInputState,
+    /// 最近一次操作的结果或错误
+    pub message: Option<String>,
+    pub microsoft_login: MicrosoftLogin,
+    pub yggdrasil_login: YggdrasilLogin,
+    pub login_text: TextAreaState,
+    pub validator: AccountValidator,
+    /// 手动更新选中的账号
+    pub updater: AccountValidator,
+    /// 最近一次后台检查的结果
+    pub status: Option<AccountStatus>,
+    pub heads: PlayerHeads,
+    /// 本地皮肤服务器，开启时才运行
+    pub skin_server: Option<LocalYggdrasil>,
+}
+
+impl AccountData {
+    /// 当前账号的状态，后台检查发现需要重新登录时以检查结果为准
+    pub fn current_status(&self, account: &Account) -> AccountStatus {
+        match self.status {
+            Some(AccountStatus::NeedsLogin) => AccountStatus::NeedsLogin,
+            _ => account.get_status(),
+        }
+    }
+
+    /// 按设置启动或停止本地皮肤服务器，并更新提供的离线账号
+    pub fn sync_skin_server(&mut self, setting: &AccountSetting, key_path: &Path) -> Result<()> {
+        if !setting.local_skin_server {
+            self.skin_server = None;
+            return Ok(());
+        }
+        let accounts = setting.offline_accounts();
+        match &self.skin_server {
+            Some(server) => server.set_accounts(accounts),
+            None => self.skin_server = Some(LocalYggdrasil::start(key_path, accounts)?),
+        }
+        Ok(())
+    }
+
+    pub fn selected_account(&self) -> Option<usize> {
+        self.account_table.selected()
+    }
+
+    /// 进入输入模式并清空输入框
+    pub fn set_mode(&mut self, mode: AccountMode) {
+        self.mode = mode;
+        self.message = None;
+        match mode {
+            AccountMode::Browse => {}
+            AccountMode::AddOffline | AccountMode::Rename(_) => {
+                self.username_input.clear();
+            }
+            AccountMode::AddYggdrasil(_) => {
+                self.login_input.clear();
+                self.password_input.clear();
+            }
+            AccountMode::SetSkin(_) => {
+                self.skin_input.clear();
+            }
+            AccountMode::ClientId => {
+                self.client_id_input.clear();
+            }
+            AccountMode::Passphrase => {
+                self.passphrase_input.clear();
+            }
+        }
+    }
+
+    /// 当前获得焦点的输入框
+    pub fn focused_input(&mut self) -> Option<&mut TextInputState> {
+        match self.mode {
+            AccountMode::Browse => None,
+            AccountMode::AddOffline | AccountMode::Rename(_) => Some(&mut self.username_input),
+            AccountMode::AddYggdrasil(0) => Some(&mut self.server_input),
+            AccountMode::AddYggdrasil(1) => Some(&mut self.login_input),
+            AccountMode::AddYggdrasil(_) => Some(&mut self.password_input),
+            AccountMode::SetSkin(_) => Some(&mut self.skin_input),
+            AccountMode::ClientId => Some(&mut self.client_id_input),
+            AccountMode::Passphrase => Some(&mut self.passphrase_input),
         }
     }
 }
\ No newline at end of file
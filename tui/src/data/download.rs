@@ -1,22 +1,62 @@
-use mc_core::download::single_downloader::SingleDownloader;
-use rat_widget::{menu::MenuLineState, textarea::TextAreaState};
+use mc_core::{
+    download::{download_pool::DownloadPool, single_downloader::SingleDownloader},
+    install::{
+        minecraft::game_root::GameRoot,
+        verify::{Verifier, VerifyReport},
+    },
+};
+use rat_widget::{list::ListState, menu::MenuLineState, textarea::TextAreaState};
+use rust_i18n::t;
 
 pub struct DownloadData{
     pub download_selected: MenuLineState,
+    pub download_pool: DownloadPool,
     // minecraft
     pub minecraft_downloader: SingleDownloader,
     pub text_state: TextAreaState,
+    // verify
+    pub verifier: Verifier,
+    pub installed_versions: Vec<String>,
+    pub version_list: ListState,
+    pub verify_text: TextAreaState,
+    /// 最近一次完成的校验结果
+    pub verify_report: Option<VerifyReport>,
+    /// 结果文本中显示的校验进度
+    pub verify_progress: Option<(usize, usize)>,
 }
 
+impl DownloadData {
+    pub fn refresh_installed_versions(&mut self, root: &GameRoot) {
+        self.installed_versions = root.installed_versions();
+        if self.version_list.selected().is_none() && !self.installed_versions.is_empty() {
+            self.version_list.select(Some(0));
+        }
+    }
+
+    pub fn selected_version(&self) -> Option<&String> {
+        self.version_list
+            .selected()
+            .and_then(|i| self.installed_versions.get(i))
+    }
+}
 
 impl Default for DownloadData {
     fn default() -> Self {
         let mut download_selected = MenuLineState::default();
         download_selected.select(Some(0));
+        let mut verify_text = TextAreaState::default();
+        verify_text.set_text(t!("ui.download.verify_hint"));
         Self {
             download_selected,
+            download_pool: DownloadPool::new(8),
             minecraft_downloader: SingleDownloader::default(),
             text_state: TextAreaState::default(),
+            verifier: Verifier::default(),
+            installed_versions: Vec::new(),
+            version_list: ListState::default(),
+            verify_text,
+            verify_report: None,
+            verify_progress: None,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, path::PathBuf, sync::mpsc::Receiver};
+
+use log::warn;
+use mc_core::{
+    install::minecraft::game_root::GameRoot,
+    instance::{Instance, launch::InstanceLauncher, list_instances, task::InstanceTask},
+    launch::{
+        crash::CrashAnalysis,
+        log4j::LogLevel,
+        process::{GameEvent, GameInfo, GameProcessManager, GameState},
+    },
+};
+use rat_widget::{
+    list::ListState,
+    table::{TableState, selection::RowSelection},
+    text_input::TextInputState,
+    textarea::TextAreaState,
+};
+
+/// 实例列表的排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstanceSort {
+    #[default]
+    Name,
+    LastPlayed,
+    Playtime,
+    Version,
+}
+
+impl InstanceSort {
+    pub fn next(self) -> Self {
+        match self {
+            InstanceSort::Name => InstanceSort::LastPlayed,
+            InstanceSort::LastPlayed => InstanceSort::Playtime,
+            InstanceSort::Playtime => InstanceSort::Version,
+            InstanceSort::Version => InstanceSort::Name,
+        }
+    }
+
+    /// 语言文件中的名称
+    pub fn get_name(&self) -> &str {
+        match self {
+            InstanceSort::Name => "name",
+            InstanceSort::LastPlayed => "last_played",
+            InstanceSort::Playtime => "playtime",
+            InstanceSort::Version => "version",
+        }
+    }
+}
+
+/// 游戏页面当前的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    #[default]
+    Browse,
+    Search,
+    /// 新建实例，数字为当前输入框：名称、版本
+    NewInstance(usize),
+    /// 登记其它启动器的游戏目录
+    AddRoot,
+    /// 导入 MultiMC / Prism 实例
+    Import,
+    /// 导出选中的实例为 Prism 压缩包
+    Export,
+    /// 复制选中的实例，输入新实例的名称
+    Clone,
+    /// 备份选中的实例
+    Backup,
+    /// 从备份恢复
+    Restore,
+    /// 确认把已安装的版本写回官方启动器的配置
+    WriteBack,
+}
+
+pub struct GameData {
+    pub process_manager: GameProcessManager,
+    /// 进程的输出和退出事件，用于统计实例的游戏时长
+    pub game_events: Receiver<GameEvent>,
+    // running games
+    pub game_list: ListState,
+    pub console_state: TextAreaState,
+    /// 控制台只显示不低于该等级的日志
+    pub min_level: LogLevel,
+    /// 已经分析过的崩溃，key 为游戏 id
+    pub crash_analysis: HashMap<usize, CrashAnalysis>,
+    pub crash_state: TextAreaState,
+    pub instances: Vec<Instance>,
+    pub instance_table: TableState<RowSelection>,
+    pub mode: GameMode,
+    pub sort: InstanceSort,
+    /// 焦点在游戏进程列表上，否则在实例列表上
+    pub focus_games: bool,
+    pub search_input: TextInputState,
+    pub name_input: TextInputState,
+    pub version_input: TextInputState,
+    pub root_input: TextInputState,
+    pub import_input: TextInputState,
+    /// 导入时直接使用原来的游戏目录，不复制文件
+    pub import_link: bool,
+    pub export_input: TextInputState,
+    pub import_task: InstanceTask<Vec<Instance>>,
+    pub export_task: InstanceTask<PathBuf>,
+    pub clone_input: TextInputState,
+    /// 复制时不复制存档、日志和缓存
+    pub clone_minimal: bool,
+    pub backup_input: TextInputState,
+    pub restore_input: TextInputState,
+    /// 恢复到选中的实例，否则恢复为新实例
+    pub restore_existing: bool,
+    pub clone_task: InstanceTask<Instance>,
+    pub backup_task: InstanceTask<PathBuf>,
+    pub restore_task: InstanceTask<Instance>,
+    pub launcher: InstanceLauncher,
+    /// 实例 id -> 最近一次启动的游戏 id
+    pub instance_games: HashMap<String, usize>,
+    /// 最近一次操作的结果或错误
+    pub message: Option<String>,
+}
+
+impl GameData {
+    /// 当前选中的游戏 id
+    pub fn selected_game(&self) -> Option<usize> {
+        self.game_list.selected()
+    }
+
+    /// 选中游戏的崩溃分析，只在游戏退出后分析一次
+    pub fn selected_crash(&mut self) -> Option<&CrashAnalysis> {
+        let id = self.selected_game()?;
+        if !self.crash_analysis.contains_key(&id) {
+            let analysis = self.process_manager.analyze_crash(id)?;
+            self.crash_analysis.insert(id, analysis);
+        }
+        self.crash_analysis.get(&id)
+    }
+
+    /// 切换到下一个日志等级过滤
+    pub fn next_min_level(&mut self) {
+        self.min_level = match self.min_level {
+            LogLevel::Trace => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Info,
+            LogLevel::Info => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Error,
+            LogLevel::Error | LogLevel::Fatal => LogLevel::Trace,
+        };
+    }
+
+    /// 重新读取 root 中的实例
+    pub fn reload_instances(&mut self, root: &GameRoot) {
+        self.instances = list_instances(root);
+        self.instance_table.select(None);
+    }
+
+    /// 搜索和排序后显示的实例，返回在 instances 中的下标
+    pub fn visible_instances(&self) -> Vec<usize> {
+        let query = self.search_input.text().trim().to_lowercase();
+        let mut visible: Vec<usize> = self
+            .instances
+            .iter()
+            .enumerate()
+            .filter(|(_, instance)| query.is_empty() || matches_query(instance, &query))
+            .map(|(i, _)| i)
+            .collect();
+        let instances = &self.instances;
+        match self.sort {
+            InstanceSort::Name => {}
+            InstanceSort::LastPlayed => {
+                visible.sort_by_key(|&i| std::cmp::Reverse(instances[i].last_played))
+            }
+            InstanceSort::Playtime => {
+                visible.sort_by_key(|&i| std::cmp::Reverse(instances[i].playtime))
+            }
+            InstanceSort::Version => visible.sort_by(|&a, &b| {
+                compare_versions(&instances[b].version, &instances[a].version)
+            }),
+        }
+        visible
+    }
+
+    /// 当前选中的实例在 instances 中的下标
+    pub fn selected_instance(&self) -> Option<usize> {
+        let selected = self.instance_table.selected()?;
+        self.visible_instances().get(selected).copied()
+    }
+
+    /// 实例最近一次启动的游戏
+    pub fn instance_game(&self, instance: &Instance) -> Option<GameInfo> {
+        let id = self.instance_games.get(&instance.get_id())?;
+        self.process_manager.get(*id)
+    }
+
+    pub fn is_running(&self, instance: &Instance) -> bool {
+        self.instance_game(instance)
+            .is_some_and(|game| game.state == GameState::Running)
+    }
+
+    /// 处理游戏进程的事件，游戏退出时累加实例的游戏时长，返回是否有游戏退出
+    pub fn record_playtime(&mut self) -> bool {
+        let mut exited = false;
+        while let Ok(event) = self.game_events.try_recv() {
+            let GameEvent::Exited { id, duration, .. } = event else {
+                continue;
+            };
+            exited = true;
+            let Some(instance_id) = self
+                .instance_games
+                .iter()
+                .find(|(_, game)| **game == id)
+                .map(|(instance, _)| instance.clone())
+            else {
+                continue;
+            };
+            if let Some(instance) = self.instances.iter_mut().find(|i| i.get_id() == instance_id) {
+                instance.add_playtime(duration);
+                if let Err(e) = instance.save() {
+                    warn!(target: "MCTui", "Failed to save instance {}: {:?}", instance.name, e);
+                }
+            }
+        }
+        exited
+    }
+
+    /// 进入输入模式并清空输入框，搜索时保留上次的关键字
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        self.message = None;
+        match mode {
+            GameMode::NewInstance(_) => {
+                self.name_input.clear();
+                self.version_input.clear();
+            }
+            GameMode::AddRoot => {
+                self.root_input.clear();
+            }
+            GameMode::Import => {
+                self.import_input.clear();
+            }
+            GameMode::Restore => {
+                self.restore_input.clear();
+            }
+            GameMode::Browse
+            | GameMode::Search
+            | GameMode::Export
+            | GameMode::Clone
+            | GameMode::Backup
+            | GameMode::WriteBack => {}
+        }
+    }
+
+    /// 当前获得焦点的输入框
+    pub fn focused_input(&mut self) -> Option<&mut TextInputState> {
+        match self.mode {
+            GameMode::Browse | GameMode::WriteBack => None,
+            GameMode::Search => Some(&mut self.search_input),
+            GameMode::NewInstance(0) => Some(&mut self.name_input),
+            GameMode::NewInstance(_) => Some(&mut self.version_input),
+            GameMode::AddRoot => Some(&mut self.root_input),
+            GameMode::Import => Some(&mut self.import_input),
+            GameMode::Export => Some(&mut self.export_input),
+            GameMode::Clone => Some(&mut self.clone_input),
+            GameMode::Backup => Some(&mut self.backup_input),
+            GameMode::Restore => Some(&mut self.restore_input),
+        }
+    }
+}
+
+/// 名称、版本或加载器中包含关键字
+fn matches_query(instance: &Instance, query: &str) -> bool {
+    instance.name.to_lowercase().contains(query)
+        || instance.version.to_lowercase().contains(query)
+        || instance
+            .loader
+            .as_ref()
+            .is_some_and(|loader| loader.kind.get_name().to_lowercase().contains(query))
+}
+
+/// 按数字逐段比较版本号，例如 1.20.4 > 1.9
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty())
+            .filter_map(|part| part.parse().ok())
+            .collect()
+    };
+    parts(a).cmp(&parts(b)).then_with(|| a.cmp(b))
+}
+
+impl Default for GameData {
+    fn default() -> Self {
+        let process_manager = GameProcessManager::default();
+        let game_events = process_manager.subscribe();
+        Self {
+            process_manager,
+            game_events,
+            game_list: ListState::default(),
+            console_state: TextAreaState::default(),
+            min_level: LogLevel::Trace,
+            crash_analysis: HashMap::new(),
+            crash_state: TextAreaState::default(),
+            instances: Vec::new(),
+            instance_table: TableState::default(),
+            mode: GameMode::Browse,
+            sort: InstanceSort::Name,
+            focus_games: false,
+            search_input: TextInputState::default(),
+            name_input: TextInputState::default(),
+            version_input: TextInputState::default(),
+            root_input: TextInputState::default(),
+            import_input: TextInputState::default(),
+            import_link: false,
+            export_input: TextInputState::default(),
+            import_task: InstanceTask::default(),
+            export_task: InstanceTask::default(),
+            clone_input: TextInputState::default(),
+            clone_minimal: false,
+            backup_input: TextInputState::default(),
+            restore_input: TextInputState::default(),
+            restore_existing: false,
+            clone_task: InstanceTask::default(),
+            backup_task: InstanceTask::default(),
+            restore_task: InstanceTask::default(),
+            launcher: InstanceLauncher::default(),
+            instance_games: HashMap::new(),
+            message: None,
+        }
+    }
+}
@@ -4,20 +4,44 @@ pub mod event;
 pub mod log;
 pub mod ui;
 
-use ::log::{error, info};
+use std::path::{Path, PathBuf};
+
+use ::log::{error, info, warn};
 use anyhow::{Context, Result};
+use chrono::Local;
+use directories::{ProjectDirs, UserDirs};
+use mc_core::{
+    account::{
+        Account, AccountStatus, base::AccountBase, credential::CredentialStore,
+        offline_account::OfflineAccount, skin::validate_skin,
+    },
+    install::minecraft::{game_root::GameRoot, scan::scan_root, version_json::VersionJson},
+    instance::{
+        Instance, adopt_versions,
+        task::InstanceTask,
+        backup::{BackupFormat, CloneOptions, backup_instance, clone_instance, restore_backup},
+        launcher_profiles::{import_profiles, official_roots, write_back_versions},
+        multimc::{export_prism, import_instances},
+    },
+    statue::Status,
+};
 use rat_event::{crossterm::modifiers::CONTROL, ct_event, try_flow};
 use rat_menu::{event::MenuOutcome, menuline};
 use rat_salsa::{
     Control, RunConfig,
-    poll::{PollCrossterm, PollRendered},
+    poll::{PollCrossterm, PollRendered, PollTick},
     run_tui,
 };
+use rat_widget::{
+    list,
+    table::selection::rowselection,
+    text_input::{self, TextInputState},
+};
 use ratatui_core::{buffer::Buffer, layout::{Constraint, Layout, Rect}};
 use rust_i18n::t;
 
 use crate::{
-    data::{AppData, Settings},
+    data::{AppData, Settings, account::AccountMode, game::GameMode},
     event::AppEvent,
 };
 
@@ -36,16 +60,62 @@ pub fn run() -> Result<()> {
         errors,
         &mut app_settings,
         &mut app_data,
-        RunConfig::default()?.poll(PollCrossterm).poll(PollRendered),
+        RunConfig::default()?
+            .poll(PollCrossterm)
+            .poll(PollRendered)
+            .poll(PollTick::new(0, REFRESH_MS)),
     )?;
 
     Ok(())
 }
 
-fn init(_app_data: &mut AppData, _app_settings: &mut Settings) -> Result<()> {
+fn init(app_data: &mut AppData, app_settings: &mut Settings) -> Result<()> {
+    app_data
+        .download_data
+        .download_pool
+        .change_max_workers(app_settings.download_thread);
+    sync_skin_server(app_data, app_settings);
+    app_data
+        .game_data
+        .reload_instances(&GameRoot::new(&app_settings.minecraft_dir));
+    if let CredentialStore::Locked(path) = &app_settings.credential_store {
+        // 凭据文件需要口令，先到账号页面输入
+        let message = if path.is_file() {
+            t!("ui.account_manage.passphrase_required", path = path.display())
+        } else {
+            t!("ui.account_manage.passphrase_new")
+        };
+        app_data.menu_selected.select(Some(ACCOUNT_MENU));
+        let data = &mut app_data.account_data;
+        data.set_mode(AccountMode::Passphrase);
+        data.message = Some(message.to_string());
+    }
     Ok(())
 }
 
+/// 账号页面在菜单中的位置
+const ACCOUNT_MENU: usize = 2;
+
+/// 定时重绘的间隔，用于更新游戏状态和启动进度，单位毫秒
+const REFRESH_MS: u64 = 500;
+
+/// 本地皮肤服务器的签名密钥文件
+const LOCAL_KEY_FILE: &str = "local_yggdrasil.pem";
+
+/// 按设置启动或停止本地皮肤服务器，失败时关闭该设置
+fn sync_skin_server(app_data: &mut AppData, app_settings: &mut Settings) {
+    let Some(proj_dirs) = ProjectDirs::from_path(PathBuf::from("mctui")) else {
+        return;
+    };
+    let key_path = proj_dirs.config_dir().join(LOCAL_KEY_FILE);
+    let data = &mut app_data.account_data;
+    if let Err(e) = data.sync_skin_server(&app_settings.account_setting, &key_path) {
+        error!(target: "MCTui", "Failed to start local skin server: {:?}", e);
+        data.message = Some(t!("ui.account_manage.skin_server_failed", error = e.to_string()).to_string());
+        app_settings.account_setting.local_skin_server = false;
+    }
+}
+
 fn render(
     area: Rect,
     buf: &mut Buffer,
@@ -63,9 +133,15 @@ fn render(
     
     
     match app_data.menu_selected.selected() {
+        Some(0) => {
+            ui::game::game_render(l1[1], buf, app_data, app_settings);
+        }
         Some(1) => {
             ui::download::download_render(l1[1], buf, app_data, app_settings);
         }
+        Some(2) => {
+            ui::account::account_render(l1[1], buf, app_data, app_settings);
+        }
         _ => {}
     }
 
@@ -81,6 +157,18 @@ fn events(
 ) -> Result<Control<AppEvent>> {
     let r= match event {
         AppEvent::Event(event) => {
+            // 输入框优先处理，避免输入的字符触发菜单
+            if app_data.menu_selected.selected() == Some(2)
+                && app_data.account_data.mode != AccountMode::Browse
+            {
+                try_flow!(account_input_events(event, app_data, app_settings)?);
+            }
+            if app_data.menu_selected.selected() == Some(0)
+                && app_data.game_data.mode != GameMode::Browse
+            {
+                try_flow!(game_input_events(event, app_data, app_settings)?);
+            }
+
             // 菜单处理
             try_flow!(
                 match menuline::handle_events(&mut app_data.menu_selected, true, event){
@@ -88,24 +176,37 @@ fn events(
                         app_data.menu_selected.select(Some(v));
                         Control::Changed
                     }
-                    MenuOutcome::Activated(2) => Control::Quit,
+                    MenuOutcome::Activated(3) => Control::Quit,
                 _ => Control::Continue
                 }
             );
 
             match app_data.menu_selected.selected() {
-                Some(0) => {},
+                Some(0) => {
+                    try_flow!(game_events(event, app_data, app_settings)?);
+                },
                 Some(1) => {
                     // Download 菜单处理
                     try_flow!(
                         match menuline::handle_events(&mut app_data.download_data.download_selected, true, event){
                             MenuOutcome::Selected(v) => {
                                 app_data.download_data.download_selected.select(Some(v));
+                                if v == 3 {
+                                    let root = GameRoot::new(&app_settings.minecraft_dir);
+                                    app_data.download_data.refresh_installed_versions(&root);
+                                }
                                 Control::Changed
                             }
                         _ => Control::Continue
                         }
                     );
+
+                    if app_data.download_data.download_selected.selected() == Some(3) {
+                        try_flow!(verify_events(event, app_data, app_settings)?);
+                    }
+                },
+                Some(2) => {
+                    try_flow!(account_events(event, app_data, app_settings)?);
                 },
                 _ => {}
             }
@@ -119,12 +220,919 @@ fn events(
 
 
         },
-        _ => {Control::Continue}
+        AppEvent::Rendered => {
+            try_flow!(login_finished(app_data, app_settings)?);
+            try_flow!(launch_finished(app_data, app_settings)?);
+            try_flow!(instance_tasks_finished(app_data, app_settings)?);
+            try_flow!(verify_finished(app_data));
+            validate_account(app_data, app_settings)?
+        }
     };
 
     Ok(r)
 }
 
+/// 游戏页面的事件处理
+fn game_events(
+    event: &crossterm::event::Event,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.game_data;
+    if data.focus_games {
+        try_flow!(list::handle_events(&mut data.game_list, true, event));
+    } else {
+        try_flow!(rowselection::handle_events(&mut data.instance_table, true, event));
+    }
+
+    let r = match event {
+        ct_event!(keycode press Tab) => {
+            data.focus_games = !data.focus_games;
+            Control::Changed
+        }
+        ct_event!(keycode press Enter) if !data.focus_games => {
+            launch_selected(app_data, app_settings);
+            Control::Changed
+        }
+        ct_event!(key press '/') => {
+            data.set_mode(GameMode::Search);
+            Control::Changed
+        }
+        ct_event!(key press 's') => {
+            data.sort = data.sort.next();
+            data.instance_table.select(None);
+            Control::Changed
+        }
+        ct_event!(key press 'n') => {
+            data.set_mode(GameMode::NewInstance(0));
+            Control::Changed
+        }
+        ct_event!(key press 'r') => {
+            data.message = None;
+            data.reload_instances(&GameRoot::new(&app_settings.minecraft_dir));
+            Control::Changed
+        }
+        ct_event!(key press 'i') => {
+            scan_game_roots(app_data, app_settings)?;
+            Control::Changed
+        }
+        ct_event!(key press 'p') => {
+            data.set_mode(GameMode::AddRoot);
+            Control::Changed
+        }
+        ct_event!(key press 'o') => {
+            import_launcher_profiles(app_data, app_settings)?;
+            Control::Changed
+        }
+        ct_event!(key press 'w') => {
+            // 会修改官方启动器的配置，先确认
+            data.set_mode(GameMode::WriteBack);
+            data.message = Some(t!("ui.game.write_back_confirm").to_string());
+            Control::Changed
+        }
+        ct_event!(key press 'm') => {
+            data.set_mode(GameMode::Import);
+            Control::Changed
+        }
+        ct_event!(key press 'e') => {
+            let Some(index) = data.selected_instance() else {
+                return Ok(Control::Continue);
+            };
+            let path = export_dir(app_settings).join(format!("{}.zip", data.instances[index].get_id()));
+            data.set_mode(GameMode::Export);
+            data.export_input.set_text(path.to_string_lossy());
+            Control::Changed
+        }
+        ct_event!(key press 'c') => {
+            let Some(index) = data.selected_instance() else {
+                return Ok(Control::Continue);
+            };
+            let name = t!("ui.game.clone_default_name", name = data.instances[index].name).to_string();
+            data.set_mode(GameMode::Clone);
+            data.clone_input.set_text(name);
+            Control::Changed
+        }
+        ct_event!(key press 'b') => {
+            let Some(index) = data.selected_instance() else {
+                return Ok(Control::Continue);
+            };
+            let name = format!(
+                "{}-{}.{}",
+                data.instances[index].get_id(),
+                Local::now().format("%Y%m%d-%H%M"),
+                BackupFormat::Zip.extension()
+            );
+            data.set_mode(GameMode::Backup);
+            data.backup_input.set_text(export_dir(app_settings).join(name).to_string_lossy());
+            Control::Changed
+        }
+        ct_event!(key press 'u') => {
+            data.set_mode(GameMode::Restore);
+            Control::Changed
+        }
+        ct_event!(key press 't') => {
+            if let Some(id) = data.selected_game() {
+                data.process_manager.terminate(id)?;
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'k') => {
+            if let Some(id) = data.selected_game() {
+                data.process_manager.kill(id)?;
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'l') => {
+            data.next_min_level();
+            Control::Changed
+        }
+        _ => Control::Continue,
+    };
+    Ok(r)
+}
+
+/// 游戏页面输入框的事件处理
+fn game_input_events(
+    event: &crossterm::event::Event,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.game_data;
+    let r = match event {
+        ct_event!(keycode press Esc) => {
+            if data.mode == GameMode::Search {
+                data.search_input.clear();
+            }
+            data.set_mode(GameMode::Browse);
+            Control::Changed
+        }
+        ct_event!(keycode press Tab)
+        | ct_event!(keycode press Down)
+        | ct_event!(keycode press SHIFT-BackTab)
+        | ct_event!(keycode press Up) => {
+            match data.mode {
+                GameMode::NewInstance(field) => data.mode = GameMode::NewInstance(1 - field),
+                GameMode::Import => data.import_link = !data.import_link,
+                GameMode::Clone => data.clone_minimal = !data.clone_minimal,
+                GameMode::Restore => data.restore_existing = !data.restore_existing,
+                GameMode::Backup => toggle_backup_format(&mut data.backup_input),
+                _ => {}
+            }
+            Control::Changed
+        }
+        ct_event!(keycode press Enter) => {
+            submit_game_input(app_data, app_settings)?;
+            Control::Changed
+        }
+        _ => {
+            if let Some(input) = data.focused_input() {
+                try_flow!(text_input::handle_events(input, true, event));
+            }
+            // 其它按键不再交给菜单
+            match event {
+                crossterm::event::Event::Key(_) => Control::Unchanged,
+                _ => Control::Continue,
+            }
+        }
+    };
+    Ok(r)
+}
+
+/// 提交游戏页面的输入
+fn submit_game_input(app_data: &mut AppData, app_settings: &mut Settings) -> Result<()> {
+    let data = &mut app_data.game_data;
+    let root = GameRoot::new(&app_settings.minecraft_dir);
+    match data.mode {
+        GameMode::NewInstance(_) => {}
+        GameMode::AddRoot => {
+            let path = PathBuf::from(data.root_input.text().trim());
+            match app_settings.game_roots.add(&path) {
+                Ok(_) => {
+                    data.set_mode(GameMode::Browse);
+                    app_settings.save_default()?;
+                    scan_game_roots(app_data, app_settings)?;
+                }
+                Err(e) => data.message = Some(format!("{:#}", e)),
+            }
+            return Ok(());
+        }
+        GameMode::Import => {
+            if data.import_task.is_running() {
+                return Ok(());
+            }
+            let path = PathBuf::from(data.import_input.text().trim());
+            let link = data.import_link;
+            data.set_mode(GameMode::Browse);
+            data.import_task.start(move |progress| import_instances(&root, &path, link, progress));
+            return Ok(());
+        }
+        GameMode::Export => {
+            let Some(index) = data.selected_instance() else {
+                data.set_mode(GameMode::Browse);
+                return Ok(());
+            };
+            if data.export_task.is_running() {
+                return Ok(());
+            }
+            let instance = data.instances[index].clone();
+            let path = PathBuf::from(data.export_input.text().trim());
+            data.set_mode(GameMode::Browse);
+            data.export_task.start(move |progress| export_prism(&instance, &path, progress));
+            return Ok(());
+        }
+        GameMode::Clone => {
+            let Some(index) = data.selected_instance() else {
+                data.set_mode(GameMode::Browse);
+                return Ok(());
+            };
+            if data.clone_task.is_running() {
+                return Ok(());
+            }
+            let instance = data.instances[index].clone();
+            let name = data.clone_input.text().trim().to_string();
+            let options = if data.clone_minimal { CloneOptions::minimal() } else { CloneOptions::default() };
+            data.set_mode(GameMode::Browse);
+            data.clone_task.start(move |progress| clone_instance(&root, &instance, &name, options, progress));
+            return Ok(());
+        }
+        GameMode::Backup => {
+            let Some(index) = data.selected_instance() else {
+                data.set_mode(GameMode::Browse);
+                return Ok(());
+            };
+            if data.backup_task.is_running() {
+                return Ok(());
+            }
+            let instance = data.instances[index].clone();
+            let path = PathBuf::from(data.backup_input.text().trim());
+            data.set_mode(GameMode::Browse);
+            data.backup_task.start(move |progress| backup_instance(&root, &instance, &path, progress));
+            return Ok(());
+        }
+        GameMode::Restore => {
+            if data.restore_task.is_running() {
+                return Ok(());
+            }
+            let target = if data.restore_existing {
+                let Some(index) = data.selected_instance() else {
+                    data.message = Some(t!("ui.game.no_instance_selected").to_string());
+                    return Ok(());
+                };
+                let instance = data.instances[index].clone();
+                if data.is_running(&instance) {
+                    data.message = Some(t!("ui.game.already_running", name = instance.name).to_string());
+                    return Ok(());
+                }
+                Some(instance)
+            } else {
+                None
+            };
+            let path = PathBuf::from(data.restore_input.text().trim());
+            data.set_mode(GameMode::Browse);
+            data.restore_task.start(move |progress| restore_backup(&root, &path, target, progress));
+            return Ok(());
+        }
+        GameMode::WriteBack => {
+            data.set_mode(GameMode::Browse);
+            // 写入登记的游戏目录中官方启动器的配置
+            let targets = official_roots(&root, &app_settings.game_roots.roots);
+            if targets.is_empty() {
+                data.message = Some(t!("ui.game.no_official_launcher").to_string());
+                return Ok(());
+            }
+            let mut added = 0;
+            for target in &targets {
+                added += write_back_versions(&root, target).context("write launcher profiles")?;
+            }
+            data.message = Some(t!("ui.game.profiles_written", added = added).to_string());
+            return Ok(());
+        }
+        GameMode::Browse | GameMode::Search => {
+            data.set_mode(GameMode::Browse);
+            return Ok(());
+        }
+    }
+    let version_id = data.version_input.text().trim().to_string();
+    if !root.installed_versions().contains(&version_id) {
+        data.message = Some(t!("ui.game.version_not_installed", version = version_id).to_string());
+        return Ok(());
+    }
+    let name = match data.name_input.text().trim() {
+        "" => version_id.clone(),
+        name => name.to_string(),
+    };
+    // 安装了模组加载器的版本继承自原版，实例的版本记为原版
+    let parent = VersionJson::load(&root.version_json(&version_id))
+        .ok()
+        .and_then(|version| version.inherits_from);
+    let created = match parent {
+        Some(parent) => Instance::create(&root, &name, &parent, None).and_then(|mut instance| {
+            instance.version_id = Some(version_id);
+            instance.save()?;
+            Ok(instance)
+        }),
+        None => Instance::create(&root, &name, &version_id, None),
+    };
+    match created {
+        Ok(instance) => {
+            data.set_mode(GameMode::Browse);
+            data.search_input.clear();
+            data.reload_instances(&root);
+            let id = instance.get_id();
+            let position = data
+                .visible_instances()
+                .iter()
+                .position(|&i| data.instances[i].get_id() == id);
+            data.instance_table.select(position);
+            data.message = Some(t!("ui.game.instance_created", name = instance.name).to_string());
+        }
+        Err(e) => data.message = Some(format!("{:#}", e)),
+    }
+    Ok(())
+}
+
+/// 扫描启动器自己和登记的游戏目录，把还没有接管的版本加入实例列表
+fn scan_game_roots(app_data: &mut AppData, app_settings: &Settings) -> Result<()> {
+    let data = &mut app_data.game_data;
+    let root = GameRoot::new(&app_settings.minecraft_dir);
+    let mut versions = scan_root(&root);
+    versions.extend(app_settings.game_roots.scan());
+    let incomplete = versions.iter().filter(|version| !version.launchable).count();
+    let adopted = adopt_versions(&root, &versions).context("adopt installed versions")?;
+    data.reload_instances(&root);
+    data.message = Some(t!(
+        "ui.game.scan_result",
+        roots = app_settings.game_roots.roots.len() + 1,
+        total = versions.len(),
+        incomplete = incomplete,
+        added = adopted.len()
+    ).to_string());
+    Ok(())
+}
+
+/// 导出和备份的默认目录，没有下载目录时使用游戏目录
+fn export_dir(app_settings: &Settings) -> PathBuf {
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(|dir| dir.to_path_buf()))
+        .unwrap_or_else(|| app_settings.minecraft_dir.clone())
+}
+
+/// 在 zip 和 tar.zst 之间切换备份路径的扩展名
+fn toggle_backup_format(input: &mut TextInputState) {
+    let path = input.text().trim().to_string();
+    let (stem, format) = match BackupFormat::from_path(Path::new(&path)) {
+        Some(BackupFormat::Zip) => (&path[..path.len() - 4], BackupFormat::TarZst),
+        Some(BackupFormat::TarZst) => match path.strip_suffix(".tzst") {
+            Some(stem) => (stem, BackupFormat::Zip),
+            None => (&path[..path.len() - 8], BackupFormat::Zip),
+        },
+        None => (path.as_str(), BackupFormat::Zip),
+    };
+    input.set_text(format!("{}.{}", stem, format.extension()));
+}
+
+/// 导入启动器自己和登记的游戏目录中官方启动器的启动配置
+fn import_launcher_profiles(app_data: &mut AppData, app_settings: &Settings) -> Result<()> {
+    let data = &mut app_data.game_data;
+    let root = GameRoot::new(&app_settings.minecraft_dir);
+    let mut imported = import_profiles(&root, &root).context("import launcher profiles")?;
+    for path in &app_settings.game_roots.roots {
+        imported.extend(import_profiles(&root, &GameRoot::new(path)).context("import launcher profiles")?);
+    }
+    data.reload_instances(&root);
+    data.message = Some(t!("ui.game.profiles_imported", added = imported.len()).to_string());
+    Ok(())
+}
+
+/// 实例文件操作完成后显示结果，有新实例时刷新实例列表
+fn instance_tasks_finished(app_data: &mut AppData, app_settings: &Settings) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.game_data;
+    let mut messages = Vec::new();
+    let mut created = None;
+    match finished(&mut data.import_task) {
+        Some(Ok(instances)) => {
+            let names: Vec<&str> = instances.iter().map(|i| i.name.as_str()).collect();
+            messages.push(t!("ui.game.imported", names = names.join(", ")).to_string());
+            created = instances.first().map(|instance| instance.get_id());
+        }
+        Some(Err(e)) => messages.push(t!("ui.game.import_failed", error = e).to_string()),
+        None => {}
+    }
+    match finished(&mut data.export_task) {
+        Some(Ok(path)) => messages.push(t!("ui.game.exported", path = path.display()).to_string()),
+        Some(Err(e)) => messages.push(t!("ui.game.export_failed", error = e).to_string()),
+        None => {}
+    }
+    match finished(&mut data.clone_task) {
+        Some(Ok(instance)) => {
+            messages.push(t!("ui.game.cloned", name = instance.name).to_string());
+            created = Some(instance.get_id());
+        }
+        Some(Err(e)) => messages.push(t!("ui.game.clone_failed", error = e).to_string()),
+        None => {}
+    }
+    match finished(&mut data.backup_task) {
+        Some(Ok(path)) => messages.push(t!("ui.game.backed_up", path = path.display()).to_string()),
+        Some(Err(e)) => messages.push(t!("ui.game.backup_failed", error = e).to_string()),
+        None => {}
+    }
+    match finished(&mut data.restore_task) {
+        Some(Ok(instance)) => {
+            messages.push(t!("ui.game.restored", name = instance.name).to_string());
+            created = Some(instance.get_id());
+        }
+        Some(Err(e)) => messages.push(t!("ui.game.restore_failed", error = e).to_string()),
+        None => {}
+    }
+    if messages.is_empty() {
+        return Ok(Control::Continue);
+    }
+    if let Some(id) = created {
+        data.reload_instances(&GameRoot::new(&app_settings.minecraft_dir));
+        let position = data
+            .visible_instances()
+            .iter()
+            .position(|&i| data.instances[i].get_id() == id);
+        data.instance_table.select(position);
+    }
+    data.message = Some(messages.join("\n"));
+    Ok(Control::Changed)
+}
+
+/// 取走已经结束的任务的结果
+fn finished<T: Clone + Send + 'static>(task: &mut InstanceTask<T>) -> Option<Result<T, String>> {
+    let result = match task.get_state()? {
+        Status::Success(value) => Ok(value),
+        Status::Failed(e) => Err(e),
+        Status::Progress(_) => return None,
+    };
+    task.set_none();
+    Some(result)
+}
+
+/// 用当前账号启动选中的实例
+fn launch_selected(app_data: &mut AppData, app_settings: &Settings) {
+    let data = &mut app_data.game_data;
+    let Some(index) = data.selected_instance() else {
+        return;
+    };
+    if data.launcher.get_state().is_some_and(|state| state.is_progress()) {
+        return;
+    }
+    let instance = data.instances[index].clone();
+    if data.is_running(&instance) {
+        data.message = Some(t!("ui.game.already_running", name = instance.name).to_string());
+        return;
+    }
+    let Some(account) = app_settings.account_setting.current_account.clone() else {
+        data.message = Some(t!("ui.game.no_account").to_string());
+        return;
+    };
+    data.message = None;
+    let ctx = app_settings.launch_context(
+        app_data.account_data.skin_server.clone(),
+        app_data.download_data.download_pool.clone(),
+    );
+    app_data.game_data.launcher.start(instance, account, ctx);
+}
+
+/// 启动准备完成后启动游戏，同时记录已退出游戏的时长
+fn launch_finished(app_data: &mut AppData, app_settings: &mut Settings) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.game_data;
+    let exited = data.record_playtime();
+    let prepared = match data.launcher.get_state() {
+        Some(Status::Success(prepared)) => prepared,
+        Some(Status::Failed(e)) => {
+            data.message = Some(t!("ui.game.launch_failed", error = e).to_string());
+            // 登录失效时保存清空了凭据的账号，下次不再使用失效的令牌
+            let expired = data.launcher.take_expired_account();
+            data.launcher.set_none();
+            if let Some(account) = expired {
+                app_settings.account_setting.update_account(account);
+                app_settings.save_default()?;
+            }
+            return Ok(Control::Changed);
+        }
+        _ => return Ok(if exited { Control::Changed } else { Control::Continue }),
+    };
+    let instance_id = data.launcher.instance_id().unwrap_or_default().to_string();
+    data.launcher.set_none();
+    if prepared.account_changed {
+        app_settings.account_setting.update_account(prepared.account.clone());
+        app_settings.save_default()?;
+    }
+
+    let Some(instance) = data.instances.iter_mut().find(|i| i.get_id() == instance_id) else {
+        return Ok(Control::Changed);
+    };
+    match data.process_manager.launch(&instance.name, &prepared.command) {
+        Ok(game) => {
+            data.instance_games.insert(instance_id, game);
+            data.game_list.select(Some(game));
+            data.message = Some(t!("ui.game.launched", name = instance.name).to_string());
+            instance.mark_launched();
+            instance.save().context("save instance")?;
+        }
+        Err(e) => {
+            data.message = Some(t!("ui.game.launch_failed", error = format!("{:#}", e)).to_string());
+        }
+    }
+    Ok(Control::Changed)
+}
+
+/// 校验页面的事件处理
+fn verify_events(
+    event: &crossterm::event::Event,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.download_data;
+    try_flow!(list::handle_events(&mut data.version_list, true, event));
+
+    let r = match event {
+        ct_event!(keycode press Enter) => {
+            if let Some(version) = data.selected_version().cloned() {
+                let root = GameRoot::new(&app_settings.minecraft_dir);
+                data.verifier.start(root, version, app_settings.download_thread);
+                data.verify_report = None;
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'r') => {
+            // 修复后清空结果，避免重复添加同样的下载任务
+            if let Some(report) = data.verify_report.take() {
+                let root = GameRoot::new(&app_settings.minecraft_dir);
+                let text = t!("ui.download.repair_started", version = report.version_id);
+                data.verifier.repair(root, report, data.download_pool.clone(), app_settings.download_thread);
+                data.verify_text.set_text(text);
+            }
+            Control::Changed
+        }
+        _ => Control::Continue,
+    };
+    Ok(r)
+}
+
+/// 更新校验结果文本，只在状态变化时设置，避免重置滚动位置
+fn verify_finished(app_data: &mut AppData) -> Control<AppEvent> {
+    let data = &mut app_data.download_data;
+    let repair_text = match data.verifier.repair_state() {
+        Some(Status::Success(count)) => Some(t!("ui.download.repair_done", count = count).to_string()),
+        Some(Status::Failed(e)) => Some(t!("ui.download.repair_failed", error = e).to_string()),
+        _ => None,
+    };
+    if let Some(text) = repair_text {
+        data.verify_text.set_text(text);
+        return Control::Changed;
+    }
+    if let Some(progress) = data.verifier.progress() {
+        // 进度变化由定时刷新显示，返回 Changed 会导致不停重绘
+        if data.verify_progress != Some(progress) {
+            data.verify_progress = Some(progress);
+            let (checked, total) = progress;
+            data.verify_text.set_text(t!("ui.download.verifying", checked = checked, total = total));
+        }
+    }
+    let text = match data.verifier.get_state() {
+        Some(Status::Success(report)) => {
+            let text = ui::download::verify_summary(&report);
+            data.verify_report = Some(report);
+            text
+        }
+        Some(Status::Failed(e)) => e,
+        _ => return Control::Continue,
+    };
+    data.verifier.set_none();
+    data.verify_progress = None;
+    data.verify_text.set_text(text);
+    Control::Changed
+}
+
+/// 账号页面的事件处理
+fn account_events(
+    event: &crossterm::event::Event,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.account_data;
+    try_flow!(rowselection::handle_events(&mut data.account_table, true, event));
+
+    let logging_in = data.microsoft_login.get_state().is_some_and(|state| state.is_progress())
+        || data.yggdrasil_login.get_state().is_some_and(|state| state.is_progress());
+    let setting = &mut app_settings.account_setting;
+    let r = match event {
+        ct_event!(key press 'm') => {
+            if !logging_in {
+                data.yggdrasil_login.set_none();
+                if setting.microsoft_endpoints.client_id.is_empty() {
+                    // 没有客户端 ID 时先填写
+                    data.microsoft_login.set_none();
+                    data.set_mode(AccountMode::ClientId);
+                    data.message = Some(t!("ui.account_manage.client_id_required").to_string());
+                } else {
+                    data.microsoft_login.start(setting.microsoft_endpoints.clone());
+                }
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'c') if !logging_in => {
+            let client_id = setting.microsoft_endpoints.client_id.clone();
+            data.microsoft_login.set_none();
+            data.yggdrasil_login.set_none();
+            data.set_mode(AccountMode::ClientId);
+            data.client_id_input.set_text(client_id);
+            Control::Changed
+        }
+        ct_event!(key press 'p') if app_settings.credential_store.is_locked() => {
+            data.set_mode(AccountMode::Passphrase);
+            Control::Changed
+        }
+        ct_event!(key press 'o') if !logging_in => {
+            data.microsoft_login.set_none();
+            data.yggdrasil_login.set_none();
+            data.set_mode(AccountMode::AddOffline);
+            Control::Changed
+        }
+        ct_event!(key press 'y') if !logging_in => {
+            data.microsoft_login.set_none();
+            data.yggdrasil_login.set_none();
+            data.set_mode(AccountMode::AddYggdrasil(0));
+            if !data.server_input.is_empty() {
+                data.mode = AccountMode::AddYggdrasil(1);
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'n') => {
+            if let Some(index) = data.selected_account() {
+                match setting.all_accounts.get(index) {
+                    Some(Account::Offline(account)) => {
+                        let username = account.username.clone();
+                        data.set_mode(AccountMode::Rename(index));
+                        data.username_input.set_text(username);
+                    }
+                    Some(_) => data.message = Some(t!("ui.account_manage.rename_online").to_string()),
+                    None => {}
+                }
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'l') => {
+            setting.local_skin_server = !setting.local_skin_server;
+            data.message = None;
+            sync_skin_server(app_data, app_settings);
+            app_settings.save_default()?;
+            Control::Changed
+        }
+        ct_event!(key press 'k') => {
+            if let Some(index) = data.selected_account() {
+                match setting.all_accounts.get(index) {
+                    Some(Account::Offline(account)) => {
+                        let path = account.skin_path.clone();
+                        data.set_mode(AccountMode::SetSkin(index));
+                        if let Some(path) = path {
+                            data.skin_input.set_text(path.to_string_lossy());
+                        }
+                    }
+                    Some(_) => data.message = Some(t!("ui.account_manage.skin_online").to_string()),
+                    None => {}
+                }
+            }
+            Control::Changed
+        }
+        ct_event!(keycode press Enter) => {
+            if let Some(index) = data.selected_account()
+                && setting.select_account(index)
+            {
+                data.status = None;
+                app_settings.save_default()?;
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'u') => {
+            if let Some(account) = data.selected_account().and_then(|i| setting.all_accounts.get(i)) {
+                data.message = Some(t!("ui.account_manage.updating", name = account.get_username()).to_string());
+                data.updater.start(account.clone(), setting.microsoft_endpoints.clone());
+            }
+            Control::Changed
+        }
+        ct_event!(key press 'x') | ct_event!(keycode press Delete) => {
+            if let Some(index) = data.selected_account()
+                && let Some(account) = setting.remove_account(index)
+            {
+                // 外置登录账号删除时吊销令牌，失败也不影响删除
+                if let Account::Yggdrasil(account) = &account {
+                    let account = account.clone();
+                    std::thread::spawn(move || account.invalidate());
+                }
+                if index >= setting.all_accounts.len() {
+                    data.account_table.select(setting.all_accounts.len().checked_sub(1));
+                }
+                data.status = None;
+                data.message = Some(t!("ui.account_manage.deleted", name = account.get_username()).to_string());
+                sync_skin_server(app_data, app_settings);
+                app_settings.save_default()?;
+            }
+            Control::Changed
+        }
+        _ => Control::Continue,
+    };
+    Ok(r)
+}
+
+/// 账号页面输入框的事件处理
+fn account_input_events(
+    event: &crossterm::event::Event,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.account_data;
+    let r = match event {
+        ct_event!(keycode press Esc) => {
+            data.set_mode(AccountMode::Browse);
+            Control::Changed
+        }
+        ct_event!(keycode press Tab) | ct_event!(keycode press Down) => {
+            if let AccountMode::AddYggdrasil(field) = data.mode {
+                data.mode = AccountMode::AddYggdrasil((field + 1) % 3);
+            }
+            Control::Changed
+        }
+        ct_event!(keycode press SHIFT-BackTab) | ct_event!(keycode press Up) => {
+            if let AccountMode::AddYggdrasil(field) = data.mode {
+                data.mode = AccountMode::AddYggdrasil((field + 2) % 3);
+            }
+            Control::Changed
+        }
+        ct_event!(keycode press Enter) => {
+            submit_account_input(app_data, app_settings)?;
+            Control::Changed
+        }
+        _ => {
+            if let Some(input) = data.focused_input() {
+                try_flow!(text_input::handle_events(input, true, event));
+            }
+            // 其它按键不再交给菜单
+            match event {
+                crossterm::event::Event::Key(_) => Control::Unchanged,
+                _ => Control::Continue,
+            }
+        }
+    };
+    Ok(r)
+}
+
+/// 提交账号页面的输入
+fn submit_account_input(app_data: &mut AppData, app_settings: &mut Settings) -> Result<()> {
+    let data = &mut app_data.account_data;
+    let setting = &mut app_settings.account_setting;
+    let username = data.username_input.text().trim().to_string();
+    match data.mode {
+        AccountMode::Browse => return Ok(()),
+        AccountMode::AddOffline => match OfflineAccount::new(username) {
+            Ok(account) => {
+                setting.add_account(Account::Offline(account));
+                data.account_table.select(Some(setting.all_accounts.len() - 1));
+                data.status = None;
+            }
+            Err(e) => {
+                data.message = Some(e.to_string());
+                return Ok(());
+            }
+        },
+        AccountMode::Rename(index) => {
+            let Some(Account::Offline(account)) = setting.all_accounts.get(index) else {
+                data.set_mode(AccountMode::Browse);
+                return Ok(());
+            };
+            let was_current = setting.is_current(&Account::Offline(account.clone()));
+            let mut renamed = account.clone();
+            if let Err(e) = renamed.rename(username) {
+                data.message = Some(e.to_string());
+                return Ok(());
+            }
+            setting.all_accounts[index] = Account::Offline(renamed);
+            if was_current {
+                setting.select_account(index);
+            }
+        }
+        AccountMode::SetSkin(index) => {
+            let Some(Account::Offline(account)) = setting.all_accounts.get(index) else {
+                data.set_mode(AccountMode::Browse);
+                return Ok(());
+            };
+            let path = data.skin_input.text().trim().to_string();
+            let mut account = account.clone();
+            account.skin_path = if path.is_empty() {
+                None
+            } else {
+                let result = std::fs::read(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|bytes| validate_skin(&bytes));
+                if let Err(e) = result {
+                    data.message = Some(e.to_string());
+                    return Ok(());
+                }
+                Some(PathBuf::from(path))
+            };
+            let account = Account::Offline(account);
+            data.heads.reload(&account);
+            setting.update_account(account);
+        }
+        AccountMode::ClientId => {
+            setting.microsoft_endpoints.client_id = data.client_id_input.text().trim().to_string();
+            data.set_mode(AccountMode::Browse);
+            data.message = Some(t!("ui.account_manage.client_id_saved").to_string());
+            return app_settings.save_default();
+        }
+        AccountMode::Passphrase => {
+            let passphrase = data.passphrase_input.text().to_string();
+            data.passphrase_input.clear();
+            let accounts = setting.all_accounts.iter_mut().chain(setting.current_account.as_mut());
+            if let Err(e) = app_settings.credential_store.unlock(&passphrase, accounts) {
+                warn!(target: "MCTui", "Failed to unlock credentials: {:?}", e);
+                data.message = Some(t!("ui.account_manage.passphrase_wrong").to_string());
+                return Ok(());
+            }
+            data.set_mode(AccountMode::Browse);
+            data.status = None;
+            data.message = Some(t!("ui.account_manage.passphrase_unlocked").to_string());
+            return app_settings.save_default();
+        }
+        AccountMode::AddYggdrasil(field) => {
+            let server = data.server_input.text().trim().to_string();
+            let login = data.login_input.text().trim().to_string();
+            let password = data.password_input.text().to_string();
+            if server.is_empty() || login.is_empty() || password.is_empty() {
+                data.mode = AccountMode::AddYggdrasil((field + 1) % 3);
+                return Ok(());
+            }
+            data.yggdrasil_login.start(server, login, password);
+            data.password_input.clear();
+            data.set_mode(AccountMode::Browse);
+            return Ok(());
+        }
+    }
+    data.set_mode(AccountMode::Browse);
+    sync_skin_server(app_data, app_settings);
+    app_settings.save_default()
+}
+
+/// 登录成功后保存账号
+fn login_finished(app_data: &mut AppData, app_settings: &mut Settings) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.account_data;
+    let account = if let Some(Status::Success(account)) = data.microsoft_login.get_state() {
+        data.microsoft_login.set_none();
+        Account::Microsoft(account)
+    } else if let Some(Status::Success(account)) = data.yggdrasil_login.get_state() {
+        data.yggdrasil_login.set_none();
+        Account::Yggdrasil(account)
+    } else {
+        return Ok(Control::Continue);
+    };
+    data.status = None;
+    data.message = Some(t!("ui.account_manage.login_success", name = account.get_username()).to_string());
+    let setting = &mut app_settings.account_setting;
+    setting.add_account(account);
+    data.account_table.select(Some(setting.all_accounts.len() - 1));
+    app_settings.save_default()?;
+    Ok(Control::Changed)
+}
+
+/// 后台检查当前账号，刷新过 token 时保存
+fn validate_account(app_data: &mut AppData, app_settings: &mut Settings) -> Result<Control<AppEvent>> {
+    let data = &mut app_data.account_data;
+    if let Some(validation) = data.updater.take_result() {
+        let name = validation.account.get_username().to_string();
+        if app_settings.account_setting.is_current(&validation.account) {
+            data.status = Some(validation.status);
+        }
+        data.message = Some(match validation.status {
+            AccountStatus::NeedsLogin => t!("ui.account_manage.update_failed", name = name),
+            _ => t!("ui.account_manage.updated", name = name),
+        }.to_string());
+        if validation.changed {
+            app_settings.account_setting.update_account(validation.account);
+            app_settings.save_default()?;
+        }
+        return Ok(Control::Changed);
+    }
+    if let Some(validation) = data.validator.take_result() {
+        data.status = Some(validation.status);
+        if validation.changed {
+            app_settings.account_setting.update_account(validation.account);
+            app_settings.save_default()?;
+        }
+        return Ok(Control::Changed);
+    }
+    if data.validator.needs_check()
+        && let Some(account) = app_settings.account_setting.current_account.clone()
+    {
+        let endpoints = app_settings.account_setting.microsoft_endpoints.clone();
+        data.validator.start(account, endpoints);
+    }
+    Ok(Control::Continue)
+}
+
 fn errors(
     err: anyhow::Error,
     _app_data: &mut AppData,
@@ -1,3 +1,6 @@
 pub mod menu;
+pub mod account;
 pub mod download;
-pub mod stacked;
\ No newline at end of file
+pub mod game;
+pub mod stacked;
+pub mod player_head;
\ No newline at end of file
@@ -0,0 +1,275 @@
+use mc_core::{
+    account::{
+        Account, AccountStatus, base::AccountBase, microsoft_account::LoginStep,
+        skin::FaceState,
+    },
+    statue::Status,
+};
+use rat_theme4::{StyleName, WidgetStyle};
+use rat_widget::{
+    scrolled::{Scroll, ScrollbarPolicy},
+    table::{Table, selection::RowSelection, textdata::Row},
+    text_input::{TextInput, TextInputState},
+    textarea::TextArea,
+};
+use ratatui_core::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::Style};
+use ratatui_core::widgets::{StatefulWidget, Widget};
+use ratatui_widgets::{block::Block, paragraph::{Paragraph, Wrap}};
+use rust_i18n::t;
+
+use crate::{
+    data::{AppData, Settings, account::{AccountData, AccountMode}},
+    ui::player_head::PlayerHead,
+};
+
+pub fn account_render(
+    area: Rect,
+    buf: &mut Buffer,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) {
+    let l1 = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(48),
+    ]).split(area);
+
+    let data = &mut app_data.account_data;
+    let accounts = &app_settings.account_setting.all_accounts;
+    if data.account_table.selected().is_none() && !accounts.is_empty() {
+        data.account_table.select(Some(0));
+    }
+    let rows = accounts.iter().map(|account| {
+        let is_current = app_settings.account_setting.is_current(account);
+        let status = if is_current { data.current_status(account) } else { account.get_status() };
+        Row::new([
+            if is_current { "*" } else { "" }.to_string(),
+            account_detail(account),
+            t!(format!("ui.stacked.{}", account.get_type())).to_string(),
+            status_name(status),
+        ])
+    });
+    Table::<RowSelection>::new()
+        .rows(rows)
+        .widths([
+            Constraint::Length(2),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ])
+        .header(Row::new([
+            String::new(),
+            t!("ui.account_manage.account_name").to_string(),
+            t!("ui.account_manage.account_type").to_string(),
+            t!("ui.account_manage.account_status").to_string(),
+        ]))
+        .styles(app_settings.theme.style(WidgetStyle::TABLE))
+        .block(Block::bordered()
+            .title(t!("ui.account_manage.account_table"))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l1[0], buf, &mut data.account_table);
+
+    let inputs = match data.mode {
+        AccountMode::Browse => 0,
+        AccountMode::AddOffline
+        | AccountMode::Rename(_)
+        | AccountMode::SetSkin(_)
+        | AccountMode::ClientId
+        | AccountMode::Passphrase => 3,
+        AccountMode::AddYggdrasil(_) => 9,
+    };
+    let l2 = Layout::vertical([
+        Constraint::Length(10),
+        Constraint::Length(inputs),
+        Constraint::Fill(1),
+    ]).split(l1[1]);
+    let selected = data.selected_account().and_then(|i| accounts.get(i));
+    render_preview(l2[0], buf, data, selected, app_settings);
+    render_inputs(l2[1], buf, data, app_settings);
+
+    let text = side_text(data, app_settings);
+    data.login_text.set_text(text);
+    TextArea::new()
+        .style(app_settings.theme.style(WidgetStyle::TEXTVIEW))
+        .vscroll(Scroll::new().policy(ScrollbarPolicy::Collapse))
+        .block(Block::bordered()
+            .title(t!("ui.account_manage.account_manage"))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        ).text_wrap(rat_widget::textarea::TextWrap::Word(0))
+        .render(l2[2], buf, &mut data.login_text);
+}
+
+/// 选中账号的头像预览
+fn render_preview(
+    area: Rect,
+    buf: &mut Buffer,
+    data: &mut AccountData,
+    account: Option<&Account>,
+    app_settings: &Settings,
+) {
+    let block = Block::bordered()
+        .title(t!("ui.account_manage.skin_preview"))
+        .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+        .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG));
+    let inner = block.inner(area);
+    block.render(area, buf);
+    let Some(account) = account else {
+        return;
+    };
+    let endpoints = &app_settings.account_setting.microsoft_endpoints;
+    let text = match data.heads.get_state(account, endpoints) {
+        FaceState::Success(Some(face)) => {
+            PlayerHead::new(face).render(inner, buf);
+            return;
+        }
+        FaceState::Success(None) => t!("ui.account_manage.no_skin").to_string(),
+        FaceState::Progress(()) => t!("ui.account_manage.loading_skin").to_string(),
+        FaceState::Failed(e) => t!("ui.account_manage.skin_failed", error = e).to_string(),
+    };
+    Paragraph::new(text)
+        .style(app_settings.theme.style_style(Style::CONTAINER_BASE))
+        .wrap(Wrap { trim: true })
+        .render(inner, buf);
+}
+
+fn render_inputs(area: Rect, buf: &mut Buffer, data: &mut AccountData, app_settings: &Settings) {
+    match data.mode {
+        AccountMode::Browse => {}
+        AccountMode::AddOffline | AccountMode::Rename(_) => {
+            let title = match data.mode {
+                AccountMode::AddOffline => t!("ui.account_manage.add_offline_account"),
+                _ => t!("ui.account_manage.rename_account"),
+            };
+            data.username_input.focus.set(true);
+            text_input(title.to_string(), false, app_settings)
+                .render(area, buf, &mut data.username_input);
+        }
+        AccountMode::SetSkin(_) => {
+            data.skin_input.focus.set(true);
+            text_input(t!("ui.account_manage.skin_path").to_string(), false, app_settings)
+                .render(area, buf, &mut data.skin_input);
+        }
+        AccountMode::ClientId => {
+            data.client_id_input.focus.set(true);
+            text_input(t!("ui.account_manage.client_id").to_string(), false, app_settings)
+                .render(area, buf, &mut data.client_id_input);
+        }
+        AccountMode::Passphrase => {
+            data.passphrase_input.focus.set(true);
+            text_input(t!("ui.account_manage.passphrase").to_string(), true, app_settings)
+                .render(area, buf, &mut data.passphrase_input);
+        }
+        AccountMode::AddYggdrasil(field) => {
+            let l1 = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ]).split(area);
+            let fields: [(&mut TextInputState, String, bool); 3] = [
+                (&mut data.server_input, t!("ui.account_manage.yggdrasil_server").to_string(), false),
+                (&mut data.login_input, t!("ui.account_manage.yggdrasil_login").to_string(), false),
+                (&mut data.password_input, t!("ui.account_manage.password").to_string(), true),
+            ];
+            for (i, (state, title, passwd)) in fields.into_iter().enumerate() {
+                state.focus.set(i == field);
+                text_input(title, passwd, app_settings).render(l1[i], buf, state);
+            }
+        }
+    }
+}
+
+fn text_input(title: String, passwd: bool, app_settings: &Settings) -> TextInput<'static> {
+    let input = TextInput::new()
+        .styles(app_settings.theme.style(WidgetStyle::TEXT))
+        .block(Block::bordered()
+            .title(title)
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        );
+    if passwd { input.passwd() } else { input }
+}
+
+/// 右侧显示登录进度、操作结果或按键说明
+fn side_text(data: &mut AccountData, app_settings: &Settings) -> String {
+    if let Some(state) = data.microsoft_login.get_state() {
+        return match state {
+            Status::Progress(step) => login_step(&step),
+            Status::Success(account) => {
+                t!("ui.account_manage.login_success", name = account.username).to_string()
+            }
+            Status::Failed(e) => t!("ui.account_manage.login_failed", error = e).to_string(),
+        };
+    }
+    if let Some(state) = data.yggdrasil_login.get_state() {
+        return match state {
+            Status::Progress(()) => t!("ui.account_manage.logging_in").to_string(),
+            Status::Success(account) => {
+                t!("ui.account_manage.login_success", name = account.username).to_string()
+            }
+            Status::Failed(e) => t!("ui.account_manage.yggdrasil_failed", error = e).to_string(),
+        };
+    }
+    let mut text = String::new();
+    if let Some(message) = &data.message {
+        text.push_str(message);
+        text.push_str("\n\n");
+    }
+    if data.mode != AccountMode::Browse {
+        text.push_str(&t!("ui.account_manage.input_hint"));
+        return text;
+    }
+    let needs_login = app_settings
+        .account_setting
+        .current_account
+        .as_ref()
+        .is_some_and(|account| data.current_status(account) == AccountStatus::NeedsLogin);
+    if needs_login {
+        text.push_str(&t!("ui.account_manage.relogin_hint"));
+        text.push_str("\n\n");
+    }
+    if app_settings.credential_store.is_locked() {
+        text.push_str(&t!("ui.account_manage.credentials_locked"));
+        text.push_str("\n\n");
+    }
+    if let Some(server) = &data.skin_server {
+        text.push_str(&t!("ui.account_manage.skin_server_running", url = server.api_root()));
+        text.push_str("\n\n");
+    }
+    text.push_str(&t!("ui.account_manage.help"));
+    text
+}
+
+fn status_name(status: AccountStatus) -> String {
+    match status {
+        AccountStatus::Valid => t!("ui.stacked.valid").to_string(),
+        AccountStatus::Expiring => t!("ui.stacked.expiring").to_string(),
+        AccountStatus::NeedsLogin => t!("ui.stacked.needs_login").to_string(),
+    }
+}
+
+fn login_step(step: &LoginStep) -> String {
+    match step {
+        LoginStep::RequestingCode => t!("ui.account_manage.requesting_code").to_string(),
+        LoginStep::WaitingForUser(code) => t!(
+            "ui.account_manage.device_code",
+            url = code.verification_uri,
+            code = code.user_code
+        )
+        .to_string(),
+        LoginStep::XboxLive => t!("ui.account_manage.step_xbox").to_string(),
+        LoginStep::Xsts => t!("ui.account_manage.step_xsts").to_string(),
+        LoginStep::Minecraft => t!("ui.account_manage.step_minecraft").to_string(),
+        LoginStep::Entitlement => t!("ui.account_manage.step_entitlement").to_string(),
+        LoginStep::Profile => t!("ui.account_manage.step_profile").to_string(),
+    }
+}
+
+/// 账号在表格中的描述，外置登录附带服务器名称
+fn account_detail(account: &Account) -> String {
+    match account {
+        Account::Yggdrasil(account) => format!("{} ({})", account.username, account.server_name),
+        _ => account.get_username().to_string(),
+    }
+}
@@ -1,8 +1,14 @@
 use std::borrow::Cow;
 
-use mc_core::{install::minecraft::get_all_minecraft_versions, statue::Status};
+use mc_core::{
+    install::{
+        minecraft::get_all_minecraft_versions,
+        verify::{FileProblem, GameFileKind, VerifyReport},
+    },
+    statue::Status,
+};
 use rat_theme4::{StyleName, WidgetStyle};
-use rat_widget::{menu::{MenuLine, MenuLineState}, scrolled::{Scroll, ScrollbarPolicy}, textarea::TextArea};
+use rat_widget::{list::List, menu::{MenuLine, MenuLineState}, scrolled::{Scroll, ScrollbarPolicy}, textarea::TextArea};
 use ratatui_core::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::Style};
 use ratatui_core::widgets::StatefulWidget;
 use ratatui_widgets::block::Block;
@@ -25,6 +31,7 @@ pub fn download_render(
         .item_parsed(menu_item(t!("ui.download.minecraft"), 0, &app_data.download_data.download_selected).as_str())
         .item_parsed(menu_item(t!("ui.download.modpack"), 1, &app_data.download_data.download_selected).as_str())
         .item_parsed(menu_item(t!("ui.download.mod"), 2, &app_data.download_data.download_selected).as_str())
+        .item_parsed(menu_item(t!("ui.download.verify"), 3, &app_data.download_data.download_selected).as_str())
         .styles(app_settings.theme.style(WidgetStyle::MENU))
         .focus_style(app_settings.theme.p.primary(2))
         .render(l1[0], buf, &mut app_data.download_data.download_selected);  
@@ -39,6 +46,9 @@ pub fn download_render(
         // Some(2) => {
         //     render_mod_download_area(l1[1], buf, app_data, app_settings);
         // }
+        Some(3) => {
+            render_verify_area(l1[1], buf, app_data, app_settings);
+        }
         _ => {}
     }
 }
@@ -96,4 +106,92 @@ fn render_minecraft_download_area(
             .render(area, buf, &mut app_data.download_data.text_state);
         }
     }
-}
\ No newline at end of file
+}
+
+fn render_verify_area(
+    area: Rect,
+    buf: &mut Buffer,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) {
+    let l1 = Layout::horizontal([
+        Constraint::Length(30),
+        Constraint::Fill(1),
+    ]).split(area);
+
+    let data = &mut app_data.download_data;
+    List::new(data.installed_versions.clone())
+        .styles(app_settings.theme.style(WidgetStyle::LIST))
+        .block(Block::bordered()
+            .title(t!("ui.download.installed_versions"))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l1[0], buf, &mut data.version_list);
+
+    TextArea::new()
+        .style(app_settings.theme.style(WidgetStyle::TEXTVIEW))
+        .vscroll(Scroll::new().policy(ScrollbarPolicy::Collapse))
+        .block(Block::bordered()
+            .title(t!("ui.download.verify_result"))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        ).text_wrap(rat_widget::textarea::TextWrap::Word(0))
+        .render(l1[1], buf, &mut data.verify_text);
+}
+
+/// 校验结果文本
+pub fn verify_summary(report: &VerifyReport) -> String {
+    let mut text = format!(
+        "{}\n{}\n",
+        t!("ui.download.verify_version", version = report.version_id),
+        t!("ui.download.verify_checked", checked = report.checked, missing = report.missing(), corrupt = report.corrupt()),
+    );
+    if report.is_ok() {
+        text.push_str(&t!("ui.download.verify_ok"));
+        return text;
+    }
+    for kind in [
+        GameFileKind::Client,
+        GameFileKind::Library,
+        GameFileKind::Native,
+        GameFileKind::AssetIndex,
+        GameFileKind::Asset,
+        GameFileKind::LogConfig,
+    ] {
+        let count = report.count(kind);
+        if count > 0 {
+            text.push_str(&format!("  {}: {}\n", file_kind_name(kind), count));
+        }
+    }
+    if report.assets_skipped {
+        text.push_str(&t!("ui.download.verify_assets_skipped"));
+        text.push('\n');
+    }
+    text.push('\n');
+    for (file, problem) in &report.problems {
+        let problem = match problem {
+            FileProblem::Missing => t!("ui.download.file_missing").to_string(),
+            FileProblem::SizeMismatch { expected, actual } => {
+                t!("ui.download.file_size_mismatch", expected = expected, actual = actual).to_string()
+            }
+            FileProblem::HashMismatch { .. } => t!("ui.download.file_hash_mismatch").to_string(),
+            FileProblem::Unreadable(e) => e.clone(),
+        };
+        text.push_str(&format!("[{}] {} {}\n", file_kind_name(file.kind), file.path.display(), problem));
+    }
+    text.push('\n');
+    text.push_str(&t!("ui.download.repair_hint"));
+    text
+}
+
+fn file_kind_name(kind: GameFileKind) -> Cow<'static, str> {
+    match kind {
+        GameFileKind::Client => t!("ui.download.kind_client"),
+        GameFileKind::Library => t!("ui.download.kind_library"),
+        GameFileKind::Native => t!("ui.download.kind_native"),
+        GameFileKind::AssetIndex => t!("ui.download.kind_asset_index"),
+        GameFileKind::Asset => t!("ui.download.kind_asset"),
+        GameFileKind::LogConfig => t!("ui.download.kind_log_config"),
+    }
+}
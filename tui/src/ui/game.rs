@@ -0,0 +1,417 @@
+use std::time::Duration;
+
+use chrono::{Local, TimeZone};
+use mc_core::{
+    instance::{Instance, launch::LaunchStep},
+    launch::{
+        crash::{CrashAnalysis, CrashCause},
+        log4j::LogRecord,
+        process::{GameInfo, GameState},
+    },
+    statue::Status,
+};
+use rat_theme4::{StyleName, WidgetStyle};
+use rat_widget::{
+    list::List,
+    scrolled::{Scroll, ScrollbarPolicy},
+    table::{Table, selection::RowSelection, textdata::Row},
+    text_input::{TextInput, TextInputState},
+    textarea::TextArea,
+};
+use ratatui_core::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::Style};
+use ratatui_core::widgets::{StatefulWidget, Widget};
+use ratatui_widgets::{block::Block, paragraph::{Paragraph, Wrap}};
+use rust_i18n::t;
+
+use crate::data::{AppData, Settings, game::{GameData, GameMode}};
+
+pub fn game_render(
+    area: Rect,
+    buf: &mut Buffer,
+    app_data: &mut AppData,
+    app_settings: &mut Settings,
+) {
+    let l1 = Layout::horizontal([
+        Constraint::Fill(3),
+        Constraint::Fill(2),
+    ]).split(area);
+
+    instances_render(l1[0], buf, &mut app_data.game_data, app_settings);
+
+    let data = &mut app_data.game_data;
+    let games = data.process_manager.list();
+    if data.game_list.selected().is_none() && !games.is_empty() {
+        data.game_list.select(Some(games.len() - 1));
+    }
+
+    let diagnosis = data.selected_crash().map(crash_diagnosis);
+    let l2 = Layout::vertical([
+        Constraint::Length(8),
+        Constraint::Length(if diagnosis.is_some() { 12 } else { 0 }),
+        Constraint::Fill(1),
+    ]).split(l1[1]);
+
+    data.game_list.focus.set(data.focus_games);
+    List::new(games.iter().map(game_item))
+        .styles(app_settings.theme.style(WidgetStyle::LIST))
+        .block(Block::bordered()
+            .title(t!("ui.game.running_games"))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l2[0], buf, &mut data.game_list);
+
+    if let Some(diagnosis) = diagnosis {
+        data.crash_state.set_text(diagnosis);
+        TextArea::new()
+            .style(app_settings.theme.style(WidgetStyle::TEXTVIEW))
+            .vscroll(Scroll::new().policy(ScrollbarPolicy::Collapse))
+            .block(Block::bordered()
+                .title(t!("ui.game.crash_title"))
+                .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+                .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+            ).text_wrap(rat_widget::textarea::TextWrap::Word(0))
+            .render(l2[1], buf, &mut data.crash_state);
+    }
+
+    let console = match data.selected_game() {
+        Some(id) => data
+            .process_manager
+            .records(id)
+            .iter()
+            .filter(|record| record.level >= data.min_level)
+            .map(format_record)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => t!("ui.game.no_game").to_string(),
+    };
+    data.console_state.set_text(console);
+    TextArea::new()
+        .style(app_settings.theme.style(WidgetStyle::TEXTVIEW))
+        .vscroll(Scroll::new().policy(ScrollbarPolicy::Collapse))
+        .block(Block::bordered()
+            .title(t!("ui.game.console", level = data.min_level))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l2[2], buf, &mut data.console_state);
+}
+
+/// 实例列表、搜索框和启动状态
+fn instances_render(area: Rect, buf: &mut Buffer, data: &mut GameData, app_settings: &Settings) {
+    let inputs = match data.mode {
+        GameMode::NewInstance(_) => 6,
+        GameMode::Search
+        | GameMode::AddRoot
+        | GameMode::Import
+        | GameMode::Export
+        | GameMode::Clone
+        | GameMode::Backup
+        | GameMode::Restore => 3,
+        GameMode::Browse if !data.search_input.is_empty() => 3,
+        GameMode::Browse | GameMode::WriteBack => 0,
+    };
+    let l1 = Layout::vertical([
+        Constraint::Length(inputs),
+        Constraint::Fill(1),
+        Constraint::Length(5),
+    ]).split(area);
+
+    match data.mode {
+        GameMode::NewInstance(field) => {
+            let l2 = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ]).split(l1[0]);
+            let fields: [(&mut TextInputState, String); 2] = [
+                (&mut data.name_input, t!("ui.game.instance_name").to_string()),
+                (&mut data.version_input, t!("ui.game.instance_version").to_string()),
+            ];
+            for (i, (state, title)) in fields.into_iter().enumerate() {
+                state.focus.set(i == field);
+                text_input(title, app_settings).render(l2[i], buf, state);
+            }
+        }
+        GameMode::AddRoot => {
+            data.root_input.focus.set(true);
+            text_input(t!("ui.game.game_root").to_string(), app_settings)
+                .render(l1[0], buf, &mut data.root_input);
+        }
+        GameMode::Import => {
+            let title = if data.import_link {
+                t!("ui.game.import_link")
+            } else {
+                t!("ui.game.import_copy")
+            };
+            data.import_input.focus.set(true);
+            text_input(title.to_string(), app_settings).render(l1[0], buf, &mut data.import_input);
+        }
+        GameMode::Export => {
+            data.export_input.focus.set(true);
+            text_input(t!("ui.game.export_path").to_string(), app_settings)
+                .render(l1[0], buf, &mut data.export_input);
+        }
+        GameMode::Clone => {
+            let title = if data.clone_minimal {
+                t!("ui.game.clone_minimal")
+            } else {
+                t!("ui.game.clone_full")
+            };
+            data.clone_input.focus.set(true);
+            text_input(title.to_string(), app_settings).render(l1[0], buf, &mut data.clone_input);
+        }
+        GameMode::Backup => {
+            data.backup_input.focus.set(true);
+            text_input(t!("ui.game.backup_path").to_string(), app_settings)
+                .render(l1[0], buf, &mut data.backup_input);
+        }
+        GameMode::Restore => {
+            let title = if data.restore_existing {
+                t!("ui.game.restore_existing")
+            } else {
+                t!("ui.game.restore_new")
+            };
+            data.restore_input.focus.set(true);
+            text_input(title.to_string(), app_settings).render(l1[0], buf, &mut data.restore_input);
+        }
+        GameMode::WriteBack => {}
+        GameMode::Search | GameMode::Browse => {
+            data.search_input.focus.set(data.mode == GameMode::Search);
+            text_input(t!("ui.game.search").to_string(), app_settings)
+                .render(l1[0], buf, &mut data.search_input);
+        }
+    }
+
+    let visible = data.visible_instances();
+    if data.instance_table.selected().is_none_or(|i| i >= visible.len()) {
+        data.instance_table.select(if visible.is_empty() { None } else { Some(0) });
+    }
+    let rows: Vec<Row> = visible
+        .iter()
+        .map(|&i| {
+            let instance = &data.instances[i];
+            Row::new([
+                instance.name.clone(),
+                instance_version(instance),
+                instance
+                    .last_played
+                    .and_then(|time| Local.timestamp_opt(time, 0).single())
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| t!("ui.game.never_played").to_string()),
+                format_playtime(instance.playtime),
+                instance_state(data, instance),
+            ])
+        })
+        .collect();
+    data.instance_table.focus.set(!data.focus_games);
+    Table::<RowSelection>::new()
+        .rows(rows)
+        .widths([
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Length(16),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ])
+        .header(Row::new([
+            t!("ui.game.instance_name").to_string(),
+            t!("ui.game.instance_version").to_string(),
+            t!("ui.game.last_played").to_string(),
+            t!("ui.game.playtime").to_string(),
+            t!("ui.game.instance_state").to_string(),
+        ]))
+        .styles(app_settings.theme.style(WidgetStyle::TABLE))
+        .block(Block::bordered()
+            .title(t!(
+                "ui.game.instances",
+                sort = t!(format!("ui.game.sort_{}", data.sort.get_name()))
+            ))
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l1[1], buf, &mut data.instance_table);
+
+    Paragraph::new(instance_status(data))
+        .style(app_settings.theme.style_style(Style::CONTAINER_BASE))
+        .wrap(Wrap { trim: true })
+        .block(Block::bordered()
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+        .render(l1[2], buf);
+}
+
+fn text_input(title: String, app_settings: &Settings) -> TextInput<'static> {
+    TextInput::new()
+        .styles(app_settings.theme.style(WidgetStyle::TEXT))
+        .block(Block::bordered()
+            .title(title)
+            .border_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG))
+            .title_style(app_settings.theme.style_style(Style::CONTAINER_BORDER_FG)),
+        )
+}
+
+/// 启动进度、实例文件操作的进度、操作结果或按键说明
+fn instance_status(data: &mut GameData) -> String {
+    let tasks = [
+        ("ui.game.importing", data.import_task.get_state().and_then(Status::to_progress)),
+        ("ui.game.exporting", data.export_task.get_state().and_then(Status::to_progress)),
+        ("ui.game.cloning", data.clone_task.get_state().and_then(Status::to_progress)),
+        ("ui.game.backing_up", data.backup_task.get_state().and_then(Status::to_progress)),
+        ("ui.game.restoring", data.restore_task.get_state().and_then(Status::to_progress)),
+    ];
+    for (key, progress) in tasks {
+        if let Some((done, total)) = progress {
+            return t!(key, done = done, total = total).to_string();
+        }
+    }
+    match data.launcher.get_state() {
+        Some(Status::Progress(step)) => {
+            let name = data
+                .launcher
+                .instance_id()
+                .and_then(|id| data.instances.iter().find(|i| i.get_id() == id))
+                .map(|instance| instance.name.clone())
+                .unwrap_or_default();
+            t!("ui.game.launching", name = name, step = launch_step(step)).to_string()
+        }
+        _ => match &data.message {
+            Some(message) => message.clone(),
+            None if data.mode != GameMode::Browse => t!("ui.game.input_hint").to_string(),
+            None => t!("ui.game.help").to_string(),
+        },
+    }
+}
+
+fn launch_step(step: LaunchStep) -> String {
+    match step {
+        LaunchStep::Account => t!("ui.game.step_account").to_string(),
+        LaunchStep::Version => t!("ui.game.step_version").to_string(),
+        LaunchStep::Files => t!("ui.game.step_files").to_string(),
+        LaunchStep::Java => t!("ui.game.step_java").to_string(),
+        LaunchStep::Runtime => t!("ui.game.step_runtime").to_string(),
+        LaunchStep::Natives => t!("ui.game.step_natives").to_string(),
+        LaunchStep::AuthlibInjector => t!("ui.game.step_authlib_injector").to_string(),
+    }
+}
+
+/// 版本和模组加载器
+fn instance_version(instance: &Instance) -> String {
+    match &instance.loader {
+        Some(loader) => format!("{} {} {}", instance.version, loader.kind.get_name(), loader.version),
+        None => instance.version.clone(),
+    }
+}
+
+/// 实例最近一次启动的状态
+fn instance_state(data: &GameData, instance: &Instance) -> String {
+    if data.launcher.instance_id() == Some(instance.get_id().as_str()) {
+        return t!("ui.game.preparing").to_string();
+    }
+    match data.instance_game(instance).map(|game| game.state) {
+        Some(GameState::Running) => t!("ui.game.running").to_string(),
+        Some(GameState::Exited { code: Some(0) }) => t!("ui.game.exited", code = 0).to_string(),
+        Some(GameState::Exited { code: Some(code) }) => t!("ui.game.crashed", code = code).to_string(),
+        Some(GameState::Exited { code: None }) => t!("ui.game.killed").to_string(),
+        None => String::new(),
+    }
+}
+
+/// 累计游戏时长，例如 12h05m
+fn format_playtime(secs: u64) -> String {
+    format!("{}h{:02}m", secs / 3600, secs / 60 % 60)
+}
+
+/// 崩溃原因和建议
+fn crash_diagnosis(analysis: &CrashAnalysis) -> String {
+    let mut text = match analysis.exit_code {
+        Some(code) => t!("ui.game.crash_exit_code", code = code).to_string(),
+        None => t!("ui.game.crash_killed").to_string(),
+    };
+    text.push('\n');
+    if let Some(report) = &analysis.report {
+        text.push_str(&t!("ui.game.crash_report", path = report.path.display()));
+        text.push('\n');
+        if !report.description.is_empty() {
+            text.push_str(&t!("ui.game.crash_description", description = report.description));
+            text.push('\n');
+        }
+        if let Some(exception) = report.stack_trace.first() {
+            text.push_str(exception.trim());
+            text.push('\n');
+        }
+        if !report.suspected_mods.is_empty() {
+            text.push_str(&t!("ui.game.crash_suspected_mods", mods = report.suspected_mods.join(", ")));
+            text.push('\n');
+        }
+    }
+    if analysis.causes.is_empty() {
+        text.push_str(&t!("ui.game.cause_unknown"));
+    }
+    for cause in &analysis.causes {
+        text.push_str("\n- ");
+        text.push_str(&crash_cause(cause));
+    }
+    text
+}
+
+fn crash_cause(cause: &CrashCause) -> String {
+    match cause {
+        CrashCause::WrongJavaVersion { required, current } => {
+            let unknown = t!("ui.game.unknown").to_string();
+            t!(
+                "ui.game.cause_java_version",
+                required = required.map(|v| v.to_string()).unwrap_or(unknown.clone()),
+                current = current.map(|v| v.to_string()).unwrap_or(unknown)
+            ).to_string()
+        }
+        CrashCause::MissingDependency { mod_id, dependency } => {
+            t!("ui.game.cause_missing_dependency", mod_id = mod_id, dependency = dependency).to_string()
+        }
+        CrashCause::DuplicateMod(mod_id) => t!("ui.game.cause_duplicate_mod", mod_id = mod_id).to_string(),
+        CrashCause::OutOfMemory => t!("ui.game.cause_out_of_memory").to_string(),
+        CrashCause::GraphicsDriver(detail) => t!("ui.game.cause_graphics_driver", detail = detail).to_string(),
+        CrashCause::MixinFailure { mod_id } => t!(
+            "ui.game.cause_mixin",
+            mod_id = mod_id.clone().unwrap_or(t!("ui.game.unknown").to_string())
+        ).to_string(),
+    }
+}
+
+fn game_item(game: &GameInfo) -> String {
+    let state = match &game.state {
+        GameState::Running => t!("ui.game.running").to_string(),
+        GameState::Exited { code: Some(code) } => t!("ui.game.exited", code = code).to_string(),
+        GameState::Exited { code: None } => t!("ui.game.killed").to_string(),
+    };
+    format!("{} [{}] {}", game.name, state, format_duration(game.duration))
+}
+
+/// [时间] [线程/等级] [logger]: 消息
+fn format_record(record: &LogRecord) -> String {
+    let mut line = String::new();
+    if let Some(time) = record
+        .timestamp
+        .and_then(|timestamp| Local.timestamp_millis_opt(timestamp).single())
+    {
+        line.push_str(&time.format("[%H:%M:%S] ").to_string());
+    }
+    if record.thread.is_empty() {
+        line.push_str(&format!("[{}] ", record.level));
+    } else {
+        line.push_str(&format!("[{}/{}] ", record.thread, record.level));
+    }
+    if !record.logger.is_empty() {
+        line.push_str(&format!("[{}]: ", record.logger));
+    }
+    line.push_str(&record.message);
+    if let Some(throwable) = &record.throwable {
+        line.push('\n');
+        line.push_str(throwable);
+    }
+    line
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
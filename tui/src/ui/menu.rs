@@ -18,7 +18,8 @@ pub fn menu_render(
     MenuLine::new()
         .item_parsed(menu_item(t!("ui.menu.game"), 0, &app_data.menu_selected).as_str())
         .item_parsed(menu_item(t!("ui.menu.download_install"), 1, &app_data.menu_selected).as_str())
-        .item_parsed(menu_item(t!("ui.menu.quit"), 2, &app_data.menu_selected).as_str())
+        .item_parsed(menu_item(t!("ui.menu.account_manage"), 2, &app_data.menu_selected).as_str())
+        .item_parsed(menu_item(t!("ui.menu.quit"), 3, &app_data.menu_selected).as_str())
         .styles(app_settings.theme.style(WidgetStyle::MENU))
         .focus_style(app_settings.theme.p.primary(3))
         .render(area, buf, &mut app_data.menu_selected);
@@ -0,0 +1,109 @@
+use std::sync::OnceLock;
+
+use mc_core::account::skin::{FACE_SIZE, PlayerFace};
+use ratatui_core::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+/// 上半块字符，前景色为上方像素，背景色为下方像素
+const UPPER_HALF: &str = "▀";
+
+/// 用半块字符绘制玩家头像，一个字符显示上下两个像素
+///
+/// 头像缩放到能放下的最大正方形并居中，终端不支持真彩色时使用 256 色
+pub struct PlayerHead<'a> {
+    face: &'a PlayerFace,
+}
+
+impl<'a> PlayerHead<'a> {
+    pub fn new(face: &'a PlayerFace) -> Self {
+        Self { face }
+    }
+
+    /// 显示 size 像素宽的头像时 (x, y) 处的颜色，缩小时取区域内的平均值
+    fn sample(&self, size: usize, x: usize, y: usize) -> Color {
+        let range = |i: usize| {
+            let start = i * FACE_SIZE / size;
+            start..((i + 1) * FACE_SIZE / size).max(start + 1)
+        };
+        let (mut sum, mut count) = ([0u32; 4], 0u32);
+        for sy in range(y) {
+            for sx in range(x) {
+                let pixel = self.face.pixel(sx, sy);
+                for (s, p) in sum.iter_mut().zip(pixel) {
+                    *s += p as u32;
+                }
+                count += 1;
+            }
+        }
+        let [r, g, b, a] = sum.map(|s| (s / count) as u8);
+        if a < 128 {
+            return Color::Reset;
+        }
+        if truecolor() {
+            Color::Rgb(r, g, b)
+        } else {
+            Color::Indexed(ansi256(r, g, b))
+        }
+    }
+}
+
+impl Widget for PlayerHead<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let size = (area.width as usize).min(area.height as usize * 2);
+        if size == 0 {
+            return;
+        }
+        let left = area.x + (area.width - size as u16) / 2;
+        let top = area.y + (area.height - size.div_ceil(2) as u16) / 2;
+        for y in (0..size).step_by(2) {
+            for x in 0..size {
+                let upper = self.sample(size, x, y);
+                let lower = if y + 1 < size {
+                    self.sample(size, x, y + 1)
+                } else {
+                    Color::Reset
+                };
+                if let Some(cell) = buf.cell_mut((left + x as u16, top + (y / 2) as u16)) {
+                    cell.set_symbol(UPPER_HALF).set_fg(upper).set_bg(lower);
+                }
+            }
+        }
+    }
+}
+
+/// 根据 COLORTERM 判断终端是否支持真彩色
+fn truecolor() -> bool {
+    static TRUECOLOR: OnceLock<bool> = OnceLock::new();
+    *TRUECOLOR.get_or_init(|| {
+        std::env::var("COLORTERM")
+            .is_ok_and(|value| value.eq_ignore_ascii_case("truecolor") || value.eq_ignore_ascii_case("24bit"))
+    })
+}
+
+/// 转换为 256 色调色板中最接近的颜色，从 6x6x6 色块和灰阶中选择
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (**level as i32 - c as i32).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap_or_default()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = [LEVELS[ri as usize], LEVELS[gi as usize], LEVELS[bi as usize]];
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = (average.saturating_sub(8) / 10).min(23) as u8;
+    let gray = 8 + gray_index * 10;
+
+    let distance = |[cr, cg, cb]: [u8; 3]| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance([gray, gray, gray]) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
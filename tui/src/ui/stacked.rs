@@ -1,13 +1,16 @@
-use mc_core::account::base::AccountBase;
+use mc_core::account::{AccountStatus, base::AccountBase};
 use rat_theme4::StyleName;
 use rat_theme4::palette::Colors;
 use rat_widget::statusline_stacked::{SLANT_BL_TR, StatusLineStacked};
 use ratatui_core::text::Span;
-use ratatui_core::{buffer::Buffer, layout::Rect, style::Style};
+use ratatui_core::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::Style};
 use ratatui_core::widgets::Widget;
 use rust_i18n::t;
 
-use crate::data::{AppData, Settings};
+use crate::{
+    data::{AppData, Settings},
+    ui::player_head::PlayerHead,
+};
 
 pub fn stacked_render(
     area: Rect,
@@ -23,6 +26,12 @@ pub fn stacked_render(
     let color_4 = pal.color(Colors::Cyan, 7);
 
     let (account_name, account_type) = get_current_account_name_and_type(app_settings);
+    let account_status = get_current_account_status(app_data, app_settings);
+
+    let l1 = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(2),
+    ]).split(area);
 
     StatusLineStacked::new()
         .style(app_settings.theme.style(Style::STATUS_BASE))
@@ -32,7 +41,8 @@ pub fn stacked_render(
             Span::from(SLANT_BL_TR).style(Style::new().fg(color_3).bg(color_4)),
         )
         .end(
-            "",
+            Span::from(account_status)
+                .style(Style::new().fg(pal.color(Colors::TextLight, 3)).bg(color_4)),
             Span::from(SLANT_BL_TR).style(Style::new().fg(color_4).bg(color_3)),
         )
         .end(
@@ -41,7 +51,16 @@ pub fn stacked_render(
             Span::from(SLANT_BL_TR).style(Style::new().fg(color_3).bg(color_4)),
         )
         .end("", Span::from(SLANT_BL_TR).style(Style::new().fg(color_4)))
-        .render(area, buf);
+        .render(l1[0], buf);
+
+    // 当前账号的头像
+    buf.set_style(l1[1], app_settings.theme.style_style(Style::STATUS_BASE));
+    let account_setting = &app_settings.account_setting;
+    if let Some(account) = &account_setting.current_account
+        && let Some(face) = app_data.account_data.heads.get(account, &account_setting.microsoft_endpoints)
+    {
+        PlayerHead::new(face).render(l1[1], buf);
+    }
 
 }
 
@@ -51,4 +70,15 @@ fn get_current_account_name_and_type(app_settings: &Settings) -> (String, String
     } else {
         (String::new(), t!("ui.stacked.no_account").to_string())
     }
+}
+
+fn get_current_account_status(app_data: &AppData, app_settings: &Settings) -> String {
+    let Some(account) = &app_settings.account_setting.current_account else {
+        return String::new();
+    };
+    match app_data.account_data.current_status(account) {
+        AccountStatus::Valid => t!("ui.stacked.valid").to_string(),
+        AccountStatus::Expiring => t!("ui.stacked.expiring").to_string(),
+        AccountStatus::NeedsLogin => t!("ui.stacked.needs_login").to_string(),
+    }
 }
\ No newline at end of file